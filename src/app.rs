@@ -1,24 +1,135 @@
-use std::{collections::HashMap};
 use std::error::Error;
-use std::ops::Drop;
 use color_eyre::Result;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::widgets::Clear;
 use ratatui::{
-    layout::Layout, prelude::{Constraint, Direction}, style::{Style, Stylize}, symbols, text::Line, widgets::{Block, List, ListItem, ListState, Paragraph, Tabs}, DefaultTerminal, Frame
+    layout::{Layout, Rect}, prelude::{Constraint, Direction}, style::{Style, Stylize}, symbols, text::{Line, Span}, widgets::{Block, List, ListItem, ListState, Paragraph, Tabs, Wrap}, DefaultTerminal, Frame
 };
 use tui_textarea::{TextArea};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
 
-use crate::leaderboard::Leaderboard;
-use crate::storage::{read_from_file, write_to_file};
+use crate::config::UiConfig;
+use crate::leaderboard::{ImportMode, Leaderboard, RankingMode};
+use crate::history::History;
+use crate::storage::{copy_leaderboard, read_from_file, write_to_file, Storage};
+use crate::trash::{self, expiry_cutoff, format_time};
+use crate::search::{find_matches, search_boards, MatchMode, SearchHit};
+use crate::snapshot::{self, Comparison, Snapshot};
+use crate::pairwise::{BinaryInsertion, MergeSort};
+use crate::composite::{self, AggregationMethod, Composite};
+use crate::rating::Outcome;
+use crate::tournament::{self, Tournament, TournamentFormat};
+use crate::matches::{self, format_standings, parse_participants, standings, Match, Participant, Standing};
 use crate::log::Log;
+use crate::node::{Details, Movement, Node, ID};
 
 #[derive(Debug, PartialEq, Eq)]
 enum AppState {
     Show,
     NewLDB,
-    NewEntry
+    NewEntry,
+    SetScore,
+    Import,
+    EditEntry,
+    RenameLDB,
+    CopyLDB,
+    Confirm,
+    Trash,
+    Search,
+    GlobalSearch,
+    Baseline,
+    Snapshots,
+    NewSnapshot,
+    Comparison,
+    PairwiseName,
+    Pairwise,
+    Opponent,
+    Matches,
+    EditMatch,
+    Tournaments,
+    NewTournament,
+    Tournament,
+    Placements,
+    NewComposite,
+    Help,
+}
+
+/// A ranking decided one "A or B?" question at a time.
+#[derive(Debug)]
+enum Pairwise {
+    /// Placing a new entry called `name` by binary insertion.
+    Insert { name: String, insertion: BinaryInsertion },
+    /// Reordering every entry of the current leaderboard.
+    Sort(MergeSort<ID>),
+}
+
+/// Keys of the leaderboard view and what they do, listed by the help overlay.
+const KEY_BINDINGS: [(&str, &str); 35] = [
+    ("←/→ h/l", "Previous/next leaderboard"),
+    ("↑/↓ k/j", "Previous/next entry"),
+    ("Ctrl+n", "New entry"),
+    ("c", "Add entry by comparing"),
+    ("C", "Sort by comparing"),
+    ("r", "Edit entry"),
+    ("s", "Set score"),
+    ("Ctrl+d", "Delete entry"),
+    ("Ctrl+y", "Pick up entry"),
+    ("p", "Put picked entry here"),
+    ("u", "Undo"),
+    ("Ctrl+r", "Redo"),
+    ("H", "Keep undo history between sessions"),
+    ("/", "Search"),
+    ("n/N", "Next/previous match"),
+    ("Ctrl+f", "Search all leaderboards"),
+    ("v", "Show/hide details"),
+    ("B", "Movement since"),
+    ("m", "Ranking mode or rating system"),
+    ("t", "Tie rule"),
+    ("g", "Record game"),
+    ("M", "Matches"),
+    ("O", "Tournaments"),
+    ("S", "Snapshots"),
+    ("T", "Trash"),
+    ("Ctrl+l", "New leaderboard"),
+    ("A", "New composite leaderboard"),
+    ("R", "Rename leaderboard"),
+    ("D", "Duplicate leaderboard"),
+    ("Ctrl+x", "Delete leaderboard"),
+    ("e", "Export CSV"),
+    ("i", "Import CSV"),
+    ("Esc", "Back"),
+    ("?", "This help"),
+    ("Ctrl+c", "Quit"),
+];
+
+/// What the movement arrows in the leaderboard view compare against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Baseline {
+    /// The ranks when the leaderboard was last opened in an earlier session.
+    LastVisit,
+    /// The ranks at this unix time.
+    Since(i64),
+    Off,
+}
+
+/// Something that can be restored from the trash view.
+#[derive(Debug, Clone, Copy)]
+enum TrashItem {
+    /// The trashed leaderboard at this position of the store's trash.
+    Board(usize),
+    /// A deleted entry of the current leaderboard.
+    Entry(ID),
+}
+
+/// A deletion waiting for the user to confirm it.
+#[derive(Debug, Clone, Copy)]
+enum PendingDelete {
+    /// The leaderboard at this index.
+    Board(usize),
+    /// The entry at this position of the current leaderboard.
+    Entry(usize),
 }
 
 #[derive(Debug)]
@@ -27,6 +138,38 @@ enum EntryFocus {
     Rank,
 }
 
+/// The field being edited in the entry editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditField {
+    Name,
+    Score,
+    Tags,
+    Notes,
+    Attributes,
+}
+
+impl EditField {
+    fn next(&self) -> EditField {
+        match self {
+            EditField::Name => EditField::Score,
+            EditField::Score => EditField::Tags,
+            EditField::Tags => EditField::Notes,
+            EditField::Notes => EditField::Attributes,
+            EditField::Attributes => EditField::Name,
+        }
+    }
+
+    fn previous(&self) -> EditField {
+        match self {
+            EditField::Name => EditField::Attributes,
+            EditField::Score => EditField::Name,
+            EditField::Tags => EditField::Score,
+            EditField::Notes => EditField::Tags,
+            EditField::Attributes => EditField::Notes,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct App <'a>{
     leaderboard_names: Vec<String>,
@@ -40,68 +183,210 @@ pub struct App <'a>{
     entry_name: String,
     entry_rank_input: TextArea<'a>,
     entry_rank: usize,
+    edited_entry: Option<ID>,
+    edit_field: EditField,
+    entry_tags_input: TextArea<'a>,
+    entry_notes_input: TextArea<'a>,
+    entry_attributes_input: TextArea<'a>,
+    pending_delete: Option<PendingDelete>,
+    confirm_input: TextArea<'a>,
+    trash_selected: usize,
+    /// Everything in the trash with a description, read when the trash view
+    /// opens and after each restore or purge rather than for every frame.
+    trash_items: Vec<(TrashItem, String)>,
+    search_input: TextArea<'a>,
+    search_query: String,
+    search_mode: MatchMode,
+    search_origin: usize,
+    global_results: Vec<SearchHit>,
+    global_selected: usize,
+    show_details: bool,
+    baseline: Baseline,
+    baseline_input: TextArea<'a>,
+    /// Undo histories of the leaderboards opened this session, by name, so
+    /// switching away from a leaderboard and back keeps its history.
+    histories: HashMap<String, History>,
+    /// When each leaderboard opened this session was opened before, if ever.
+    last_visits: HashMap<String, Option<i64>>,
+    snapshot_selected: usize,
+    snapshot_marked: Option<String>,
+    /// Snapshots of the leaderboard named first, read once for the
+    /// snapshots view rather than for every frame. Dropped when a snapshot
+    /// is taken or deleted.
+    snapshots: Option<(String, Vec<Snapshot>)>,
+    snapshot_input: TextArea<'a>,
+    comparison: String,
+    pairwise: Option<Pairwise>,
+    /// The entry whose game is being recorded while its opponent is picked.
+    game_player: Option<ID>,
+    match_selected: usize,
+    /// Matches of the leaderboard named first, read once for the matches
+    /// view rather than for every frame. Dropped when a match is saved or
+    /// removed.
+    matches: Option<(String, Vec<Match>)>,
+    /// The match being edited, `None` while a new one is entered.
+    edited_match: Option<usize>,
+    match_results_input: TextArea<'a>,
+    match_notes_input: TextArea<'a>,
+    /// Whether the notes, rather than the results, of a match are edited.
+    editing_match_notes: bool,
+    tournament_selected: usize,
+    /// Tournaments of the leaderboard named first, read once for the
+    /// tournament views rather than for every frame. Dropped when a
+    /// tournament is created, changed or deleted.
+    tournaments: Option<(String, Vec<Tournament>)>,
+    /// Name of the tournament shown in the tournament view.
+    open_tournament: Option<String>,
+    fixture_selected: usize,
+    /// Name of a new tournament, or the leaderboard placements are written to.
+    tournament_input: TextArea<'a>,
+    tournament_format: TournamentFormat,
+    /// Comma separated names of the leaderboards a new composite combines.
+    composite_sources_input: TextArea<'a>,
+    composite_method: AggregationMethod,
+    /// Whether the sources, rather than the name, of a new composite are edited.
+    editing_composite_sources: bool,
     ldb_name_input: TextArea<'a>,
     ldb_name: String,
+    score_input: TextArea<'a>,
+    import_path_input: TextArea<'a>,
+    import_mode: ImportMode,
+    message: String,
     focus: EntryFocus,
     logger: Log,
-    list_state: ListState
+    list_state: ListState,
+    storage: Rc<dyn Storage>,
+    /// Where CSV exports are written, the working directory unless set with
+    /// [`App::with_export_dir`].
+    export_dir: PathBuf,
+    ui: UiConfig
 }
 
-impl <'a> App <'_> {
+impl App <'_> {
 
-    fn create_folder_and_main_json() -> Result<(), Box<dyn Error>> {
-        std::fs::create_dir("Leaderboards")?;
-        write_to_file("{\"leaderboards\":[]}", "Leaderboards/Leaderboards.json")?;
-        //write_to_file("{\"name\":\"Games\",\"entries\":[],\"next_id\":33}", "Leaderboards/First_Leaderboard.json")?;
-        Ok(())
-    }
-    
-    pub fn new(mut log: Log) -> Result<Self, Box<dyn Error>> {
-        if !Path::new("Leaderboards").exists(){
-            log.write("Leaderboards directory exists");
-            App::create_folder_and_main_json()?;
-        }
-        let json_str = read_from_file("Leaderboards/Leaderboards.json")?;
-        let hmap: HashMap<String, Vec<String>> = serde_json::from_str(&json_str)?;
-        let mut ldb_vec: Vec<String> = Vec::new();
-        if let Some(ldbs) = hmap.get("leaderboards") {
-            for ldb in ldbs {
-                ldb_vec.push(ldb.to_string());
-            }
-        }
-        let lb: Option<Leaderboard>;
-        if ldb_vec.is_empty() {
-            lb = None;
-        } else {
-            lb = Some(Leaderboard::open_leaderboard(&ldb_vec[0])?);
+    /// An App showing the first leaderboard, with the trash emptied of
+    /// whatever expired.
+    pub fn new(log: Log, storage: Rc<dyn Storage>, ui: UiConfig) -> Result<Self, Box<dyn Error>> {
+        let mut app = App::headless(log, storage, ui)?;
+        if !app.leaderboard_names.is_empty() {
+            app.current_leaderboard = Some(app.open_leaderboard(0)?);
         }
+        app.expire_trash();
+        Ok(app)
+    }
+
+    pub fn with_export_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.export_dir = dir.into();
+        self
+    }
+
+    /// An App with no leaderboard open that leaves the trash alone, for
+    /// running single commands. It only reads the index until a leaderboard
+    /// is selected or viewed.
+    pub fn headless(log: Log, storage: Rc<dyn Storage>, ui: UiConfig) -> Result<Self, Box<dyn Error>> {
+        let ldb_vec = storage.read_index()?;
         Ok(App {
             leaderboard_names: ldb_vec,
             running: true,
             current_leaderboard_index: 0,
-            current_leaderboard: lb,
+            current_leaderboard: None,
             current_entry: 0,
             yanked_entry: None,
             state: AppState::Show,
             entry_name_input: TextArea::default(),
             entry_rank_input: TextArea::default(),
             ldb_name_input: TextArea::default(),
+            score_input: TextArea::default(),
+            import_path_input: TextArea::default(),
+            import_mode: ImportMode::Merge,
+            message: String::new(),
             entry_name: String::new(),
             entry_rank: 100,
+            edited_entry: None,
+            edit_field: EditField::Name,
+            entry_tags_input: TextArea::default(),
+            entry_notes_input: TextArea::default(),
+            entry_attributes_input: TextArea::default(),
+            pending_delete: None,
+            confirm_input: TextArea::default(),
+            trash_selected: 0,
+            trash_items: Vec::new(),
+            search_input: TextArea::default(),
+            search_query: String::new(),
+            search_mode: ui.search_mode,
+            search_origin: 0,
+            global_results: Vec::new(),
+            global_selected: 0,
+            show_details: !ui.hide_details,
+            baseline: Baseline::LastVisit,
+            baseline_input: TextArea::default(),
+            histories: HashMap::new(),
+            last_visits: HashMap::new(),
+            snapshot_selected: 0,
+            snapshot_marked: None,
+            snapshots: None,
+            snapshot_input: TextArea::default(),
+            comparison: String::new(),
+            pairwise: None,
+            game_player: None,
+            match_selected: 0,
+            matches: None,
+            edited_match: None,
+            match_results_input: TextArea::default(),
+            match_notes_input: TextArea::default(),
+            editing_match_notes: false,
+            tournament_selected: 0,
+            tournaments: None,
+            open_tournament: None,
+            fixture_selected: 0,
+            tournament_input: TextArea::default(),
+            tournament_format: TournamentFormat::default(),
+            composite_sources_input: TextArea::default(),
+            composite_method: AggregationMethod::default(),
+            editing_composite_sources: false,
             focus: EntryFocus::Name,
             ldb_name: String::new(),
             logger: log,
-            list_state: ListState::default()
+            list_state: ListState::default(),
+            storage,
+            export_dir: PathBuf::new(),
+            ui
         })
     }
 
+    /// Purges whatever has been in the trash longer than the configured
+    /// number of days.
+    fn expire_trash(&mut self) {
+        let Some(days) = self.ui.trash_days else { return };
+        let cutoff = expiry_cutoff(days);
+        if let Err(e) = trash::purge_expired(self.storage.as_ref(), cutoff) {
+            self.logger.write(format!("Unable to empty the trash: {}", e));
+        }
+        if let Some(ldb) = &mut self.current_leaderboard
+            && let Err(e) = ldb.purge_entries_deleted_before(cutoff) {
+            self.logger.write(format!("Unable to empty the trash of {}: {}", ldb.name(), e));
+        }
+    }
+
+    pub fn leaderboard_names(&self) -> &[String] {
+        &self.leaderboard_names
+    }
+
+    pub fn find_leaderboard(&self, name: &str) -> Option<usize> {
+        self.leaderboard_names.iter().position(|n| n == name)
+    }
+
     pub fn new_leaderboard(&mut self, name: &str) -> Result<Leaderboard, Box<dyn Error>> {
         if self.leaderboard_names.contains(&name.to_string()) {
             return Err(format!("Leaderboard named {} already exists!", name).into());
         }
-        let new_lb = Leaderboard::new(name);
+        let mut new_lb = Leaderboard::new(name, self.storage.clone())
+            .with_ranking(self.ui.ranking_mode, self.ui.tie_rule)
+            .with_rating_system(self.ui.rating_system);
+        new_lb.set_keep_history(self.ui.keep_history)?;
         new_lb.save_leaderboard()?;
         self.leaderboard_names.push(name.to_string());
+        self.storage.write_index(&self.leaderboard_names)?;
         Ok(new_lb)
     }
 
@@ -109,41 +394,685 @@ impl <'a> App <'_> {
         if index >= self.leaderboard_names.len() {
             return Err(format!("No leaderboard at index {}", index).into());
         }
-        let lb = Leaderboard::open_leaderboard(&self.leaderboard_names[index])?;
+        self.stash_history();
+        let mut lb = Leaderboard::open_leaderboard(&self.leaderboard_names[index], self.storage.clone())?;
+        if let Some(history) = self.histories.get(lb.name()) {
+            lb.set_history(history.clone());
+        }
+        if let Some(days) = self.ui.trash_days {
+            lb.purge_entries_deleted_before(expiry_cutoff(days))?;
+        }
+        self.refresh_composite(&mut lb)?;
         Ok(lb)
     }
 
+    /// Keeps the undo history of the open leaderboard for when it is opened
+    /// again later in the session.
+    fn stash_history(&mut self) {
+        if let Some(current) = &self.current_leaderboard {
+            self.histories.insert(current.name().to_string(), current.history().clone());
+        }
+    }
+
+    /// Makes a copy of the leaderboard at `index` the current one without
+    /// attaching it to the store, so looking at it never writes anything.
+    pub fn view_leaderboard(&mut self, index: usize) -> Result<&Leaderboard, Box<dyn Error>> {
+        let name = self.leaderboard_names.get(index)
+            .ok_or_else(|| format!("No leaderboard at index {}", index))?;
+        let mut lb = self.storage.read_leaderboard(name)?;
+        self.refresh_composite(&mut lb)?;
+        self.current_leaderboard_index = index;
+        self.current_entry = 0;
+        Ok(self.current_leaderboard.insert(lb))
+    }
+
+    /// Recombines `lb` from its sources if it is a composite leaderboard.
+    fn refresh_composite(&mut self, lb: &mut Leaderboard) -> Result<(), Box<dyn Error>> {
+        if lb.composite().is_none() {
+            return Ok(());
+        }
+        let missing = composite::refresh(self.storage.as_ref(), lb, &self.leaderboard_names)?;
+        if !missing.is_empty() {
+            self.message = format!("{} combines leaderboards that no longer exist: {}", lb.name(), missing.join(", "));
+            self.logger.write(self.message.clone());
+        }
+        Ok(())
+    }
+
+    /// Creates a leaderboard called `name` that combines the rankings of
+    /// the leaderboards `sources` with `method`.
+    pub fn new_composite(&mut self, name: &str, sources: &[String], method: AggregationMethod) -> Result<Leaderboard, Box<dyn Error>> {
+        let name = self.check_new_name(name)?;
+        let mut unique: Vec<String> = Vec::new();
+        for source in sources.iter().map(|source| source.trim()).filter(|source| !source.is_empty()) {
+            if self.find_leaderboard(source).is_none() {
+                return Err(format!("There is no leaderboard named {}", source).into());
+            }
+            if !unique.iter().any(|other| other == source) {
+                unique.push(source.to_string());
+            }
+        }
+        if unique.len() < 2 {
+            return Err("A composite leaderboard combines at least two leaderboards".into());
+        }
+        // Combined before the leaderboard is created, so failing to read a
+        // source leaves nothing behind.
+        let composite = Composite { sources: unique, method };
+        let (combined, _) = composite::combine_sources(self.storage.as_ref(), &composite, &self.leaderboard_names)?;
+        let mut ldb = self.new_leaderboard(name)?.with_composite(composite);
+        ldb.set_combined_entries(combined)?;
+        self.logger.write(format!("CREATE COMPOSITE {} Succeeded!", name));
+        Ok(ldb)
+    }
+
+    /// Points the composite leaderboards combining `old` to its new name.
+    fn rename_source(&mut self, old: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        for board in self.leaderboard_names.clone() {
+            if let Some(ldb) = &mut self.current_leaderboard
+                && ldb.name() == board {
+                if ldb.rename_source(old, name) {
+                    ldb.save_leaderboard()?;
+                }
+                continue;
+            }
+            let mut ldb = self.storage.read_leaderboard(&board)?;
+            if ldb.rename_source(old, name) {
+                self.storage.write_leaderboard(&ldb)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the leaderboard at `index` and makes it the current one.
+    pub fn select_leaderboard(&mut self, index: usize) -> Result<&mut Leaderboard, Box<dyn Error>> {
+        let ldb = self.open_leaderboard(index)?;
+        self.current_leaderboard_index = index;
+        self.current_entry = 0;
+        Ok(self.current_leaderboard.insert(ldb))
+    }
+
     pub fn remove_leaderboard(&mut self, index: usize) -> Result<(), Box<dyn Error>>{
         if index >= self.leaderboard_names.len() {
             return Err(format!("No leaderboard at that index {}", index).into());
         }
-        let name = &self.leaderboard_names[index];
-        let mut file_name = "Leaderboards/".to_owned();
-        file_name.push_str(name);
-        file_name.push_str(".json");
-        if !std::path::Path::new(&file_name).exists() {
-            self.logger.write("File does not exist!");
-        } else {
-            self.logger.write("File exists, attempting deletion");
+        // Leaderboards save themselves when dropped, so let go of the open one
+        // before its file is deleted.
+        self.stash_history();
+        let was_open = self.current_leaderboard.take().is_some();
+        self.matches = None;
+        if let Err(e) = trash::trash_leaderboard(self.storage.as_ref(), &self.leaderboard_names[index]) {
+            // The leaderboard stays listed, so it can still be reached.
+            self.logger.write(format!("REMOVE LDB {} Failed: {}", &self.leaderboard_names[index], e));
+            if was_open && let Ok(ldb) = self.open_leaderboard(self.current_leaderboard_index) {
+                self.current_leaderboard = Some(ldb);
+            }
+            return Err(e);
+        }
+        self.logger.write(format!("REMOVE LDB {} Succeeded!", &self.leaderboard_names[index]));
+        self.histories.remove(&self.leaderboard_names[index]);
+        self.leaderboard_names.remove(index);
+        self.storage.write_index(&self.leaderboard_names)?;
+        self.current_leaderboard_index = 0;
+        self.current_entry = 0;
+        if !self.leaderboard_names.is_empty() {
+            match self.open_leaderboard(0) {
+                Ok(ldb) => self.current_leaderboard = Some(ldb),
+                Err(e) => self.logger.write(format!("OPEN LDB {} Failed: {}", &self.leaderboard_names[0], e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `name` can be used for a new leaderboard.
+    fn check_new_name<'n>(&self, name: &'n str) -> Result<&'n str, Box<dyn Error>> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Leaderboard name must not be empty!".into());
+        }
+        if self.find_leaderboard(name).is_some() {
+            return Err(format!("Leaderboard named {} already exists!", name).into());
+        }
+        Ok(name)
+    }
+
+    /// Renames the leaderboard at `index`, moving it and its attachments in
+    /// the store and updating the index.
+    pub fn rename_leaderboard(&mut self, index: usize, name: &str) -> Result<(), Box<dyn Error>> {
+        let name = self.check_new_name(name)?;
+        let old = self.leaderboard_names.get(index)
+            .ok_or_else(|| format!("No leaderboard at index {}", index))?
+            .clone();
+        // The open board saves itself when dropped, so it has to be written
+        // under its old name before it is renamed, not after.
+        let closed = (self.current_leaderboard_index == index).then(|| self.current_leaderboard.take()).flatten();
+        let reopen = closed.is_some();
+        if let Some(ldb) = closed {
+            self.histories.insert(old.clone(), ldb.history().clone());
+        }
+        let result = self.storage.rename_leaderboard(&old, name);
+        if result.is_ok() {
+            self.matches = None;
+            if let Some(history) = self.histories.remove(&old) {
+                self.histories.insert(name.to_string(), history);
+            }
+            self.leaderboard_names[index] = name.to_string();
+            self.storage.write_index(&self.leaderboard_names)?;
+            self.logger.write(format!("RENAME LDB {} to {} Succeeded!", old, name));
+            self.rename_source(&old, name)?;
+        }
+        if reopen {
+            self.current_leaderboard = Some(self.open_leaderboard(index)?);
+        }
+        result
+    }
+
+    /// Copies the leaderboard at `index` to a new leaderboard called `name`,
+    /// placed right after it. Returns the index of the copy.
+    pub fn duplicate_leaderboard(&mut self, index: usize, name: &str) -> Result<usize, Box<dyn Error>> {
+        let name = self.check_new_name(name)?;
+        let old = self.leaderboard_names.get(index)
+            .ok_or_else(|| format!("No leaderboard at index {}", index))?
+            .clone();
+        if self.current_leaderboard_index == index
+            && let Some(ldb) = &self.current_leaderboard {
+            ldb.save_leaderboard()?;
+        }
+        copy_leaderboard(self.storage.as_ref(), &old, name)?;
+        self.leaderboard_names.insert(index + 1, name.to_string());
+        if self.current_leaderboard_index > index {
+            self.current_leaderboard_index += 1;
+        }
+        self.storage.write_index(&self.leaderboard_names)?;
+        self.logger.write(format!("DUPLICATE LDB {} as {} Succeeded!", old, name));
+        Ok(index + 1)
+    }
+
+    /// Reads everything in the trash for the trash view: trashed
+    /// leaderboards, then the deleted entries of the current leaderboard.
+    fn load_trash_items(&mut self) {
+        let trashed = self.storage.read_trash().unwrap_or_else(|e| {
+            self.message = format!("Unable to read the trash: {}", e);
+            Vec::new()
+        });
+        let mut items = Vec::new();
+        for (position, trashed) in trashed.iter().enumerate() {
+            items.push((TrashItem::Board(position),
+                format!("Leaderboard {} (deleted {})", trashed.name, format_time(trashed.deleted_at))));
+        }
+        if let Some(ldb) = &self.current_leaderboard {
+            for tombstone in ldb.deleted_entries() {
+                items.push((TrashItem::Entry(tombstone.node.id),
+                    format!("Entry {} from rank {} (deleted {})",
+                        tombstone.node.name, tombstone.node.rank, format_time(tombstone.deleted_at))));
+            }
+        }
+        self.trash_items = items;
+        self.clamp_trash_selection();
+    }
+
+    /// Restores the selected trash item, a leaderboard goes to the end of
+    /// the index.
+    fn restore_from_trash(&mut self) {
+        let Some((item, _)) = self.trash_items.get(self.trash_selected).cloned() else { return };
+        let result = match item {
+            TrashItem::Board(position) => {
+                let name = self.storage.read_trash().ok()
+                    .and_then(|trash| trash.get(position).map(|trashed| trashed.name.clone()))
+                    .unwrap_or_default();
+                self.check_new_name(&name).map(|_| ())
+                    .and_then(|()| trash::restore_leaderboard(self.storage.as_ref(), position))
+                    .and_then(|name| {
+                        self.leaderboard_names.push(name.clone());
+                        self.storage.write_index(&self.leaderboard_names)?;
+                        if self.current_leaderboard.is_none() {
+                            self.select_leaderboard(self.leaderboard_names.len() - 1)?;
+                        }
+                        Ok(format!("Restored leaderboard {}", name))
+                    })
+            },
+            TrashItem::Entry(id) => match &mut self.current_leaderboard {
+                Some(ldb) => ldb.restore_entry(id)
+                    .map(|()| format!("Restored entry to {}", ldb.name()))
+                    .map_err(|e| e.into()),
+                None => return,
+            },
+        };
+        self.message = result.unwrap_or_else(|e| format!("Unable to restore: {}", e));
+        self.load_trash_items();
+    }
+
+    /// Deletes the selected trash item for good.
+    fn purge_from_trash(&mut self) {
+        let Some((item, _)) = self.trash_items.get(self.trash_selected).cloned() else { return };
+        let result = match item {
+            TrashItem::Board(position) => trash::purge_leaderboard(self.storage.as_ref(), position),
+            TrashItem::Entry(id) => match &mut self.current_leaderboard {
+                Some(ldb) => ldb.purge_entry(id),
+                None => return,
+            },
+        };
+        self.message = match result {
+            Ok(()) => "Deleted for good".to_string(),
+            Err(e) => format!("Unable to purge: {}", e),
+        };
+        self.load_trash_items();
+    }
+
+    fn clamp_trash_selection(&mut self) {
+        self.trash_selected = self.trash_selected.min(self.trash_items.len().saturating_sub(1));
+    }
+
+    /// Searches every leaderboard for entries matching `query`.
+    pub fn search_all(&self, query: &str, mode: MatchMode) -> Result<Vec<SearchHit>, Box<dyn Error>> {
+        search_boards(self.storage.as_ref(), &self.leaderboard_names, query, mode)
+    }
+
+    /// Opens the leaderboard of a search result and selects its entry.
+    fn jump_to_hit(&mut self, hit: &SearchHit) -> Result<(), Box<dyn Error>> {
+        let index = self.find_leaderboard(&hit.board)
+            .ok_or_else(|| format!("No leaderboard named {}", hit.board))?;
+        let ldb = self.select_leaderboard(index)?;
+        let position = ldb.entries().iter().position(|node| node.id == hit.id)
+            .ok_or_else(|| format!("{} is no longer on {}", hit.name, hit.board))?;
+        self.current_entry = position;
+        self.list_state.select(Some(position));
+        Ok(())
+    }
+
+    fn update_global_results(&mut self) {
+        let query = self.search_input.lines().first().cloned().unwrap_or_default();
+        match self.search_all(&query, self.search_mode) {
+            Ok(hits) => self.global_results = hits,
+            Err(e) => self.message = format!("Unable to search: {}", e),
+        }
+        self.global_selected = 0;
+    }
+
+    /// Remembers when the current leaderboard was last opened, the first
+    /// time it is shown this session, and stores the time of this visit.
+    fn note_visit(&mut self) {
+        let Some(name) = self.leaderboard_names.get(self.current_leaderboard_index).cloned() else { return };
+        if self.current_leaderboard.is_none() || self.last_visits.contains_key(&name) {
+            return;
+        }
+        let previous = self.storage.read_attachment(&name, "visits").ok().flatten()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        self.last_visits.insert(name.clone(), previous);
+        if let Err(e) = self.storage.write_attachment(&name, "visits", &trash::now().to_string()) {
+            self.logger.write(format!("Unable to save the visit to {}: {}", name, e));
+        }
+    }
+
+    /// The unix time movement is shown relative to.
+    fn baseline_time(&self) -> Option<i64> {
+        match self.baseline {
+            Baseline::LastVisit => self.leaderboard_names.get(self.current_leaderboard_index)
+                .and_then(|name| self.last_visits.get(name).copied().flatten()),
+            Baseline::Since(at) => Some(at),
+            Baseline::Off => None,
+        }
+    }
+
+    /// Sets the baseline from the prompt: a date, "off", or nothing for the
+    /// last visit.
+    fn set_baseline(&mut self, text: &str) -> Result<(), String> {
+        self.baseline = match text.trim() {
+            "" => Baseline::LastVisit,
+            "off" => Baseline::Off,
+            date => {
+                let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("{} is not a date like 2024-12-31", date))?;
+                let at = date.and_hms_opt(0, 0, 0)
+                    .and_then(|time| time.and_local_timezone(chrono::Local).earliest())
+                    .ok_or(format!("{} is not a valid local date", date))?;
+                Baseline::Since(at.timestamp())
+            },
+        };
+        Ok(())
+    }
+
+    fn current(&self) -> Result<&Leaderboard, Box<dyn Error>> {
+        self.current_leaderboard.as_ref().ok_or_else(|| "No leaderboard is open".into())
+    }
+
+    /// Reads the snapshots of the current leaderboard, unless they were read
+    /// already, for [`App::snapshots`].
+    pub fn load_snapshots(&mut self) -> Result<(), Box<dyn Error>> {
+        let name = self.current()?.name();
+        if self.snapshots.as_ref().is_some_and(|(board, _)| board == name) {
+            return Ok(());
+        }
+        let snapshots = snapshot::read_snapshots(self.storage.as_ref(), name)?;
+        self.snapshots = Some((name.to_string(), snapshots));
+        Ok(())
+    }
+
+    /// Snapshots of the current leaderboard, oldest first, as read by
+    /// [`App::load_snapshots`].
+    pub fn snapshots(&self) -> &[Snapshot] {
+        match (&self.snapshots, &self.current_leaderboard) {
+            (Some((board, snapshots)), Some(ldb)) if board == ldb.name() => snapshots,
+            _ => &[],
+        }
+    }
+
+    pub fn take_snapshot(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.snapshots = None;
+        snapshot::take_snapshot(self.storage.as_ref(), self.current()?, name)
+    }
+
+    pub fn remove_snapshot(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.snapshots = None;
+        snapshot::remove_snapshot(self.storage.as_ref(), self.current()?.name(), name)
+    }
+
+    /// Reads the snapshots of the current leaderboard for the snapshots
+    /// view.
+    fn show_snapshots(&mut self) {
+        if let Err(e) = self.load_snapshots() {
+            self.message = format!("Unable to read snapshots: {}", e);
+        }
+    }
+
+    /// Reads the matches of the current leaderboard, unless they were read
+    /// already, for [`App::matches`].
+    pub fn load_matches(&mut self) -> Result<(), Box<dyn Error>> {
+        let name = self.current()?.name();
+        if self.matches.as_ref().is_some_and(|(board, _)| board == name) {
+            return Ok(());
         }
-        match std::fs::remove_file(file_name) {
-            Ok(()) => self.logger.write(format!("REMOVE LDB file {}.json Succeeded!", &self.leaderboard_names[index])),
-            Err(e) => self.logger.write(format!("REMOVE LDB file {}.json Failed: {}", &self.leaderboard_names[index], e))
+        let matches = matches::read_matches(self.storage.as_ref(), name)?;
+        self.matches = Some((name.to_string(), matches));
+        Ok(())
+    }
+
+    /// Matches of the current leaderboard, oldest first, as read by
+    /// [`App::load_matches`].
+    pub fn matches(&self) -> &[Match] {
+        match (&self.matches, &self.current_leaderboard) {
+            (Some((board, matches)), Some(ldb)) if board == ldb.name() => matches,
+            _ => &[],
+        }
+    }
+
+    pub fn add_match(&mut self, participants: Vec<Participant>, notes: &str) -> Result<Match, Box<dyn Error>> {
+        self.matches = None;
+        matches::add_match(self.storage.as_ref(), self.current()?, participants, notes)
+    }
+
+    pub fn update_match(&mut self, id: usize, participants: Vec<Participant>, notes: &str) -> Result<(), Box<dyn Error>> {
+        self.matches = None;
+        matches::update_match(self.storage.as_ref(), self.current()?, id, participants, notes)
+    }
+
+    pub fn remove_match(&mut self, id: usize) -> Result<(), Box<dyn Error>> {
+        self.matches = None;
+        matches::remove_match(self.storage.as_ref(), self.current()?.name(), id)
+    }
+
+    /// The standings of the current leaderboard, computed from its matches.
+    pub fn standings(&mut self) -> Result<Vec<Standing>, Box<dyn Error>> {
+        self.load_matches()?;
+        Ok(standings(self.current()?.entries(), self.matches()))
+    }
+
+    /// Reads the matches of the current leaderboard for the matches view.
+    fn show_matches(&mut self) {
+        if let Err(e) = self.load_matches() {
+            self.message = format!("Unable to read matches: {}", e);
+        }
+    }
+
+    /// Describes a match of the current leaderboard with the names of its
+    /// participants.
+    pub fn describe_match(&self, m: &Match) -> String {
+        let ldb = self.current_leaderboard.as_ref();
+        m.describe(|id| ldb.and_then(|ldb| ldb.entry_name(id)).map_or(format!("#{}", id), str::to_string))
+    }
+
+    /// Opens the match editor, prefilled with the match `id` or empty for a
+    /// new match.
+    fn edit_match(&mut self, id: Option<usize>) {
+        let existing = id.and_then(|id| self.matches().iter().find(|m| m.id == id).cloned());
+        let (results, notes) = match (&existing, &self.current_leaderboard) {
+            (Some(m), Some(ldb)) => (
+                m.participants.iter()
+                    .map(|participant| format!("{}: {}", ldb.entry_name(participant.id).unwrap_or_default(), participant.score))
+                    .collect(),
+                m.notes.lines().map(str::to_string).collect(),
+            ),
+            _ => (Vec::new(), Vec::new()),
         };
-        match self.open_leaderboard(0) {
-            Ok(ldb) => self.current_leaderboard = Some(ldb),
+        self.edited_match = existing.map(|m| m.id);
+        self.match_results_input = TextArea::new(results);
+        self.match_notes_input = TextArea::new(notes);
+        self.editing_match_notes = false;
+        self.state = AppState::EditMatch;
+    }
+
+    fn save_edited_match(&mut self) {
+        let Some(ldb) = &self.current_leaderboard else { return };
+        let participants = match parse_participants(ldb, self.match_results_input.lines().iter().map(String::as_str)) {
+            Ok(participants) => participants,
             Err(e) => {
-                self.current_leaderboard = None;
-                self.logger.write(format!("OPEN LDB {} Failed: {}", &self.leaderboard_names[index], e))
-            }
+                self.message = e;
+                return;
+            },
+        };
+        let notes = self.match_notes_input.lines().join("\n");
+        let saved = match self.edited_match {
+            Some(id) => self.update_match(id, participants, &notes),
+            None => self.add_match(participants, &notes).map(|_| ()),
+        };
+        match saved {
+            Ok(()) => {
+                self.message = "Saved match, standings updated".to_string();
+                self.show_matches();
+                if self.edited_match.is_none() {
+                    self.match_selected = self.matches().len().saturating_sub(1);
+                }
+                self.close_match_editor();
+            },
+            Err(e) => self.message = e.to_string(),
         }
-        self.leaderboard_names.remove(index);
+    }
+
+    fn close_match_editor(&mut self) {
+        self.edited_match = None;
+        self.match_results_input = TextArea::default();
+        self.match_notes_input = TextArea::default();
+        self.state = AppState::Matches;
+    }
+
+    fn remove_selected_match(&mut self) {
+        let count = self.matches().len();
+        let Some(id) = self.matches().get(self.match_selected).map(|m| m.id) else { return };
+        match self.remove_match(id) {
+            Ok(()) => self.message = format!("Deleted match {}, standings updated", id),
+            Err(e) => self.message = format!("Unable to delete match: {}", e),
+        }
+        self.show_matches();
+        self.match_selected = self.match_selected.min(count.saturating_sub(2));
+    }
+
+    /// Reads the tournaments of the current leaderboard, unless they were
+    /// read already, for [`App::tournaments`].
+    pub fn load_tournaments(&mut self) -> Result<(), Box<dyn Error>> {
+        let name = self.current()?.name();
+        if self.tournaments.as_ref().is_some_and(|(board, _)| board == name) {
+            return Ok(());
+        }
+        let tournaments = tournament::read_tournaments(self.storage.as_ref(), name)?;
+        self.tournaments = Some((name.to_string(), tournaments));
+        Ok(())
+    }
+
+    /// Tournaments seeded from the current leaderboard, oldest first, as
+    /// read by [`App::load_tournaments`].
+    pub fn tournaments(&self) -> &[Tournament] {
+        match (&self.tournaments, &self.current_leaderboard) {
+            (Some((board, tournaments)), Some(ldb)) if board == ldb.name() => tournaments,
+            _ => &[],
+        }
+    }
+
+    pub fn tournament(&mut self, name: &str) -> Result<&Tournament, Box<dyn Error>> {
+        self.load_tournaments()?;
+        let board = self.current()?.name();
+        self.tournaments().iter()
+            .find(|tournament| tournament.name == name)
+            .ok_or_else(|| format!("{} has no tournament named {}", board, name).into())
+    }
+
+    /// Creates a tournament between the entries of the current leaderboard,
+    /// seeded by their ranks.
+    pub fn create_tournament(&mut self, name: &str, format: TournamentFormat) -> Result<Tournament, Box<dyn Error>> {
+        self.tournaments = None;
+        tournament::create_tournament(self.storage.as_ref(), self.current()?, name, format)
+    }
+
+    /// Records the result of a fixture, `round` and `fixture` counting from
+    /// zero.
+    pub fn record_tournament_result(&mut self, name: &str, round: usize, fixture: usize, outcome: Outcome) -> Result<Tournament, Box<dyn Error>> {
+        self.tournaments = None;
+        tournament::record_result(self.storage.as_ref(), self.current()?.name(), name, round, fixture, outcome)
+    }
+
+    pub fn remove_tournament(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.tournaments = None;
+        tournament::remove_tournament(self.storage.as_ref(), self.current()?.name(), name)
+    }
+
+    /// Reads the tournaments of the current leaderboard for the tournament
+    /// views.
+    fn show_tournaments(&mut self) {
+        if let Err(e) = self.load_tournaments() {
+            self.message = format!("Unable to read tournaments: {}", e);
+        }
+    }
+
+    /// The tournament shown in the tournament view.
+    fn shown_tournament(&self) -> Option<&Tournament> {
+        let name = self.open_tournament.as_ref()?;
+        self.tournaments().iter().find(|tournament| &tournament.name == name)
+    }
+
+    /// Ranks the entries of the leaderboard `board` by the standings of the
+    /// tournament `name`, creating the leaderboard when it does not exist.
+    pub fn write_placements(&mut self, name: &str, board: &str) -> Result<(), Box<dyn Error>> {
+        let tournament = self.tournament(name)?.clone();
+        let board = board.trim();
+        if self.current()?.name() == board {
+            let ldb = self.current_leaderboard.as_mut().ok_or("No leaderboard is open")?;
+            return Ok(tournament::write_placements(&tournament, ldb, true)?);
+        }
+        let mut target = match self.find_leaderboard(board) {
+            Some(index) => self.open_leaderboard(index)?,
+            None => {
+                self.check_new_name(board)?;
+                self.new_leaderboard(board)?
+            },
+        };
+        tournament::write_placements(&tournament, &mut target, false)?;
+        self.histories.insert(target.name().to_string(), target.history().clone());
         Ok(())
     }
 
+    /// The fixtures of the open tournament as (round, fixture) positions, in
+    /// the order they are listed.
+    fn fixture_positions(&self) -> Vec<(usize, usize)> {
+        let Some(tournament) = self.shown_tournament() else { return Vec::new() };
+        tournament.rounds.iter().enumerate()
+            .flat_map(|(round, fixtures)| (0..fixtures.len()).map(move |fixture| (round, fixture)))
+            .collect()
+    }
+
+    fn record_selected_fixture(&mut self, outcome: Outcome) {
+        let Some(name) = self.open_tournament.clone() else { return };
+        let Some(&(round, fixture)) = self.fixture_positions().get(self.fixture_selected) else { return };
+        match self.record_tournament_result(&name, round, fixture, outcome) {
+            Ok(tournament) => {
+                self.message = match tournament.is_finished() {
+                    true => format!("{} is finished, write the placements to a leaderboard with <w>", name),
+                    false => tournament.describe(&tournament.rounds[round][fixture]),
+                };
+            },
+            Err(e) => self.message = e.to_string(),
+        }
+        self.show_tournaments();
+    }
+
+    /// Compares two snapshots of the current leaderboard, or a snapshot with
+    /// the leaderboard itself when `to` is `None`.
+    pub fn compare_snapshots(&self, from: &str, to: Option<&str>) -> Result<Comparison, Box<dyn Error>> {
+        snapshot::compare(self.storage.as_ref(), self.current()?, from, to)
+    }
+
+    /// Compares the selected snapshot with the live leaderboard, or the
+    /// marked snapshot with the selected one.
+    fn compare_selected_snapshot(&mut self) {
+        let Some(selected) = self.snapshots().get(self.snapshot_selected).map(|s| s.name.clone()) else { return };
+        let result = match self.snapshot_marked.clone() {
+            Some(marked) if marked != selected => self.compare_snapshots(&marked, Some(&selected)),
+            _ => self.compare_snapshots(&selected, None),
+        };
+        match result {
+            Ok(comparison) => {
+                self.comparison = comparison.to_string();
+                self.state = AppState::Comparison;
+            },
+            Err(e) => self.message = format!("Unable to compare: {}", e),
+        }
+    }
+
+    /// The names to choose between for the current question.
+    fn pairwise_question(&self) -> Option<(String, String)> {
+        let ldb = self.current_leaderboard.as_ref()?;
+        let name = |id: &ID| ldb.entries().iter().find(|node| node.id == *id).map(|node| node.name.clone());
+        match self.pairwise.as_ref()? {
+            Pairwise::Insert { name, insertion } =>
+                Some((name.clone(), ldb.entries().get(insertion.question()?)?.name.clone())),
+            Pairwise::Sort(sort) => {
+                let (a, b) = sort.question()?;
+                Some((name(a)?, name(b)?))
+            },
+        }
+    }
+
+    /// Answers the current question and applies the ranking once complete.
+    fn answer_pairwise(&mut self, first_ranks_above: bool) {
+        let Some(mut pairwise) = self.pairwise.take() else { return };
+        match &mut pairwise {
+            Pairwise::Insert { insertion, .. } => insertion.answer(first_ranks_above),
+            Pairwise::Sort(sort) => sort.answer(first_ranks_above),
+        }
+        let Some(ldb) = &mut self.current_leaderboard else { return };
+        let result = match pairwise {
+            Pairwise::Insert { name, insertion } => {
+                if insertion.question().is_some() {
+                    self.pairwise = Some(Pairwise::Insert { name, insertion });
+                    return;
+                }
+                ldb.finish_insertion(&name, &insertion).map(|rank| format!("Added {} at rank {}", name, rank))
+            },
+            Pairwise::Sort(sort) => {
+                if sort.question().is_some() {
+                    self.pairwise = Some(Pairwise::Sort(sort));
+                    return;
+                }
+                let asked = sort.asked();
+                ldb.finish_sort(sort)
+                    .map(|()| format!("Sorted {} with {} comparisons", ldb.name(), asked))
+            },
+        };
+        self.message = result.unwrap_or_else(|e| e);
+        self.state = AppState::Show;
+    }
+
     pub fn run(&mut self, mut terminal: DefaultTerminal) -> Result<()> {
         self.running = true;
         while self.running {
+            self.note_visit();
             terminal.draw(|frame| self.render(frame))?;
             self.handle_crossterm_events()?;
         }
@@ -180,60 +1109,86 @@ impl <'a> App <'_> {
             chunks[0]
         );
 
-        let title_txt: String;
-        match &self.leaderboard_names.get(self.current_leaderboard_index) {
-            Some(s) => title_txt = s.to_string(),
-            None => title_txt = "Add a new Leaderboard".to_string()
-        }
+        let title_txt: String = match (self.leaderboard_names.get(self.current_leaderboard_index), &self.current_leaderboard) {
+            (Some(s), Some(ldb)) if let Some(composite) = ldb.composite() =>
+                format!("{} (composite of {} by {})", s, composite.sources.join(", "), composite.method),
+            (Some(s), Some(ldb)) if ldb.ranking_mode() == RankingMode::Rated =>
+                format!("{} (rated by {}, {})", s, ldb.rating_system(), ldb.tie_rule()),
+            (Some(s), Some(ldb)) if ldb.ranking_mode().is_scored() =>
+                format!("{} ({}, {})", s, ldb.ranking_mode(), ldb.tie_rule()),
+            (Some(s), _) => s.to_string(),
+            (None, _) => "Add a new Leaderboard".to_string()
+        };
         let title = Line::from(title_txt.clone().bold());
-        let instructions = Line::from(vec![
-            " <-".into(),
-            "<Left> ".blue().bold(),
-            " ->".into(),
-            "<Right> ".blue().bold(),
-            " New Entry".into(),
-            "<Ctrl+n> ".blue().bold(),
-            " New Leaderboard".into(),
-            "<Ctrl+l> ".blue().bold(),
-            " Back".into(),
-            "<b> ".blue().bold(),
-            " Delete".into(),
-            "<Ctrl+d> ".blue().bold(),
-            " X".into(),
-            "<Ctrl+C> ".blue().bold(),
-        ]);
+        let instructions = self.instructions();
         let para_block = Block::bordered()
             .title(title.centered())
             .title_bottom(instructions.centered());
 
         match self.state {
-            AppState::Show => {
+            AppState::Show | AppState::Confirm | AppState::Search | AppState::Opponent | AppState::Help => {
+                let mut list_area = chunks[1];
+                if self.state == AppState::Search {
+                    let search_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(0), Constraint::Length(3)])
+                        .split(chunks[1]);
+                    list_area = search_chunks[0];
+                    let found = self.search_matches().len();
+                    self.search_input.set_block(
+                        Block::bordered()
+                        .title(format!("Search ({}, <Tab> to switch) {} found", self.search_mode, found)),
+                    );
+                    frame.render_widget(Clear, search_chunks[1]);
+                    frame.render_widget(&self.search_input, search_chunks[1]);
+                }
+                let matches = self.search_matches();
+                let baseline = self.baseline_time();
                 match &self.current_leaderboard {
                     Some(ldb) => {
+                        // The panel makes way for the list on narrow terminals.
+                        if self.show_details && list_area.width >= DETAILS_MIN_WIDTH
+                            && let Some(node) = ldb.entries().get(self.current_entry) {
+                            let detail_chunks = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                                .split(list_area);
+                            list_area = detail_chunks[0];
+                            let rank = ldb.display_ranks().get(self.current_entry).cloned().unwrap_or_default();
+                            frame.render_widget(Clear, detail_chunks[1]);
+                            frame.render_widget(
+                                Paragraph::new(entry_lines(node, &rank, baseline))
+                                    .wrap(Wrap { trim: false })
+                                    .block(Block::bordered().title(node.name.clone())),
+                                detail_chunks[1],
+                            );
+                        }
                         let ldb_entries = ldb.write_to_vector();
                         let mut items = Vec::new();
-                        for i in 0..ldb.len() {
-                            let mut item: ListItem = Line::raw(ldb_entries[i].clone()).into();
+                        for (i, entry) in ldb_entries.iter().enumerate() {
+                            let mut line = Line::raw(entry.trim_end().to_string());
+                            if matches.contains(&i) {
+                                line = line.cyan().bold();
+                            }
                             if i == self.current_entry {
-                                item = Line::raw(ldb_entries[i].clone()).yellow().into();
-                            } 
-                            match self.yanked_entry {
-                                Some(e) =>
-                                if i == e {
-                                    item = Line::raw(ldb_entries[i].clone()).red().into();
-                                },
-                                None => ()
+                                line = line.yellow();
+                            }
+                            if self.yanked_entry == Some(i) {
+                                line = line.red();
                             }
-                            items.push(item);
+                            if let (Some(at), Some(node)) = (baseline, ldb.entries().get(i)) {
+                                line.push_span(movement_span(node.movement_since(at)));
+                            }
+                            items.push(ListItem::from(line));
                         }
                         let list = List::default().items(items).block(para_block);//.block(para_block);
-                        frame.render_widget(Clear, chunks[1]);
-                        frame.render_stateful_widget(list,chunks[1],&mut self.list_state);
+                        frame.render_widget(Clear, list_area);
+                        frame.render_stateful_widget(list,list_area,&mut self.list_state);
                     },
                     None => {
                         let line = Line::from("Add a new leaderboard using Ctrl + l");
                         let para = Paragraph::new(line).block(para_block);
-                        frame.render_widget(para, chunks[1]);
+                        frame.render_widget(para, list_area);
                     }
                 }
             },
@@ -251,28 +1206,438 @@ impl <'a> App <'_> {
                     .title("New Entry name"),
                 );
                 frame.render_widget(&self.entry_name_input, entry_chunks[0]);
+                let scored = self.current_leaderboard.as_ref()
+                    .is_some_and(|ldb| ldb.ranking_mode().is_scored());
                 self.entry_rank_input.set_block(
                     Block::bordered()
-                    .title("New Entry rank"),
+                    .title(if scored { "New Entry score" } else { "New Entry rank" }),
                 );
                 frame.render_widget(&self.entry_rank_input, entry_chunks[1]);
             },
+            AppState::EditEntry => {
+                let entry_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(1)
+                .constraints([
+                    Constraint::Length(3),     // for the name
+                    Constraint::Length(3),     // for the score
+                    Constraint::Length(3),     // for the tags
+                    Constraint::Fill(1),       // for the notes
+                    Constraint::Fill(1),       // for the attributes
+                ])
+                .split(chunks[1]);
+                let fields = [
+                    (EditField::Name, "Name (<Tab> to switch, <Ctrl+s> to save)", &mut self.entry_name_input),
+                    (EditField::Score, "Score (leave empty for none)", &mut self.entry_rank_input),
+                    (EditField::Tags, "Tags (comma separated)", &mut self.entry_tags_input),
+                    (EditField::Notes, "Notes", &mut self.entry_notes_input),
+                    (EditField::Attributes, "Attributes (one key: value per line)", &mut self.entry_attributes_input),
+                ];
+                for (area, (field, title, input)) in entry_chunks.iter().zip(fields) {
+                    let style = if field == self.edit_field { Style::default().yellow() } else { Style::default() };
+                    input.set_block(Block::bordered().border_style(style).title(title));
+                    frame.render_widget(&*input, *area);
+                }
+            },
+            AppState::SetScore => {
+                self.score_input.set_block(
+                    Block::bordered()
+                    .title("Score (leave empty to clear)"),
+                );
+                frame.render_widget(&self.score_input, chunks[1]);
+            },
             AppState::NewLDB => {
                 self.ldb_name_input.set_block(
                     Block::bordered()
                     .title("New Leaderboard"),
                 );
                 frame.render_widget(&self.ldb_name_input, chunks[1]);
-            }
-        }
-
-    }
-
-    /// Reads the crossterm events and updates the state of [`App`].
-    ///
-    /// If your application needs to perform work in between handling events, you can use the
-    /// [`event::poll`] function to check if there are any events available with a timeout.
-    fn handle_crossterm_events(&mut self) -> Result<()> {
+            },
+            AppState::NewComposite => {
+                let composite_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(1)
+                    .constraints([Constraint::Length(3), Constraint::Length(3)])
+                    .split(chunks[1]);
+                let focused = |editing: bool| if editing { Style::default().yellow() } else { Style::default() };
+                self.ldb_name_input.set_block(
+                    Block::bordered()
+                    .border_style(focused(!self.editing_composite_sources))
+                    .title("New composite leaderboard (<Tab> to switch)"),
+                );
+                self.composite_sources_input.set_block(
+                    Block::bordered()
+                    .border_style(focused(self.editing_composite_sources))
+                    .title(format!("Combines (comma separated) by {} (<Up>/<Down> to change)", self.composite_method)),
+                );
+                frame.render_widget(&self.ldb_name_input, composite_chunks[0]);
+                frame.render_widget(&self.composite_sources_input, composite_chunks[1]);
+            },
+            AppState::PairwiseName => {
+                self.entry_name_input.set_block(Block::bordered().title("New entry, placed by comparing it with others"));
+                frame.render_widget(&self.entry_name_input, chunks[1]);
+            },
+            AppState::Pairwise => {
+                let mut lines = vec![Line::from("Which ranks higher?").bold().centered(), Line::raw("")];
+                if let Some((a, b)) = self.pairwise_question() {
+                    lines.push(Line::from(vec!["A".blue().bold(), format!(": {}", a).into()]).centered());
+                    lines.push(Line::raw("or").centered());
+                    lines.push(Line::from(vec!["B".blue().bold(), format!(": {}", b).into()]).centered());
+                }
+                lines.push(Line::raw(""));
+                lines.push(Line::raw(match &self.pairwise {
+                    Some(Pairwise::Insert { insertion, .. }) => format!("At most {} more questions", insertion.remaining()),
+                    Some(Pairwise::Sort(sort)) => format!("{} answered so far", sort.asked()),
+                    None => String::new(),
+                }).centered());
+                let block = Block::bordered()
+                    .title(Line::from(title_txt.clone().bold()).centered())
+                    .title_bottom(Line::from(vec![
+                        " A".into(), "<a/Left> ".blue().bold(),
+                        " B".into(), "<b/Right> ".blue().bold(),
+                        " Cancel".into(), "<Esc> ".blue().bold(),
+                    ]).centered());
+                frame.render_widget(Paragraph::new(lines).block(block), chunks[1]);
+            },
+            AppState::Snapshots => {
+                let snapshots = self.snapshots();
+                let items: Vec<ListItem> = snapshots.iter().enumerate()
+                    .map(|(i, snapshot)| {
+                        let mut text = format!("{} ({}, {} entries)", snapshot.name, format_time(snapshot.taken_at), snapshot.entries.len());
+                        if self.snapshot_marked.as_ref() == Some(&snapshot.name) {
+                            text.push_str(" [marked]");
+                        }
+                        let line = Line::raw(text);
+                        if i == self.snapshot_selected { line.yellow().into() } else { line.into() }
+                    })
+                    .collect();
+                let block = Block::bordered()
+                    .title(Line::from("Snapshots").bold().centered())
+                    .title_bottom(Line::from(vec![
+                        " New".into(), "<n> ".blue().bold(),
+                        " Compare".into(), "<c> ".blue().bold(),
+                        " Mark".into(), "<m> ".blue().bold(),
+                        " Delete".into(), "<X> ".blue().bold(),
+                        " Back".into(), "<Esc> ".blue().bold(),
+                    ]).centered());
+                if items.is_empty() {
+                    frame.render_widget(Paragraph::new("No snapshots yet, take one with <n>").block(block), chunks[1]);
+                } else {
+                    frame.render_widget(List::new(items).block(block), chunks[1]);
+                }
+            },
+            AppState::NewSnapshot => {
+                self.snapshot_input.set_block(Block::bordered().title("Snapshot name"));
+                frame.render_widget(&self.snapshot_input, chunks[1]);
+            },
+            AppState::Matches => {
+                let match_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(chunks[1]);
+                let block = Block::bordered()
+                    .title(Line::from("Matches").bold().centered())
+                    .title_bottom(Line::from(vec![
+                        " New".into(), "<n> ".blue().bold(),
+                        " Edit".into(), "<Enter> ".blue().bold(),
+                        " Delete".into(), "<X> ".blue().bold(),
+                        " Back".into(), "<Esc> ".blue().bold(),
+                    ]).centered());
+                let items: Vec<ListItem> = self.matches().iter().enumerate()
+                    .map(|(i, m)| {
+                        let mut text = format!("{}: {}", format_time(m.played_at), self.describe_match(m));
+                        if let Some(first) = m.notes.lines().next() {
+                            text.push_str(&format!(" ({})", first));
+                        }
+                        let line = Line::raw(text);
+                        if i == self.match_selected { line.yellow().into() } else { line.into() }
+                    })
+                    .collect();
+                if items.is_empty() {
+                    frame.render_widget(Paragraph::new("No matches yet, record one with <n>").block(block), match_chunks[0]);
+                } else {
+                    frame.render_widget(List::new(items).block(block), match_chunks[0]);
+                }
+                let table: Vec<Line> = format_standings(&self.standings().unwrap_or_default()).into_iter().map(Line::raw).collect();
+                frame.render_widget(
+                    Paragraph::new(table).block(Block::bordered().title(Line::from("Standings").bold().centered())),
+                    match_chunks[1],
+                );
+            },
+            AppState::EditMatch => {
+                let match_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(1)
+                    .constraints([Constraint::Min(4), Constraint::Length(5)])
+                    .split(chunks[1]);
+                let fields = [
+                    (false, "Results, one name: score per line (<Tab> to switch, <Ctrl+s> to save)", &mut self.match_results_input),
+                    (true, "Notes", &mut self.match_notes_input),
+                ];
+                for (area, (notes, title, input)) in match_chunks.iter().zip(fields) {
+                    let style = if notes == self.editing_match_notes { Style::default().yellow() } else { Style::default() };
+                    input.set_block(Block::bordered().border_style(style).title(title));
+                    frame.render_widget(&*input, *area);
+                }
+            },
+            AppState::Tournaments => {
+                let items: Vec<ListItem> = self.tournaments().iter().enumerate()
+                    .map(|(i, tournament)| {
+                        let status = if tournament.is_finished() { "finished" } else { "in progress" };
+                        let line = Line::raw(format!("{} ({}, {} players, {})",
+                            tournament.name, tournament.format, tournament.players.len(), status));
+                        if i == self.tournament_selected { line.yellow().into() } else { line.into() }
+                    })
+                    .collect();
+                let block = Block::bordered()
+                    .title(Line::from("Tournaments").bold().centered())
+                    .title_bottom(Line::from(vec![
+                        " New".into(), "<n> ".blue().bold(),
+                        " Open".into(), "<Enter> ".blue().bold(),
+                        " Delete".into(), "<X> ".blue().bold(),
+                        " Back".into(), "<Esc> ".blue().bold(),
+                    ]).centered());
+                if items.is_empty() {
+                    frame.render_widget(Paragraph::new("No tournaments yet, seed one from this leaderboard with <n>").block(block), chunks[1]);
+                } else {
+                    frame.render_widget(List::new(items).block(block), chunks[1]);
+                }
+            },
+            AppState::NewTournament => {
+                self.tournament_input.set_block(
+                    Block::bordered()
+                    .title(format!("New {} tournament seeded from this leaderboard (<Tab> to switch)", self.tournament_format)),
+                );
+                frame.render_widget(&self.tournament_input, chunks[1]);
+            },
+            AppState::Placements => {
+                self.tournament_input.set_block(Block::bordered().title("Write the placements to leaderboard (new or existing)"));
+                frame.render_widget(&self.tournament_input, chunks[1]);
+            },
+            AppState::Tournament => {
+                let tournament_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(chunks[1]);
+                if let Some(tournament) = self.shown_tournament() {
+                    let mut lines = tournament.bracket_lines();
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.extend(tournament.standings_lines());
+                    let title = format!("{} ({}{})", tournament.name, tournament.format, if tournament.is_finished() { ", finished" } else { "" });
+                    frame.render_widget(
+                        Paragraph::new(lines.into_iter().map(Line::raw).collect::<Vec<_>>())
+                            .block(Block::bordered().title(Line::from(title).bold().centered())),
+                        tournament_chunks[0],
+                    );
+                    let items: Vec<ListItem> = self.fixture_positions().into_iter().enumerate()
+                        .map(|(i, (round, fixture))| {
+                            let line = Line::raw(format!("{}.{} {}", round + 1, fixture + 1, tournament.describe(&tournament.rounds[round][fixture])));
+                            if i == self.fixture_selected { line.yellow().into() } else { line.into() }
+                        })
+                        .collect();
+                    let block = Block::bordered()
+                        .title(Line::from("Fixtures").bold().centered())
+                        .title_bottom(Line::from(vec![
+                            " 1st Won".into(), "<1> ".blue().bold(),
+                            " 2nd Won".into(), "<2> ".blue().bold(),
+                            " Draw".into(), "<d> ".blue().bold(),
+                            " Placements".into(), "<w> ".blue().bold(),
+                            " Back".into(), "<Esc> ".blue().bold(),
+                        ]).centered());
+                    let mut list_state = ListState::default().with_selected(Some(self.fixture_selected));
+                    frame.render_stateful_widget(List::new(items).block(block), tournament_chunks[1], &mut list_state);
+                }
+            },
+            AppState::Comparison => {
+                frame.render_widget(
+                    Paragraph::new(self.comparison.as_str())
+                        .block(Block::bordered().title(Line::from("Comparison").bold().centered())),
+                    chunks[1],
+                );
+            },
+            AppState::Baseline => {
+                self.baseline_input.set_block(
+                    Block::bordered()
+                    .title("Show movement since a date (YYYY-MM-DD), \"off\", or nothing for the last visit"),
+                );
+                frame.render_widget(&self.baseline_input, chunks[1]);
+            },
+            AppState::GlobalSearch => {
+                let search_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)])
+                    .split(chunks[1]);
+                self.search_input.set_block(
+                    Block::bordered()
+                    .title(format!("Search all leaderboards ({}, <Tab> to switch)", self.search_mode)),
+                );
+                frame.render_widget(&self.search_input, search_chunks[0]);
+                let items: Vec<ListItem> = self.global_results.iter().enumerate()
+                    .map(|(i, hit)| {
+                        let line = Line::raw(hit.to_string());
+                        if i == self.global_selected { line.yellow().into() } else { line.into() }
+                    })
+                    .collect();
+                let block = Block::bordered()
+                    .title(format!("{} found", self.global_results.len()))
+                    .title_bottom(Line::from(vec![
+                        " Go to".into(), "<Enter> ".blue().bold(),
+                        " Back".into(), "<Esc> ".blue().bold(),
+                    ]).centered());
+                frame.render_widget(List::new(items).block(block), search_chunks[1]);
+            },
+            AppState::Trash => {
+                let items: Vec<ListItem> = self.trash_items.iter().enumerate()
+                    .map(|(i, (_, text))| {
+                        let line = Line::raw(text.as_str());
+                        if i == self.trash_selected { line.yellow().into() } else { line.into() }
+                    })
+                    .collect();
+                let block = Block::bordered()
+                    .title(Line::from("Trash").bold().centered())
+                    .title_bottom(Line::from(vec![
+                        " Restore".into(), "<r> ".blue().bold(),
+                        " Delete for good".into(), "<P> ".blue().bold(),
+                        " Back".into(), "<Esc> ".blue().bold(),
+                    ]).centered());
+                if items.is_empty() {
+                    frame.render_widget(Paragraph::new("The trash is empty").block(block), chunks[1]);
+                } else {
+                    frame.render_widget(List::new(items).block(block), chunks[1]);
+                }
+            },
+            AppState::RenameLDB | AppState::CopyLDB => {
+                let title = match self.state {
+                    AppState::RenameLDB => "Rename leaderboard to",
+                    _ => "Duplicate leaderboard as",
+                };
+                self.ldb_name_input.set_block(Block::bordered().title(title));
+                frame.render_widget(&self.ldb_name_input, chunks[1]);
+            },
+            AppState::Import => {
+                self.import_path_input.set_block(
+                    Block::bordered()
+                    .title(format!("Import CSV file ({}, <Tab> to switch)", self.import_mode)),
+                );
+                frame.render_widget(&self.import_path_input, chunks[1]);
+            }
+        }
+
+        frame.render_widget(Paragraph::new(self.message.as_str()).block(Block::bordered()), chunks[2]);
+
+        if self.state == AppState::Confirm {
+            self.render_confirmation(frame, chunks[1]);
+        }
+        if self.state == AppState::Help {
+            render_help(frame, chunks[1]);
+        }
+
+    }
+
+    /// The keys shown under the leaderboard: the ones for the current state,
+    /// or the most used ones with the rest left to the help overlay.
+    fn instructions(&self) -> Line<'static> {
+        let keys: &[(&str, &str)] = match self.state {
+            AppState::Search => &[("Mode", "Tab"), ("Done", "Enter"), ("Cancel", "Esc")],
+            AppState::Opponent => &[("Won", "w"), ("Lost", "l"), ("Draw", "d"), ("Cancel", "Esc")],
+            _ if self.current_leaderboard.is_none() => &[("New Leaderboard", "Ctrl+l"), ("Help", "?"), ("Quit", "Ctrl+c")],
+            _ if !self.search_query.is_empty() => &[
+                ("Next/Prev Match", "n/N"), ("Search", "/"), ("Clear", "Esc"), ("Help", "?"), ("Quit", "Ctrl+c"),
+            ],
+            _ => &[
+                ("<-", "Left"), ("->", "Right"), ("New Entry", "Ctrl+n"), ("Edit", "r"), ("Delete", "Ctrl+d"),
+                ("Undo", "u"), ("Search", "/"), ("Help", "?"), ("Quit", "Ctrl+c"),
+            ],
+        };
+        Line::from(keys.iter()
+            .flat_map(|(action, key)| [format!(" {}", action).into(), format!("<{}> ", key).blue().bold()])
+            .collect::<Vec<_>>())
+    }
+
+    /// Draws the confirmation dialog for the pending deletion over `area`.
+    fn render_confirmation(&mut self, frame: &mut Frame, area: Rect) {
+        let question = match self.pending_delete {
+            Some(PendingDelete::Board(index)) => format!(
+                "Delete leaderboard {}?", self.leaderboard_names.get(index).map_or("", |n| n.as_str())),
+            Some(PendingDelete::Entry(position)) => match self.current_leaderboard.as_ref()
+                .and_then(|ldb| ldb.entries().get(position)) {
+                Some(node) => format!("Delete {} at rank {}?", node.name, node.rank),
+                None => "Delete entry?".to_string(),
+            },
+            None => return,
+        };
+        let typed = self.confirm_by_name();
+        let popup = centered(area, 60, if typed { 7 } else { 4 });
+        let block = Block::bordered()
+            .title(Line::from(" Confirm ").bold().centered())
+            .border_style(Style::default().red());
+        frame.render_widget(Clear, popup);
+        frame.render_widget(block, popup);
+        let inner = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)])
+            .split(popup);
+        frame.render_widget(Line::from(question).centered(), inner[0]);
+        if typed {
+            frame.render_widget(Line::from("Type the leaderboard name and press <Enter>, <Esc> to cancel").centered(), inner[1]);
+            self.confirm_input.set_block(Block::bordered());
+            frame.render_widget(&self.confirm_input, inner[2]);
+        } else {
+            frame.render_widget(Line::from(vec![
+                " Yes".into(), "<y> ".blue().bold(), " No".into(), "<n> ".blue().bold(),
+            ]).centered(), inner[1]);
+        }
+    }
+
+    /// Whether the pending deletion is confirmed by typing the board name.
+    fn confirm_by_name(&self) -> bool {
+        self.ui.confirm_with_board_name && matches!(self.pending_delete, Some(PendingDelete::Board(_)))
+    }
+
+    /// Asks before deleting, unless confirmations are turned off.
+    fn request_delete(&mut self, pending: PendingDelete) {
+        if self.ui.skip_confirmations {
+            self.delete(pending);
+        } else {
+            self.pending_delete = Some(pending);
+            self.confirm_input = TextArea::default();
+            self.state = AppState::Confirm;
+        }
+    }
+
+    fn delete(&mut self, pending: PendingDelete) {
+        match pending {
+            PendingDelete::Board(index) => {
+                if self.current_leaderboard.is_some()
+                    && let Err(e) = self.remove_leaderboard(index) {
+                    self.message = format!("Unable to remove leaderboard: {}", e);
+                    self.logger.write(self.message.clone());
+                }
+            },
+            PendingDelete::Entry(position) => {
+                if let Some(ldb) = &mut self.current_leaderboard {
+                    ldb.remove(position+1)
+                        .unwrap_or_else(|e| self.logger.write(format!("Unable to remove entry: {}", e)));
+                }
+                self.clamp_current_entry();
+            },
+        }
+    }
+
+    fn close_confirmation(&mut self) {
+        self.pending_delete = None;
+        self.confirm_input = TextArea::default();
+        self.state = AppState::Show;
+    }
+
+    /// Reads the crossterm events and updates the state of [`App`].
+    ///
+    /// If your application needs to perform work in between handling events, you can use the
+    /// [`event::poll`] function to check if there are any events available with a timeout.
+    fn handle_crossterm_events(&mut self) -> Result<()> {
         if let Ok(event) = crossterm::event::read() {
         match event {
             Event::Key(key) if key.kind == KeyEventKind::Press => {
@@ -288,7 +1653,7 @@ impl <'a> App <'_> {
                             KeyCode::Enter => {
                                 match self.focus {
                                     EntryFocus::Name => {
-                                        if let Some(name) = self.entry_name_input.lines().get(0) {
+                                        if let Some(name) = self.entry_name_input.lines().first() {
                                             self.entry_name = name.clone();
                                         }
                                         // Move focus to rank input
@@ -297,22 +1662,38 @@ impl <'a> App <'_> {
                                         self.entry_rank_input = TextArea::default();
                                     }
                                     EntryFocus::Rank => {
-                                        if let Some(rank_line) = self.entry_rank_input.lines().get(0) {
-                                            if let Ok(rank) = rank_line.parse::<usize>() {
+                                        let scored = self.current_leaderboard.as_ref()
+                                            .is_some_and(|ldb| ldb.ranking_mode().is_scored());
+                                        if let Some(rank_line) = self.entry_rank_input.lines().first() {
+                                            if scored {
+                                                if let Ok(score) = rank_line.trim().parse::<f64>() {
+                                                    if let Some(ldb) = &mut self.current_leaderboard {
+                                                        ldb.new_scored_entry(&self.entry_name, self.entry_rank, Some(score))
+                                                            .unwrap_or_else(|e| self.logger.write(
+                                                                format!("Unable to create new entry: {}", e)
+                                                            ));
+                                                    }
+                                                    self.state = AppState::Show;
+                                                    self.focus = EntryFocus::Name;
+                                                } else {
+                                                    self.message = "Score must be a number!".to_string();
+                                                    self.logger.write(&self.message);
+                                                }
+                                            } else if let Ok(rank) = rank_line.parse::<usize>() {
                                                 self.entry_rank = rank;
                                                 //println!("Submitted name: {}, rank: {}", self.entry_name, self.entry_rank);
                                                 // Done editing, maybe go back to main state
-                                                match &mut self.current_leaderboard{
-                                                    Some(ldb) => ldb.new_entry(&self.entry_name, self.entry_rank)
+                                                if let Some(ldb) = &mut self.current_leaderboard {
+                                                    ldb.new_entry(&self.entry_name, self.entry_rank)
                                                         .unwrap_or_else(|e| self.logger.write(
                                                             format!("Unable to create new entry: {}", e)
-                                                        )),
-                                                    None => {}
+                                                        ));
                                                 }
                                                 self.state = AppState::Show;
                                                 self.focus = EntryFocus::Name;
                                             } else {
-                                                println!("Rank must be a number!");
+                                                self.message = "Rank must be a number!".to_string();
+                                                self.logger.write(&self.message);
                                             }
                                         }
                                         self.entry_name_input = TextArea::default();
@@ -328,22 +1709,483 @@ impl <'a> App <'_> {
                     }
                     AppState::NewLDB => {
                         self.ldb_name_input.input(key);
+                        if key.code == KeyCode::Enter
+                            && let Some(name_line) = self.ldb_name_input.lines().first() {
+                            self.ldb_name = name_line.clone();
+                            match self.new_leaderboard(&self.ldb_name.clone()) {
+                                Ok(new_ldb) => {
+                                    self.stash_history();
+                                    self.current_leaderboard = Some(new_ldb);
+                                    self.logger.write(format!("Created new leaderboard: {}", self.ldb_name));
+                                },
+                                Err(err) => self.logger.write(format!("Error creating new leaderboard: {}", err)),
+                            }
+                            self.state = AppState::Show;
+                            self.ldb_name_input = TextArea::default();
+                        }
+                    }
+                    AppState::Help => {
+                        if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c') {
+                            self.quit();
+                        } else {
+                            self.state = AppState::Show;
+                        }
+                        return Ok(());
+                    }
+                    AppState::Confirm => {
+                        let Some(pending) = self.pending_delete else {
+                            self.close_confirmation();
+                            return Ok(());
+                        };
+                        if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c') {
+                            self.quit();
+                        } else if self.confirm_by_name() {
+                            match key.code {
+                                KeyCode::Enter => {
+                                    let typed = self.confirm_input.lines().first().cloned().unwrap_or_default();
+                                    if let PendingDelete::Board(index) = pending
+                                        && self.leaderboard_names.get(index) == Some(&typed) {
+                                        self.close_confirmation();
+                                        self.delete(pending);
+                                    } else {
+                                        self.message = "The name does not match, nothing was deleted".to_string();
+                                        self.close_confirmation();
+                                    }
+                                },
+                                KeyCode::Esc => self.close_confirmation(),
+                                _ => { self.confirm_input.input(key); },
+                            }
+                        } else {
+                            match key.code {
+                                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                    self.close_confirmation();
+                                    self.delete(pending);
+                                },
+                                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.close_confirmation(),
+                                _ => {},
+                            }
+                        }
+                        // The answer is not meant for the leaderboard view.
+                        return Ok(());
+                    }
+                    AppState::Search => {
                         match key.code {
+                            KeyCode::Tab => {
+                                self.search_mode = self.search_mode.next();
+                                self.jump_to_match(self.search_origin, true);
+                            },
                             KeyCode::Enter => {
-                                if let Some(name_line) = self.ldb_name_input.lines().get(0) {
-                                    self.ldb_name = name_line.clone();
-                                    match self.new_leaderboard(&self.ldb_name.clone()) {
-                                        Ok(new_ldb) => {
-                                            self.current_leaderboard = Some(new_ldb);
-                                            self.logger.write(format!("Created new leaderboard: {}", self.ldb_name));
-                                        },
-                                        Err(err) => self.logger.write(format!("Error creating new leaderboard: {}", err)),
-                                    }
+                                self.state = AppState::Show;
+                                self.message = match self.search_matches().len() {
+                                    0 => format!("No entries match {}", self.search_query),
+                                    found => format!("{} entries match {}", found, self.search_query),
+                                };
+                            },
+                            KeyCode::Esc => {
+                                self.current_entry = self.search_origin;
+                                self.search_query.clear();
+                                self.list_state.select(Some(self.current_entry));
+                            },
+                            _ => {
+                                self.search_input.input(key);
+                                self.search_query = self.search_input.lines().first().cloned().unwrap_or_default();
+                                self.jump_to_match(self.search_origin, true);
+                            },
+                        }
+                    }
+                    AppState::PairwiseName => {
+                        match key.code {
+                            KeyCode::Enter => {
+                                let name = self.entry_name_input.lines().first().cloned().unwrap_or_default();
+                                self.entry_name_input = TextArea::default();
+                                if name.trim().is_empty() {
                                     self.state = AppState::Show;
-                                    self.ldb_name_input = TextArea::default();
+                                    return Ok(());
+                                }
+                                let len = self.current_leaderboard.as_ref().map_or(0, |ldb| ldb.len());
+                                self.pairwise = Some(Pairwise::Insert { name: name.trim().to_string(), insertion: BinaryInsertion::new(len) });
+                                self.state = AppState::Pairwise;
+                                // An empty board needs no questions.
+                                if len == 0 {
+                                    self.answer_pairwise(true);
+                                }
+                                return Ok(());
+                            },
+                            KeyCode::Esc => self.entry_name_input = TextArea::default(),
+                            _ => { self.entry_name_input.input(key); },
+                        }
+                    }
+                    AppState::Opponent => {
+                        match key.code {
+                            KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => self.quit(),
+                            KeyCode::Up | KeyCode::Char('k') => {
+                                self.show_prev_entry().unwrap();
+                                self.list_state.scroll_up_by(1);
+                            },
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.show_next_entry().unwrap();
+                                self.list_state.scroll_down_by(1);
+                            },
+                            KeyCode::Char('w') => self.record_game(Outcome::FirstWins),
+                            KeyCode::Char('l') => self.record_game(Outcome::SecondWins),
+                            KeyCode::Char('d') => self.record_game(Outcome::Draw),
+                            KeyCode::Esc => {
+                                self.game_player = None;
+                                self.message.clear();
+                                self.state = AppState::Show;
+                            },
+                            _ => {},
+                        }
+                        // The answer keys mean something else in the leaderboard view.
+                        return Ok(());
+                    }
+                    AppState::Pairwise => {
+                        match key.code {
+                            KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => self.quit(),
+                            KeyCode::Char('a') | KeyCode::Left => self.answer_pairwise(true),
+                            KeyCode::Char('b') | KeyCode::Right => self.answer_pairwise(false),
+                            KeyCode::Esc => {
+                                self.pairwise = None;
+                                self.state = AppState::Show;
+                            },
+                            _ => {},
+                        }
+                        // The answer keys mean something else in the leaderboard view.
+                        return Ok(());
+                    }
+                    AppState::Snapshots => {
+                        let count = self.snapshots().len();
+                        match key.code {
+                            KeyCode::Up | KeyCode::Char('k') => self.snapshot_selected = self.snapshot_selected.saturating_sub(1),
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.snapshot_selected = (self.snapshot_selected + 1).min(count.saturating_sub(1));
+                            },
+                            KeyCode::Char('n') => {
+                                self.snapshot_input = TextArea::default();
+                                self.state = AppState::NewSnapshot;
+                            },
+                            KeyCode::Char('m') => {
+                                let selected = self.snapshots().get(self.snapshot_selected).map(|s| s.name.clone());
+                                self.snapshot_marked = if self.snapshot_marked == selected { None } else { selected };
+                            },
+                            KeyCode::Char('c') => self.compare_selected_snapshot(),
+                            KeyCode::Char('X') => {
+                                if let Some(name) = self.snapshots().get(self.snapshot_selected).map(|s| s.name.clone()) {
+                                    match self.remove_snapshot(&name) {
+                                        Ok(()) => self.message = format!("Deleted snapshot {}", name),
+                                        Err(e) => self.message = format!("Unable to delete snapshot: {}", e),
+                                    }
+                                    self.show_snapshots();
+                                    self.snapshot_selected = self.snapshot_selected.min(count.saturating_sub(2));
+                                }
+                            },
+                            _ => {},
+                        }
+                    }
+                    AppState::Matches => {
+                        let count = self.matches().len();
+                        match key.code {
+                            KeyCode::Up | KeyCode::Char('k') => self.match_selected = self.match_selected.saturating_sub(1),
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.match_selected = (self.match_selected + 1).min(count.saturating_sub(1));
+                            },
+                            KeyCode::Char('n') => self.edit_match(None),
+                            KeyCode::Enter | KeyCode::Char('r') => {
+                                if let Some(id) = self.matches().get(self.match_selected).map(|m| m.id) {
+                                    self.edit_match(Some(id));
+                                }
+                            },
+                            KeyCode::Char('X') => self.remove_selected_match(),
+                            _ => {},
+                        }
+                    }
+                    AppState::EditMatch => {
+                        match (key.modifiers, key.code) {
+                            (_, KeyCode::Tab | KeyCode::BackTab) => self.editing_match_notes = !self.editing_match_notes,
+                            (KeyModifiers::CONTROL, KeyCode::Char('s')) => self.save_edited_match(),
+                            (_, KeyCode::Esc) => {
+                                self.close_match_editor();
+                                return Ok(());
+                            },
+                            _ if self.editing_match_notes => { self.match_notes_input.input(key); },
+                            _ => { self.match_results_input.input(key); },
+                        }
+                    }
+                    AppState::Tournaments => {
+                        let count = self.tournaments().len();
+                        match key.code {
+                            KeyCode::Up | KeyCode::Char('k') => self.tournament_selected = self.tournament_selected.saturating_sub(1),
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.tournament_selected = (self.tournament_selected + 1).min(count.saturating_sub(1));
+                            },
+                            KeyCode::Char('n') => {
+                                self.tournament_input = TextArea::default();
+                                self.state = AppState::NewTournament;
+                            },
+                            KeyCode::Enter => {
+                                if let Some(tournament) = self.tournaments().get(self.tournament_selected) {
+                                    self.open_tournament = Some(tournament.name.clone());
+                                    self.fixture_selected = 0;
+                                    self.state = AppState::Tournament;
+                                }
+                            },
+                            KeyCode::Char('X') => {
+                                if let Some(name) = self.tournaments().get(self.tournament_selected).map(|t| t.name.clone()) {
+                                    match self.remove_tournament(&name) {
+                                        Ok(()) => self.message = format!("Deleted tournament {}", name),
+                                        Err(e) => self.message = format!("Unable to delete tournament: {}", e),
+                                    }
+                                    self.show_tournaments();
+                                    self.tournament_selected = self.tournament_selected.min(count.saturating_sub(2));
+                                }
+                            },
+                            _ => {},
+                        }
+                    }
+                    AppState::NewComposite => {
+                        match key.code {
+                            KeyCode::Tab => self.editing_composite_sources = !self.editing_composite_sources,
+                            KeyCode::Up => self.composite_method = self.composite_method.next(),
+                            KeyCode::Down => self.composite_method = self.composite_method.previous(),
+                            KeyCode::Enter => {
+                                let name = self.ldb_name_input.lines().first().cloned().unwrap_or_default();
+                                let sources: Vec<String> = self.composite_sources_input.lines().join(",")
+                                    .split(',')
+                                    .map(str::to_string)
+                                    .collect();
+                                match self.new_composite(&name, &sources, self.composite_method) {
+                                    Ok(ldb) => {
+                                        let sources = ldb.composite().map(|composite| composite.sources.join(", ")).unwrap_or_default();
+                                        self.message = format!("Created {} combining {}", ldb.name(), sources);
+                                        self.current_leaderboard_index = self.leaderboard_names.len() - 1;
+                                        self.stash_history();
+                                        self.current_leaderboard = Some(ldb);
+                                        self.current_entry = 0;
+                                        self.state = AppState::Show;
+                                    },
+                                    Err(e) => self.message = e.to_string(),
+                                }
+                            },
+                            _ if self.editing_composite_sources => { self.composite_sources_input.input(key); },
+                            _ => { self.ldb_name_input.input(key); },
+                        }
+                    }
+                    AppState::NewTournament => {
+                        match key.code {
+                            KeyCode::Tab => self.tournament_format = self.tournament_format.next(),
+                            KeyCode::Enter => {
+                                let name = self.tournament_input.lines().first().cloned().unwrap_or_default();
+                                match self.create_tournament(&name, self.tournament_format) {
+                                    Ok(tournament) => {
+                                        self.message = format!("Drew {} between {} players", tournament.name, tournament.players.len());
+                                        self.open_tournament = Some(tournament.name);
+                                        self.show_tournaments();
+                                        self.fixture_selected = 0;
+                                        self.state = AppState::Tournament;
+                                    },
+                                    Err(e) => self.message = e.to_string(),
+                                }
+                            },
+                            _ => { self.tournament_input.input(key); },
+                        }
+                    }
+                    AppState::Tournament => {
+                        let count = self.fixture_positions().len();
+                        match key.code {
+                            KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => self.quit(),
+                            KeyCode::Up | KeyCode::Char('k') => self.fixture_selected = self.fixture_selected.saturating_sub(1),
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.fixture_selected = (self.fixture_selected + 1).min(count.saturating_sub(1));
+                            },
+                            KeyCode::Char('1') => self.record_selected_fixture(Outcome::FirstWins),
+                            KeyCode::Char('2') => self.record_selected_fixture(Outcome::SecondWins),
+                            KeyCode::Char('d') => self.record_selected_fixture(Outcome::Draw),
+                            KeyCode::Char('w') => {
+                                let board = self.current_leaderboard.as_ref().map(|ldb| ldb.name().to_string()).unwrap_or_default();
+                                self.tournament_input = TextArea::new(vec![board]);
+                                self.tournament_input.move_cursor(tui_textarea::CursorMove::End);
+                                self.state = AppState::Placements;
+                            },
+                            KeyCode::Esc => {
+                                self.open_tournament = None;
+                                self.state = AppState::Tournaments;
+                            },
+                            _ => {},
+                        }
+                        // Leaving goes back to the list of tournaments, not the leaderboard.
+                        return Ok(());
+                    }
+                    AppState::Placements => {
+                        match key.code {
+                            KeyCode::Enter => {
+                                let board = self.tournament_input.lines().first().cloned().unwrap_or_default();
+                                let name = self.open_tournament.clone().unwrap_or_default();
+                                match self.write_placements(&name, &board) {
+                                    Ok(()) => {
+                                        self.message = format!("Wrote the placements of {} to {}", name, board.trim());
+                                        self.state = AppState::Tournament;
+                                    },
+                                    Err(e) => self.message = e.to_string(),
+                                }
+                                return Ok(());
+                            },
+                            KeyCode::Esc => {
+                                self.state = AppState::Tournament;
+                                return Ok(());
+                            },
+                            _ => { self.tournament_input.input(key); },
+                        }
+                    }
+                    AppState::NewSnapshot => {
+                        match key.code {
+                            KeyCode::Enter => {
+                                let name = self.snapshot_input.lines().first().cloned().unwrap_or_default();
+                                match self.take_snapshot(&name) {
+                                    Ok(()) => {
+                                        self.message = format!("Took snapshot {}", name.trim());
+                                        self.show_snapshots();
+                                        self.state = AppState::Snapshots;
+                                    },
+                                    Err(e) => self.message = e.to_string(),
+                                }
+                            },
+                            _ => { self.snapshot_input.input(key); },
+                        }
+                    }
+                    AppState::Baseline => {
+                        match key.code {
+                            KeyCode::Enter => {
+                                let text = self.baseline_input.lines().first().cloned().unwrap_or_default();
+                                match self.set_baseline(&text) {
+                                    Ok(()) => {
+                                        self.state = AppState::Show;
+                                        self.baseline_input = TextArea::default();
+                                    },
+                                    Err(e) => self.message = e,
                                 }
                             },
-                            _ => {} 
+                            KeyCode::Esc => self.baseline_input = TextArea::default(),
+                            _ => { self.baseline_input.input(key); },
+                        }
+                    }
+                    AppState::GlobalSearch => {
+                        match key.code {
+                            KeyCode::Up => self.global_selected = self.global_selected.saturating_sub(1),
+                            KeyCode::Down => {
+                                self.global_selected = (self.global_selected + 1)
+                                    .min(self.global_results.len().saturating_sub(1));
+                            },
+                            KeyCode::Tab => {
+                                self.search_mode = self.search_mode.next();
+                                self.update_global_results();
+                            },
+                            KeyCode::Enter => {
+                                if let Some(hit) = self.global_results.get(self.global_selected).cloned() {
+                                    match self.jump_to_hit(&hit) {
+                                        Ok(()) => self.state = AppState::Show,
+                                        Err(e) => self.message = e.to_string(),
+                                    }
+                                }
+                            },
+                            KeyCode::Esc => self.global_results.clear(),
+                            _ => {
+                                self.search_input.input(key);
+                                self.update_global_results();
+                            },
+                        }
+                    }
+                    AppState::Trash => {
+                        match key.code {
+                            KeyCode::Up | KeyCode::Char('k') => self.trash_selected = self.trash_selected.saturating_sub(1),
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.trash_selected += 1;
+                                self.clamp_trash_selection();
+                            },
+                            KeyCode::Char('r') => self.restore_from_trash(),
+                            KeyCode::Char('P') => self.purge_from_trash(),
+                            _ => {},
+                        }
+                    }
+                    AppState::RenameLDB | AppState::CopyLDB => {
+                        match key.code {
+                            KeyCode::Enter => {
+                                let name = self.ldb_name_input.lines().first().cloned().unwrap_or_default();
+                                let index = self.current_leaderboard_index;
+                                let result = match self.state {
+                                    AppState::RenameLDB => self.rename_leaderboard(index, &name)
+                                        .map(|()| format!("Renamed leaderboard to {}", name.trim())),
+                                    _ => self.duplicate_leaderboard(index, &name)
+                                        .and_then(|copy| self.select_leaderboard(copy).map(|_| ()))
+                                        .map(|()| format!("Duplicated leaderboard as {}", name.trim())),
+                                };
+                                match result {
+                                    Ok(message) => {
+                                        self.message = message;
+                                        self.state = AppState::Show;
+                                        self.ldb_name_input = TextArea::default();
+                                    },
+                                    Err(e) => self.message = e.to_string(),
+                                }
+                            },
+                            KeyCode::Esc => self.ldb_name_input = TextArea::default(),
+                            _ => { self.ldb_name_input.input(key); },
+                        }
+                    }
+                    AppState::EditEntry => {
+                        // Notes and attributes span several lines, so <Enter>
+                        // only saves from the single line fields.
+                        match (key.modifiers, key.code, self.edit_field) {
+                            (KeyModifiers::CONTROL, KeyCode::Char('c'), _) => self.quit(),
+                            (_, KeyCode::Tab, field) | (_, KeyCode::Enter, field @ EditField::Name) => self.edit_field = field.next(),
+                            (_, KeyCode::BackTab, field) => self.edit_field = field.previous(),
+                            (KeyModifiers::CONTROL, KeyCode::Char('s'), _)
+                            | (_, KeyCode::Enter, EditField::Score | EditField::Tags) => self.save_edited_entry(),
+                            (_, KeyCode::Esc, _) => self.close_entry_editor(),
+                            (_, _, EditField::Name) => { self.entry_name_input.input(key); },
+                            (_, _, EditField::Score) => { self.entry_rank_input.input(key); },
+                            (_, _, EditField::Tags) => { self.entry_tags_input.input(key); },
+                            (_, _, EditField::Notes) => { self.entry_notes_input.input(key); },
+                            (_, _, EditField::Attributes) => { self.entry_attributes_input.input(key); },
+                        }
+                        // Saving closes the editor, and <Ctrl+s> must not go on
+                        // to open the score prompt of the leaderboard view.
+                        return Ok(());
+                    }
+                    AppState::Import => {
+                        match key.code {
+                            KeyCode::Tab => {
+                                self.import_mode = match self.import_mode {
+                                    ImportMode::Merge => ImportMode::Replace,
+                                    ImportMode::Replace => ImportMode::Merge,
+                                };
+                            },
+                            KeyCode::Enter => {
+                                let path = self.import_path_input.lines().first().cloned().unwrap_or_default();
+                                self.import_csv(path.trim());
+                                self.state = AppState::Show;
+                                self.import_path_input = TextArea::default();
+                            },
+                            _ => { self.import_path_input.input(key); },
+                        }
+                    }
+                    AppState::SetScore => {
+                        self.score_input.input(key);
+                        if key.code == KeyCode::Enter {
+                            let score_line = self.score_input.lines().first().cloned().unwrap_or_default();
+                            let score = match score_line.trim() {
+                                "" => Ok(None),
+                                s => s.parse::<f64>().map(Some),
+                            };
+                            match (score, &mut self.current_leaderboard) {
+                                (Ok(score), Some(ldb)) => {
+                                    ldb.set_score(self.current_entry+1, score)
+                                        .unwrap_or_else(|e| self.logger.write(format!("Unable to set score: {}", e)));
+                                    self.state = AppState::Show;
+                                    self.score_input = TextArea::default();
+                                },
+                                (Err(_), _) => self.logger.write("Score must be a number!"),
+                                (_, None) => self.state = AppState::Show,
+                            }
                         }
                     }
                     _ => {}
@@ -362,67 +2204,191 @@ impl <'a> App <'_> {
 
     /// Handles the key events and updates the state of [`App`].
     fn on_key_event(&mut self, key: KeyEvent) {
+        let show = self.state == AppState::Show;
         // setup for inputs
         match (key.modifiers, key.code) {
             // (_, KeyCode::Char('q'))
             | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
 
             // Add other key handlers here.
-            (_, KeyCode::Left) => if self.state == AppState::Show { self.show_prev_leaderboard().unwrap() },
-            (_, KeyCode::Right) => if self.state == AppState::Show { self.show_next_leaderboard().unwrap() },
+            (_, KeyCode::Left) if show => self.show_prev_leaderboard().unwrap(),
+            (_, KeyCode::Right) if show => self.show_next_leaderboard().unwrap(),
             (KeyModifiers::CONTROL, KeyCode::Char('l')) => self.state = AppState::NewLDB,
-            (KeyModifiers::CONTROL, KeyCode::Char('n')) => {
-                match &self.current_leaderboard {
-                    Some(_) => self.state = AppState::NewEntry,
-                    None => {}
-                }
+            (KeyModifiers::CONTROL, KeyCode::Char('n')) if self.current_leaderboard.is_some() => {
+                self.state = AppState::NewEntry;
+            },
+            (_, KeyCode::Char('h')) if show => self.show_prev_leaderboard().unwrap(),
+            (_, KeyCode::Char('l')) if show => self.show_next_leaderboard().unwrap(),
+            (_, KeyCode::Esc) => {
+                self.state = AppState::Show;
+                self.yanked_entry = None;
+                self.search_query.clear();
             },
-            (_, KeyCode::Char('h')) => if self.state == AppState::Show { self.show_prev_leaderboard().unwrap() },
-            (_, KeyCode::Char('l')) => if self.state == AppState::Show { self.show_next_leaderboard().unwrap() },
-            (_, KeyCode::Esc) => { self.state = AppState::Show; self.yanked_entry = None; },
-            (_, KeyCode::Up) => if self.state == AppState::Show { 
+            (_, KeyCode::Up) if show => {
                 self.show_prev_entry().unwrap();
                 self.list_state.scroll_up_by(1);
             },
-            (_, KeyCode::Down) => if self.state == AppState::Show { 
+            (_, KeyCode::Down) if show => {
                 self.show_next_entry().unwrap();
                 self.list_state.scroll_down_by(1);
             },
-            (_, KeyCode::Char('k')) => if self.state == AppState::Show { self.show_prev_entry().unwrap() },
-            (_, KeyCode::Char('j')) => if self.state == AppState::Show { self.show_next_entry().unwrap() },
-            (KeyModifiers::CONTROL, KeyCode::Char('d')) => if self.state == AppState::Show { 
-                match &mut self.current_leaderboard{
-                    Some(ldb) => ldb.remove(self.current_entry+1),
-                    None => {}
+            (_, KeyCode::Char('k')) if show => self.show_prev_entry().unwrap(),
+            (_, KeyCode::Char('j')) if show => self.show_next_entry().unwrap(),
+            (KeyModifiers::CONTROL, KeyCode::Char('d'))
+                if show && self.current_leaderboard.as_ref().is_some_and(|ldb| !ldb.is_empty()) => {
+                self.request_delete(PendingDelete::Entry(self.current_entry));
+            },
+            (KeyModifiers::CONTROL, KeyCode::Char('r')) if show => {
+                if let Some(ldb) = &mut self.current_leaderboard {
+                    ldb.redo().unwrap_or_else(|e| self.logger.write(format!("Unable to redo: {}", e)));
                 }
+                self.clamp_current_entry();
             },
-            (KeyModifiers::CONTROL, KeyCode::Char('x')) => if self.state == AppState::Show { 
-                match &mut self.current_leaderboard{
-                    Some(_ldb) => self.remove_leaderboard(self.current_leaderboard_index)
-                            .unwrap_or_else(|e| self.logger.write(format!("Unable to remove leaderboard: {}", e))), 
-                    None => {}
+            (_, KeyCode::Char('u')) if show => {
+                if let Some(ldb) = &mut self.current_leaderboard {
+                    ldb.undo().unwrap_or_else(|e| self.logger.write(format!("Unable to undo: {}", e)));
+                }
+                self.clamp_current_entry();
+            },
+            (_, KeyCode::Char('H')) if show => {
+                if let Some(ldb) = &mut self.current_leaderboard {
+                    let keep = !ldb.keeps_history();
+                    match ldb.set_keep_history(keep) {
+                        Ok(()) => self.logger.write(format!("Keep history of {}: {}", self.leaderboard_names[self.current_leaderboard_index], keep)),
+                        Err(e) => self.logger.write(format!("Unable to change history setting: {}", e)),
+                    }
                 }
-                self.current_leaderboard_index = 0;
             },
-            (KeyModifiers::CONTROL, KeyCode::Char('y')) => if self.state == AppState::Show { 
+            (KeyModifiers::CONTROL, KeyCode::Char('x')) if show && self.current_leaderboard.is_some() => {
+                self.request_delete(PendingDelete::Board(self.current_leaderboard_index));
+            },
+            (_, KeyCode::Char('/')) if show && self.current_leaderboard.is_some() => {
+                self.search_origin = self.current_entry;
+                self.search_query.clear();
+                self.search_input = TextArea::default();
+                self.state = AppState::Search;
+            },
+            (KeyModifiers::NONE, KeyCode::Char('n')) if show && !self.search_query.is_empty() => {
+                self.jump_to_match(self.current_entry + 1, true);
+            },
+            (_, KeyCode::Char('N')) if show && !self.search_query.is_empty() => {
+                self.jump_to_match(self.current_entry.wrapping_sub(1), false);
+            },
+            (KeyModifiers::CONTROL, KeyCode::Char('f')) if show => {
+                self.search_input = TextArea::default();
+                self.global_results.clear();
+                self.global_selected = 0;
+                self.state = AppState::GlobalSearch;
+            },
+            (_, KeyCode::Char('v')) if show => self.show_details = !self.show_details,
+            (_, KeyCode::Char('B')) if show => self.state = AppState::Baseline,
+            (_, KeyCode::Char('c')) if show && self.current_leaderboard.is_some() => {
+                match self.current_leaderboard.as_ref().map(|ldb| ldb.ranking_mode()) {
+                    Some(mode) if mode.is_scored() => self.message = format!("Ranks are computed from scores ({})", mode),
+                    _ => self.state = AppState::PairwiseName,
+                }
+            },
+            (_, KeyCode::Char('C')) if show => {
+                if let Some(ldb) = &self.current_leaderboard {
+                    if ldb.ranking_mode().is_scored() {
+                        self.message = format!("Ranks are computed from scores ({})", ldb.ranking_mode());
+                    } else if ldb.len() > 1 {
+                        self.pairwise = Some(Pairwise::Sort(MergeSort::new(ldb.entries().iter().map(|node| node.id).collect())));
+                        self.state = AppState::Pairwise;
+                    }
+                }
+            },
+            (_, KeyCode::Char('g')) if show => {
+                if let Some(ldb) = &self.current_leaderboard
+                    && let Some(node) = ldb.entries().get(self.current_entry) {
+                    if ldb.ranking_mode() == RankingMode::Rated {
+                        self.game_player = Some(node.id);
+                        self.message = format!("Game of {}: pick the opponent, then <w> won, <l> lost, <d> draw, <Esc> cancel", node.name);
+                        self.state = AppState::Opponent;
+                    } else {
+                        self.message = format!("{} is not rated by match results", ldb.name());
+                    }
+                }
+            },
+            (_, KeyCode::Char('M')) if show && self.current_leaderboard.is_some() => {
+                self.match_selected = 0;
+                self.show_matches();
+                self.state = AppState::Matches;
+            },
+            (_, KeyCode::Char('A')) if show => {
+                // Every leaderboard is offered as a source, to be trimmed down
+                // to the ones wanted.
+                self.ldb_name_input = TextArea::default();
+                self.composite_sources_input = TextArea::new(vec![self.leaderboard_names.join(", ")]);
+                self.composite_sources_input.move_cursor(tui_textarea::CursorMove::End);
+                self.editing_composite_sources = false;
+                self.state = AppState::NewComposite;
+            },
+            (_, KeyCode::Char('O')) if show && self.current_leaderboard.is_some() => {
+                self.tournament_selected = 0;
+                self.show_tournaments();
+                self.state = AppState::Tournaments;
+            },
+            (_, KeyCode::Char('S')) if show && self.current_leaderboard.is_some() => {
+                self.snapshot_selected = 0;
+                self.snapshot_marked = None;
+                self.show_snapshots();
+                self.state = AppState::Snapshots;
+            },
+            (_, KeyCode::Char('T')) if show => {
+                self.trash_selected = 0;
+                self.load_trash_items();
+                self.state = AppState::Trash;
+            },
+            (_, KeyCode::Char('R')) if show && self.current_leaderboard.is_some() => {
+                self.state = AppState::RenameLDB;
+            },
+            (_, KeyCode::Char('D')) if show && self.current_leaderboard.is_some() => {
+                self.state = AppState::CopyLDB;
+            },
+            (KeyModifiers::CONTROL, KeyCode::Char('y')) if show => {
                 self.yanked_entry = Some(self.current_entry);
             },
-            (_, KeyCode::Char('p')) => if self.state == AppState::Show { 
-                match self.yanked_entry {
-                    Some(e) => {
-                        if e == self.current_entry {
-                            ()
-                        }
-                        else {
-                            match &mut self.current_leaderboard{
-                                Some(ldb) => ldb.change_rank(e+1, self.current_entry+1)
-                                .unwrap_or_else(|e| self.logger.write(format!("Unable to change rank of entry: {}", e))), 
-                                None => {}
-                            }
-                            self.yanked_entry = None;
-                        }
-                    },
-                    None => ()
+            (_, KeyCode::Char('p')) if show => {
+                if let Some(e) = self.yanked_entry
+                    && e != self.current_entry {
+                    if let Some(ldb) = &mut self.current_leaderboard {
+                        ldb.change_rank(e+1, self.current_entry+1)
+                            .unwrap_or_else(|e| self.logger.write(format!("Unable to change rank of entry: {}", e)));
+                    }
+                    self.yanked_entry = None;
+                }
+            },
+            (_, KeyCode::Char('?')) if show => self.state = AppState::Help,
+            (_, KeyCode::Char('e')) if show => self.export_csv(),
+            (_, KeyCode::Char('r')) if show => self.edit_current_entry(),
+            (_, KeyCode::Char('i')) if show && self.current_leaderboard.is_some() => {
+                self.state = AppState::Import;
+            },
+            (_, KeyCode::Char('s')) if show => {
+                if let Some(ldb) = &self.current_leaderboard
+                    && !ldb.is_empty() {
+                    self.state = AppState::SetScore;
+                }
+            },
+            (_, KeyCode::Char('m')) if show => {
+                if let Some(ldb) = &mut self.current_leaderboard {
+                    if ldb.ranking_mode() == RankingMode::Rated {
+                        let system = ldb.rating_system().next();
+                        ldb.set_rating_system(system)
+                            .unwrap_or_else(|e| self.logger.write(e));
+                    } else {
+                        let mode = ldb.ranking_mode().next();
+                        ldb.set_ranking_mode(mode)
+                            .unwrap_or_else(|e| self.logger.write(format!("Unable to change ranking mode: {}", e)));
+                    }
+                }
+            },
+            (_, KeyCode::Char('t')) if show => {
+                if let Some(ldb) = &mut self.current_leaderboard {
+                    let rule = ldb.tie_rule().next();
+                    ldb.set_tie_rule(rule)
+                        .unwrap_or_else(|e| self.logger.write(format!("Unable to change tie rule: {}", e)));
                 }
             },
             _ => {}
@@ -430,6 +2396,121 @@ impl <'a> App <'_> {
         }
     }
     
+    /// Records a game between the entry picked with <g> and the highlighted
+    /// one. `outcome` is seen from the picked entry.
+    fn record_game(&mut self, outcome: Outcome) {
+        let (Some(ldb), Some(player)) = (&mut self.current_leaderboard, self.game_player) else { return };
+        let Some(opponent) = ldb.entries().get(self.current_entry).map(|node| node.id) else { return };
+        let name = |id: ID| ldb.entries().iter().find(|node| node.id == id).map(|node| node.name.clone()).unwrap_or_default();
+        let (player_name, opponent_name) = (name(player), name(opponent));
+        match ldb.record_game(player, opponent, outcome) {
+            Ok(()) => {
+                self.message = match outcome {
+                    Outcome::FirstWins => format!("{} beat {}", player_name, opponent_name),
+                    Outcome::SecondWins => format!("{} beat {}", opponent_name, player_name),
+                    Outcome::Draw => format!("{} drew with {}", player_name, opponent_name),
+                };
+                self.game_player = None;
+                self.state = AppState::Show;
+            },
+            Err(e) => self.message = e,
+        }
+    }
+
+    /// Opens the selected entry for editing, prefilled with its name and score.
+    fn edit_current_entry(&mut self) {
+        let Some(node) = self.current_leaderboard.as_ref()
+            .and_then(|ldb| ldb.entries().get(self.current_entry)) else { return };
+        self.edited_entry = Some(node.id);
+        self.entry_name_input = TextArea::new(vec![node.name.clone()]);
+        self.entry_name_input.move_cursor(tui_textarea::CursorMove::End);
+        self.entry_rank_input = TextArea::new(vec![node.score.map(|s| s.to_string()).unwrap_or_default()]);
+        self.entry_rank_input.move_cursor(tui_textarea::CursorMove::End);
+        let tags: Vec<&str> = node.details.tags.iter().map(String::as_str).collect();
+        self.entry_tags_input = TextArea::new(vec![tags.join(", ")]);
+        self.entry_notes_input = TextArea::new(node.details.notes.lines().map(str::to_string).collect());
+        self.entry_attributes_input = TextArea::new(node.details.attributes.iter()
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect());
+        self.edit_field = EditField::Name;
+        self.state = AppState::EditEntry;
+    }
+
+    fn save_edited_entry(&mut self) {
+        let name = self.entry_name_input.lines().first().cloned().unwrap_or_default();
+        let score = match self.entry_rank_input.lines().first().map(|s| s.trim()).unwrap_or_default() {
+            "" => None,
+            s => match s.parse::<f64>() {
+                Ok(score) => Some(score),
+                Err(_) => {
+                    self.message = "Score must be a number!".to_string();
+                    return;
+                },
+            },
+        };
+        let attributes = match Details::parse_attributes(self.entry_attributes_input.lines().iter().map(String::as_str)) {
+            Ok(attributes) => attributes,
+            Err(e) => {
+                self.message = e;
+                return;
+            },
+        };
+        let details = Details {
+            notes: self.entry_notes_input.lines().join("\n").trim_end().to_string(),
+            tags: Details::parse_tags(&self.entry_tags_input.lines().join(",")),
+            attributes,
+        };
+        if let (Some(ldb), Some(id)) = (&mut self.current_leaderboard, self.edited_entry) {
+            match ldb.update_entry(id, &name, score, details) {
+                Ok(()) => self.message = format!("Saved {}", name.trim()),
+                Err(e) => {
+                    self.message = e;
+                    return;
+                },
+            }
+        }
+        self.close_entry_editor();
+    }
+
+    fn close_entry_editor(&mut self) {
+        self.edited_entry = None;
+        self.state = AppState::Show;
+        self.edit_field = EditField::Name;
+        self.entry_name_input = TextArea::default();
+        self.entry_rank_input = TextArea::default();
+        self.entry_tags_input = TextArea::default();
+        self.entry_notes_input = TextArea::default();
+        self.entry_attributes_input = TextArea::default();
+    }
+
+    /// Exports the current leaderboard to `<name>.csv` in the export directory.
+    fn export_csv(&mut self) {
+        let Some(ldb) = &self.current_leaderboard else { return };
+        let file_location = self.export_dir.join(format!("{}.csv", ldb.name()));
+        let file_location = file_location.to_string_lossy();
+        self.message = match ldb.serialize_to_csv() {
+            Ok(csv) => match std::fs::create_dir_all(&self.export_dir).and_then(|()| write_to_file(&csv, &file_location)) {
+                Ok(()) => format!("Exported to {}", file_location),
+                Err(e) => format!("Unable to write {}: {}", file_location, e),
+            },
+            Err(e) => format!("Unable to export leaderboard: {}", e),
+        };
+        self.logger.write(self.message.clone());
+    }
+
+    fn import_csv(&mut self, file_location: &str) {
+        let Some(ldb) = &mut self.current_leaderboard else { return };
+        self.message = match read_from_file(file_location) {
+            Ok(csv) => match ldb.import_from_csv(&csv, self.import_mode) {
+                Ok(report) => report.to_string(),
+                Err(e) => format!("Unable to import {}: {}", file_location, e),
+            },
+            Err(e) => format!("Unable to read {}: {}", file_location, e),
+        };
+        self.logger.write(self.message.clone());
+        self.clamp_current_entry();
+    }
+
     fn quit(&mut self) {
         self.running = false;
     }
@@ -438,7 +2519,7 @@ impl <'a> App <'_> {
         if !self.leaderboard_names.is_empty() {
             if self.current_leaderboard_index > 0 {
                 self.current_leaderboard_index -= 1;
-                match Leaderboard::open_leaderboard(&self.leaderboard_names[self.current_leaderboard_index]) {
+                match self.open_leaderboard(self.current_leaderboard_index) {
                     Ok(ldb) => self.current_leaderboard = Some(ldb),
                     Err(err) => self.logger.write(format!("Unable to open previous leeaderboard: {}", err)),
                 }
@@ -449,15 +2530,9 @@ impl <'a> App <'_> {
     }
 
     fn show_prev_entry(&mut self) -> Result<(), Box<dyn Error>> {
-        match &self.current_leaderboard {
-            Some(ldb) => {
-                if !ldb.is_empty() {
-                    if self.current_entry > 0 {
-                        self.current_entry -= 1;
-                    }
-                }
-            },
-            None => {}
+        if let Some(ldb) = &self.current_leaderboard
+            && !ldb.is_empty() && self.current_entry > 0 {
+            self.current_entry -= 1;
         }
         Ok(())
     }
@@ -466,7 +2541,7 @@ impl <'a> App <'_> {
         if !self.leaderboard_names.is_empty() {
             if self.current_leaderboard_index < self.leaderboard_names.len()-1 {
                 self.current_leaderboard_index += 1;
-                match Leaderboard::open_leaderboard(&self.leaderboard_names[self.current_leaderboard_index]) {
+                match self.open_leaderboard(self.current_leaderboard_index) {
                     Ok(ldb) => self.current_leaderboard = Some(ldb),
                     Err(err) => self.logger.write(format!("Unable to open next leeaderboard: {}", err)),
                 }
@@ -476,34 +2551,306 @@ impl <'a> App <'_> {
         Ok(())
     }
     
-    fn show_next_entry(&mut self) -> Result<(), Box<dyn Error>> {
+    /// Positions of the entries of the current leaderboard matching the
+    /// search query.
+    fn search_matches(&self) -> Vec<usize> {
         match &self.current_leaderboard {
-            Some(ldb) => {
-                if !ldb.is_empty() {
-                    if self.current_entry < ldb.len()-1 {
-                        self.current_entry += 1;
-                    }
-                }
-            },
-            None => {}
+            Some(ldb) => find_matches(ldb.entries(), &self.search_query, self.search_mode),
+            None => Vec::new(),
+        }
+    }
+
+    /// Selects the first match at or after `from`, or at or before it when
+    /// searching backwards, wrapping around the ends of the leaderboard.
+    fn jump_to_match(&mut self, from: usize, forward: bool) {
+        let matches = self.search_matches();
+        let found = if forward {
+            matches.iter().find(|&&position| position >= from).or(matches.first())
+        } else {
+            matches.iter().rev().find(|&&position| position <= from).or(matches.last())
         };
+        let Some(&position) = found else { return };
+        self.current_entry = position;
+        self.list_state.select(Some(position));
+        let index = matches.iter().position(|&m| m == position).unwrap_or(0);
+        self.message = format!("Match {} of {}", index + 1, matches.len());
+    }
+
+    /// Keeps the selection inside the list after entries disappear.
+    fn clamp_current_entry(&mut self) {
+        if let Some(ldb) = &self.current_leaderboard {
+            self.current_entry = std::cmp::min(self.current_entry, ldb.len().saturating_sub(1));
+        }
+    }
+
+    fn show_next_entry(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(ldb) = &self.current_leaderboard
+            && !ldb.is_empty() && self.current_entry < ldb.len()-1 {
+            self.current_entry += 1;
+        }
         Ok(())
     }
 
 }
 
-impl Drop for App <'_> {
-    fn drop(&mut self) {
-        let v : Vec<&String> = Vec::from_iter(&self.leaderboard_names);
-        let ldb_names: Vec<String> = v.into_iter().cloned().collect();
-        let mut hmap: HashMap<String, Vec<String>> = HashMap::new();
-        hmap.insert("leaderboards".to_owned(), ldb_names);
-        match serde_json::to_string(&hmap) {
-            Ok(s) => write_to_file(&s, "Leaderboards/Leaderboards.json")
-                            .unwrap_or_else(|e|
-                                self.logger.write(format!("Unable to write Leaderboard to file: {}", e)),
-                            ),
-            Err(err) => self.logger.write(format!("Unable to encode Leaderboard: {}", err)),
-        }
-    }
-}
\ No newline at end of file
+
+/// Narrowest leaderboard area that still has room for the detail panel.
+const DETAILS_MIN_WIDTH: u16 = 60;
+
+/// A movement arrow to put after an entry.
+fn movement_span(movement: Movement) -> Span<'static> {
+    let text = format!(" {}", movement);
+    match movement {
+        Movement::New => text.cyan(),
+        Movement::Up(_) => text.green(),
+        Movement::Down(_) => text.red(),
+        Movement::Same => text.into(),
+    }
+}
+
+/// Everything known about an entry, as lines for the detail panel.
+fn entry_lines(node: &Node, rank: &str, baseline: Option<i64>) -> Vec<Line<'static>> {
+    let time = |at: Option<i64>| at.map_or("unknown".to_string(), format_time);
+    let mut lines = vec![
+        Line::from(vec!["Id: ".bold(), node.id.to_string().into()]),
+        Line::from(vec!["Rank: ".bold(), rank.to_string().into()]),
+    ];
+    if let Some(score) = node.score {
+        lines.push(Line::from(vec!["Score: ".bold(), score.to_string().into()]));
+    }
+    lines.push(Line::from(vec!["Added: ".bold(), time(node.added_at).into()]));
+    lines.push(Line::from(vec!["Last moved: ".bold(), time(node.moved_at()).into()]));
+    if let Some(at) = baseline {
+        let movement = match node.movement_since(at) {
+            Movement::Same => "unchanged".to_string(),
+            movement => movement.to_string(),
+        };
+        lines.push(Line::from(vec![format!("Since {}: ", format_time(at)).bold(), movement.into()]));
+    }
+    lines.extend(detail_lines(&node.details));
+    if !node.rank_history.is_empty() {
+        lines.push(Line::raw(""));
+        lines.push(Line::from("Rank history".bold()));
+        for change in node.rank_history.iter().rev() {
+            lines.push(Line::raw(format!("{}  {} -> {} ({})", format_time(change.at), change.from, change.to, change.cause)));
+        }
+    }
+    lines
+}
+
+/// The notes, tags and attributes of an entry as lines for the detail panel.
+fn detail_lines(details: &Details) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    if !details.tags.is_empty() {
+        let tags: Vec<&str> = details.tags.iter().map(String::as_str).collect();
+        lines.push(Line::from(vec!["Tags: ".bold(), tags.join(", ").into()]));
+    }
+    for (key, value) in &details.attributes {
+        lines.push(Line::from(vec![format!("{}: ", key).bold(), value.clone().into()]));
+    }
+    if !details.notes.is_empty() {
+        lines.push(Line::raw(""));
+        lines.extend(details.notes.lines().map(|line| Line::raw(line.to_string())));
+    }
+    lines
+}
+
+/// Draws every key of the leaderboard view over `area`, in two columns.
+fn render_help(frame: &mut Frame, area: Rect) {
+    let rows = KEY_BINDINGS.len().div_ceil(2);
+    let popup = centered(area, 80, (rows as u16 + 2).min(area.height));
+    let block = Block::bordered()
+        .title(Line::from(" Keys ").bold().centered())
+        .title_bottom(Line::from(vec![" Close".into(), "<any key> ".blue().bold()]).centered());
+    frame.render_widget(Clear, popup);
+    let columns = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(block.inner(popup));
+    frame.render_widget(block, popup);
+    for (column, bindings) in columns.iter().zip(KEY_BINDINGS.chunks(rows)) {
+        let lines: Vec<Line> = bindings.iter()
+            .map(|(key, action)| Line::from(vec![format!(" {:<9}", key).blue().bold(), action.to_string().into()]))
+            .collect();
+        frame.render_widget(Paragraph::new(lines), *column);
+    }
+}
+
+/// A rectangle `percent_x` percent as wide as `area` and `height` rows high,
+/// centered in it.
+fn centered(area: Rect, percent_x: u16, height: u16) -> Rect {
+    let [row] = Layout::vertical([Constraint::Length(height)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    let [popup] = Layout::horizontal([Constraint::Percentage(percent_x)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(row);
+    popup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn app(dir: &tempfile::TempDir, boards: &[&str]) -> App<'static> {
+        let log = Log::new(dir.path().join("app.log")).unwrap();
+        let mut app = App::new(log, Rc::new(MemoryStorage::new()), UiConfig::default()).unwrap();
+        for board in boards {
+            app.new_leaderboard(board).unwrap();
+        }
+        app
+    }
+
+    #[test]
+    fn history_lasts_for_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &["a", "b"]);
+        app.select_leaderboard(0).unwrap().new_entry("X", 1).unwrap();
+        app.select_leaderboard(1).unwrap().new_entry("Y", 1).unwrap();
+        app.show_prev_leaderboard().unwrap();
+        app.current_leaderboard.as_mut().unwrap().undo().unwrap();
+        assert!(app.current().unwrap().is_empty());
+        app.show_next_leaderboard().unwrap();
+        app.current_leaderboard.as_mut().unwrap().undo().unwrap();
+        assert!(app.current().unwrap().is_empty());
+
+        // Renaming keeps the history too.
+        app.current_leaderboard.as_mut().unwrap().redo().unwrap();
+        app.rename_leaderboard(1, "c").unwrap();
+        app.current_leaderboard.as_mut().unwrap().undo().unwrap();
+        assert!(app.current().unwrap().is_empty());
+    }
+
+    #[test]
+    fn failing_to_trash_a_leaderboard_keeps_it_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &["a", "b"]);
+        app.select_leaderboard(0).unwrap();
+        app.storage.remove_leaderboard("b").unwrap();
+        assert!(app.remove_leaderboard(1).is_err());
+        assert_eq!(app.leaderboard_names, ["a", "b"]);
+        assert_eq!(app.storage.read_index().unwrap(), ["a", "b"]);
+        assert_eq!(app.current().unwrap().name(), "a");
+
+        app.remove_leaderboard(0).unwrap();
+        assert_eq!(app.storage.read_index().unwrap(), ["b"]);
+        assert_eq!(app.storage.read_trash().unwrap()[0].name, "a");
+    }
+
+    #[test]
+    fn trash_view_is_read_when_opened_and_after_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &["a", "b"]);
+        let ldb = app.select_leaderboard(1).unwrap();
+        ldb.new_entry("X", 1).unwrap();
+        ldb.new_entry("Y", 2).unwrap();
+        ldb.remove(1).unwrap();
+        app.remove_leaderboard(0).unwrap();
+        app.load_trash_items();
+        assert_eq!(app.trash_items.len(), 2);
+        assert!(matches!(app.trash_items[1].0, TrashItem::Entry(1)));
+
+        // Deleting elsewhere leaves the list alone until it is read again.
+        app.current_leaderboard.as_mut().unwrap().remove(1).unwrap();
+        assert_eq!(app.trash_items.len(), 2);
+
+        app.trash_selected = 1;
+        app.restore_from_trash();
+        assert_eq!(app.current().unwrap().entries()[0].name, "X");
+        assert_eq!(app.trash_items.len(), 2);
+        assert!(matches!(app.trash_items[1].0, TrashItem::Entry(2)));
+        app.trash_selected = 0;
+        app.purge_from_trash();
+        assert_eq!(app.trash_items.len(), 1);
+        app.purge_from_trash();
+        assert!(app.trash_items.is_empty());
+    }
+
+    #[test]
+    fn snapshots_are_read_once_per_leaderboard() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &["a", "b"]);
+        app.select_leaderboard(0).unwrap();
+        assert!(app.snapshots().is_empty());
+        app.take_snapshot("first").unwrap();
+        app.load_snapshots().unwrap();
+        assert_eq!(app.snapshots().len(), 1);
+
+        // Snapshots written behind the App's back show up only when the
+        // cache is dropped, by a change made through the App.
+        snapshot::take_snapshot(app.storage.as_ref(), app.current().unwrap(), "second").unwrap();
+        app.load_snapshots().unwrap();
+        assert_eq!(app.snapshots().len(), 1);
+        app.remove_snapshot("first").unwrap();
+        assert!(app.snapshots().is_empty());
+        app.load_snapshots().unwrap();
+        assert_eq!(app.snapshots()[0].name, "second");
+
+        app.select_leaderboard(1).unwrap();
+        assert!(app.snapshots().is_empty());
+        app.load_snapshots().unwrap();
+        assert!(app.snapshots().is_empty());
+    }
+
+    #[test]
+    fn tournament_views_follow_recorded_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &["a"]);
+        let ldb = app.select_leaderboard(0).unwrap();
+        ldb.new_entry("X", 1).unwrap();
+        ldb.new_entry("Y", 2).unwrap();
+        app.create_tournament("cup", TournamentFormat::RoundRobin).unwrap();
+        app.load_tournaments().unwrap();
+        app.open_tournament = Some("cup".to_string());
+        assert_eq!(app.fixture_positions(), [(0, 0)]);
+        assert!(!app.shown_tournament().unwrap().is_finished());
+
+        app.record_selected_fixture(Outcome::FirstWins);
+        assert!(app.shown_tournament().unwrap().is_finished());
+        assert!(app.tournament("cup").unwrap().is_finished());
+        assert!(app.tournament("bowl").is_err());
+
+        app.remove_tournament("cup").unwrap();
+        assert!(app.shown_tournament().is_none());
+        assert!(app.fixture_positions().is_empty());
+    }
+
+    fn screen(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content().chunks(width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn instructions_follow_the_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &[]);
+        assert_eq!(app.instructions().to_string(), " New Leaderboard<Ctrl+l>  Help<?>  Quit<Ctrl+c> ");
+        app.new_leaderboard("a").unwrap();
+        app.select_leaderboard(0).unwrap();
+        assert!(app.instructions().to_string().contains(" Help<?> "));
+        app.state = AppState::Search;
+        assert_eq!(app.instructions().to_string(), " Mode<Tab>  Done<Enter>  Cancel<Esc> ");
+        app.state = AppState::Show;
+        app.search_query = "x".to_string();
+        assert!(app.instructions().to_string().starts_with(" Next/Prev Match<n/N> "));
+        // The keys fit under the list on a 120 column terminal.
+        app.search_query.clear();
+        assert!(app.instructions().width() <= 116, "{}", app.instructions());
+    }
+
+    #[test]
+    fn help_lists_every_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, &["a"]);
+        app.select_leaderboard(0).unwrap();
+        app.state = AppState::Help;
+        let screen = screen(&mut app, 120, 40);
+        for (key, action) in KEY_BINDINGS {
+            assert!(screen.contains(action), "{} is missing from\n{}", action, screen);
+            assert!(screen.contains(key), "{} is missing from\n{}", key, screen);
+        }
+    }
+}
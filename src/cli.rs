@@ -0,0 +1,580 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
+
+use crate::app::App;
+use crate::composite::AggregationMethod;
+use crate::config::Config;
+use crate::leaderboard::{ImportMode, Leaderboard, RankingMode};
+use crate::log::Log;
+use crate::matches::{format_standings, parse_participants};
+use crate::node::ID;
+use crate::rating::{Outcome, RatingSystem};
+use crate::tournament::TournamentFormat;
+use crate::search::MatchMode;
+use crate::trash::format_time;
+use crate::sqlite_storage::SqliteStorage;
+use crate::storage::{copy_store, read_from_file, write_to_file, Format};
+
+/// Keep ranked lists of anything. Runs the terminal UI when no command is given.
+#[derive(Parser, Debug)]
+#[command(name = "leaderboard-app", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Config file to use instead of the one in the XDG config directory
+    #[arg(long, global = true, env = "LEADERBOARD_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Directory the leaderboards are stored in
+    #[arg(long, global = true, env = "LEADERBOARD_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Format leaderboards are saved in, existing files are read in any format
+    #[arg(long, global = true, value_enum)]
+    pub format: Option<Format>,
+    /// Keep all leaderboards in this SQLite database instead of the data
+    /// directory
+    #[arg(long, global = true)]
+    pub database: Option<PathBuf>,
+}
+
+/// Result of a tournament fixture as given on the command line.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum FixtureResult {
+    /// The first player listed won
+    First,
+    /// The second player listed won
+    Second,
+    Draw,
+}
+
+impl From<FixtureResult> for Outcome {
+    fn from(result: FixtureResult) -> Self {
+        match result {
+            FixtureResult::First => Outcome::FirstWins,
+            FixtureResult::Second => Outcome::SecondWins,
+            FixtureResult::Draw => Outcome::Draw,
+        }
+    }
+}
+
+impl Cli {
+    /// Loads the config file and applies the command line overrides to it.
+    pub fn config(&self) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if let Some(data_dir) = &self.data_dir {
+            config.data_dir = data_dir.clone();
+            config.legacy_data_dir = false;
+        }
+        if let Some(format) = self.format {
+            config.format = format;
+        }
+        if let Some(database) = &self.database {
+            config.database = Some(std::path::absolute(database)?);
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// List all leaderboards
+    List,
+    /// Print the entries of a leaderboard
+    Show {
+        board: String,
+    },
+    /// Add an entry to a leaderboard
+    Add {
+        board: String,
+        name: String,
+        /// Rank to insert at, defaults to the bottom of the board
+        #[arg(long)]
+        rank: Option<usize>,
+        /// Score of the entry, used to place it on score ranked boards
+        #[arg(long)]
+        score: Option<f64>,
+        /// Place the entry by answering which of two entries ranks higher
+        #[arg(long, conflicts_with_all = ["rank", "score"])]
+        compare: bool,
+    },
+    /// Rank every entry of a leaderboard by answering which of two entries
+    /// ranks higher
+    Sort {
+        board: String,
+    },
+    /// Remove the entry at a rank
+    Remove {
+        board: String,
+        rank: usize,
+    },
+    /// Move the entry at a rank to another rank
+    Move {
+        board: String,
+        rank: usize,
+        to: usize,
+    },
+    /// Create an empty leaderboard
+    CreateBoard {
+        name: String,
+        /// Rank the entries by the results of games between them, rated
+        /// with this system
+        #[arg(long, value_enum)]
+        rated: Option<RatingSystem>,
+    },
+    /// Create a leaderboard that combines the rankings of other leaderboards,
+    /// matching their entries by name. It can not be edited and follows
+    /// changes to its sources.
+    CreateComposite {
+        name: String,
+        /// Leaderboards to combine
+        #[arg(required = true, num_args = 2..)]
+        sources: Vec<String>,
+        /// How the rankings are combined
+        #[arg(long, value_enum, default_value_t = AggregationMethod::Borda)]
+        method: AggregationMethod,
+    },
+    /// Delete a leaderboard and its file
+    DeleteBoard {
+        name: String,
+    },
+    /// Rename a leaderboard
+    RenameBoard {
+        name: String,
+        to: String,
+    },
+    /// Copy a leaderboard to a new one
+    DuplicateBoard {
+        name: String,
+        to: String,
+    },
+    /// Find entries by name across every leaderboard
+    Find {
+        query: String,
+        /// How names are matched
+        #[arg(long, value_enum, default_value_t = MatchMode::IgnoreCase)]
+        mode: MatchMode,
+    },
+    /// Save the current ranking of a leaderboard under a name
+    Snapshot {
+        board: String,
+        name: String,
+    },
+    /// List the snapshots of a leaderboard
+    Snapshots {
+        board: String,
+    },
+    /// Show what changed between a snapshot and another snapshot, or the
+    /// leaderboard as it is now
+    Diff {
+        board: String,
+        from: String,
+        to: Option<String>,
+    },
+    /// Record a game on a leaderboard ranked by match results
+    Play {
+        board: String,
+        /// Name of the entry that won
+        winner: String,
+        /// Name of the entry that lost
+        loser: String,
+        /// The game was a draw
+        #[arg(long)]
+        draw: bool,
+    },
+    /// List the games played on a leaderboard, oldest first
+    Games {
+        board: String,
+    },
+    /// Record a match between entries of a leaderboard
+    AddMatch {
+        board: String,
+        /// Result of each participant as "name: score"
+        #[arg(required = true, num_args = 2..)]
+        results: Vec<String>,
+        #[arg(long, default_value = "")]
+        notes: String,
+    },
+    /// Replace the results and notes of a match
+    EditMatch {
+        board: String,
+        id: usize,
+        /// Result of each participant as "name: score"
+        #[arg(required = true, num_args = 2..)]
+        results: Vec<String>,
+        #[arg(long, default_value = "")]
+        notes: String,
+    },
+    /// Delete a match
+    RemoveMatch {
+        board: String,
+        id: usize,
+    },
+    /// List the matches of a leaderboard, oldest first
+    Matches {
+        board: String,
+    },
+    /// Print the wins, draws, losses and points of every entry
+    Standings {
+        board: String,
+    },
+    /// Draw a tournament between the entries of a leaderboard, seeded by
+    /// their ranks
+    CreateTournament {
+        board: String,
+        name: String,
+        /// Format of the tournament
+        #[arg(long, value_enum, default_value_t = TournamentFormat::RoundRobin)]
+        kind: TournamentFormat,
+    },
+    /// List the tournaments seeded from a leaderboard
+    Tournaments {
+        board: String,
+    },
+    /// Print the bracket, standings and fixtures of a tournament
+    Tournament {
+        board: String,
+        name: String,
+    },
+    /// Record the result of a tournament fixture, numbered as in the
+    /// fixture list
+    ReportResult {
+        board: String,
+        name: String,
+        round: usize,
+        fixture: usize,
+        /// Which player of the fixture won
+        #[arg(value_enum)]
+        result: FixtureResult,
+    },
+    /// Delete a tournament
+    RemoveTournament {
+        board: String,
+        name: String,
+    },
+    /// Rank a leaderboard by the standings of a tournament
+    WritePlacements {
+        board: String,
+        name: String,
+        /// Leaderboard to write to, created if needed. Defaults to the one
+        /// the tournament was seeded from.
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Write a leaderboard as CSV, with its notes, tags and a column per
+    /// attribute
+    Export {
+        board: String,
+        /// File to write to, defaults to stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Import entries from a CSV file, creating the leaderboard if needed.
+    /// Columns other than rank, name, id, score, notes and tags are read as
+    /// attributes. Exits with 3 when some rows were skipped and the rest
+    /// imported.
+    Import {
+        board: String,
+        file: PathBuf,
+        /// Replace the existing entries instead of merging with them
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Rewrite every leaderboard in another file format
+    Convert {
+        #[arg(value_enum)]
+        to: Format,
+    },
+    /// Copy the Leaderboards directory into a SQLite database
+    MigrateSqlite {
+        database: PathBuf,
+    },
+}
+
+/// Exit code of an import that skipped some rows but imported the others.
+pub const PARTIAL_IMPORT: u8 = 3;
+
+/// Runs a single command against the stored leaderboards, printing errors to
+/// stderr. Returns the exit code for the process.
+pub fn run(command: Command, config: &Config, log: Log) -> ExitCode {
+    match execute(command, config, log) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn execute(command: Command, config: &Config, log: Log) -> Result<ExitCode, Box<dyn Error>> {
+    // Converting and migrating rewrite stores directly, so they must not run
+    // next to an App that saves its open leaderboard when dropped.
+    match command {
+        Command::Convert { to } => {
+            for name in config.dir_storage().convert(to)? {
+                println!("Converted {}", name);
+            }
+            return Ok(ExitCode::SUCCESS);
+        },
+        Command::MigrateSqlite { database } => {
+            let target = SqliteStorage::open(&database)?;
+            for name in copy_store(&config.dir_storage(), &target)? {
+                println!("Migrated {}", name);
+            }
+            return Ok(ExitCode::SUCCESS);
+        },
+        _ => {},
+    }
+    let mut app = App::headless(log, config.storage()?, config.ui.clone())?;
+    match command {
+        Command::List => {
+            for name in app.leaderboard_names() {
+                println!("{}", name);
+            }
+        },
+        Command::Show { board } => {
+            let boards = app.leaderboard_names().to_vec();
+            let ldb = view(&mut app, &board)?;
+            ldb.display();
+            if let Some(composite) = ldb.composite() {
+                let sources: Vec<String> = composite.sources.iter()
+                    .map(|source| if boards.contains(source) { source.clone() } else { format!("{} (missing)", source) })
+                    .collect();
+                println!("Combined from {} by {}", sources.join(", "), composite.method);
+                for node in ldb.entries() {
+                    let ranks: Vec<String> = node.details.attributes.iter()
+                        .map(|(source, rank)| format!("{} {}", source, rank))
+                        .collect();
+                    println!("{}: {}", node.name, ranks.join(", "));
+                }
+            }
+        },
+        Command::Add { board, name, rank, score, compare: false } => {
+            let ldb = select(&mut app, &board)?;
+            let rank = rank.unwrap_or(ldb.len() + 1);
+            ldb.new_scored_entry(&name, rank, score)?;
+        },
+        Command::Add { board, name, compare: true, .. } => {
+            let ldb = select(&mut app, &board)?;
+            let rank = ldb.insert_by_comparison(&name, |node| ask(&format!("Does {} rank above {}?", name, node.name)))?;
+            println!("Added {} at rank {}", name, rank);
+        },
+        Command::Sort { board } => {
+            let ldb = select(&mut app, &board)?;
+            let asked = ldb.sort_by_comparison(|a, b| ask(&format!("Does {} rank above {}?", a.name, b.name)))?;
+            println!("Sorted {} with {} comparisons", ldb.name(), asked);
+        },
+        Command::Remove { board, rank } => {
+            select(&mut app, &board)?.remove(rank)?;
+        },
+        Command::Move { board, rank, to } => {
+            select(&mut app, &board)?.change_rank(rank, to)?;
+        },
+        Command::CreateBoard { name, rated } => {
+            let ldb = app.new_leaderboard(&name)?;
+            if let Some(system) = rated {
+                let tie_rule = ldb.tie_rule();
+                ldb.with_ranking(RankingMode::Rated, tie_rule)
+                    .with_rating_system(system)
+                    .save_leaderboard()?;
+            }
+        },
+        Command::CreateComposite { name, sources, method } => {
+            let ldb = app.new_composite(&name, &sources, method)?;
+            println!("Created {} with {} entries", ldb.name(), ldb.len());
+        },
+        Command::DeleteBoard { name } => {
+            let index = find(&app, &name)?;
+            app.remove_leaderboard(index)?;
+        },
+        Command::RenameBoard { name, to } => {
+            let index = find(&app, &name)?;
+            app.rename_leaderboard(index, &to)?;
+        },
+        Command::DuplicateBoard { name, to } => {
+            let index = find(&app, &name)?;
+            app.duplicate_leaderboard(index, &to)?;
+        },
+        Command::Find { query, mode } => {
+            let hits = app.search_all(&query, mode)?;
+            if hits.is_empty() {
+                return Err(format!("No entries match {}", query).into());
+            }
+            for hit in hits {
+                println!("{}", hit);
+            }
+        },
+        Command::Snapshot { board, name } => {
+            select(&mut app, &board)?;
+            app.take_snapshot(&name)?;
+        },
+        Command::Snapshots { board } => {
+            view(&mut app, &board)?;
+            app.load_snapshots()?;
+            for snapshot in app.snapshots() {
+                println!("{} ({}, {} entries)", snapshot.name, format_time(snapshot.taken_at), snapshot.entries.len());
+            }
+        },
+        Command::Diff { board, from, to } => {
+            view(&mut app, &board)?;
+            print!("{}", app.compare_snapshots(&from, to.as_deref())?);
+        },
+        Command::Play { board, winner, loser, draw } => {
+            let ldb = select(&mut app, &board)?;
+            let winner = entry_id(ldb, &winner)?;
+            let loser = entry_id(ldb, &loser)?;
+            ldb.record_game(winner, loser, if draw { Outcome::Draw } else { Outcome::FirstWins })?;
+        },
+        Command::Games { board } => {
+            let ldb = view(&mut app, &board)?;
+            let name = |id: ID| ldb.entry_name(id).map_or(format!("#{}", id), str::to_string);
+            for game in ldb.games() {
+                let (first, second) = (name(game.first), name(game.second));
+                let result = match game.outcome {
+                    Outcome::FirstWins => format!("{} beat {}", first, second),
+                    Outcome::SecondWins => format!("{} beat {}", second, first),
+                    Outcome::Draw => format!("{} drew with {}", first, second),
+                };
+                println!("{}: {}", format_time(game.played_at), result);
+            }
+        },
+        Command::AddMatch { board, results, notes } => {
+            let participants = parse_participants(select(&mut app, &board)?, results.iter().map(String::as_str))?;
+            println!("Recorded match {}", app.add_match(participants, &notes)?.id);
+        },
+        Command::EditMatch { board, id, results, notes } => {
+            let participants = parse_participants(select(&mut app, &board)?, results.iter().map(String::as_str))?;
+            app.update_match(id, participants, &notes)?;
+        },
+        Command::RemoveMatch { board, id } => {
+            select(&mut app, &board)?;
+            app.remove_match(id)?;
+        },
+        Command::Matches { board } => {
+            view(&mut app, &board)?;
+            app.load_matches()?;
+            for m in app.matches() {
+                println!("{} {}: {}", m.id, format_time(m.played_at), app.describe_match(m));
+                for line in m.notes.lines() {
+                    println!("    {}", line);
+                }
+            }
+        },
+        Command::Standings { board } => {
+            view(&mut app, &board)?;
+            for line in format_standings(&app.standings()?) {
+                println!("{}", line);
+            }
+        },
+        Command::CreateTournament { board, name, kind } => {
+            select(&mut app, &board)?;
+            for line in app.create_tournament(&name, kind)?.report() {
+                println!("{}", line);
+            }
+        },
+        Command::Tournaments { board } => {
+            view(&mut app, &board)?;
+            app.load_tournaments()?;
+            for tournament in app.tournaments() {
+                let status = if tournament.is_finished() { "finished" } else { "in progress" };
+                println!("{} ({}, {} players, {})", tournament.name, tournament.format, tournament.players.len(), status);
+            }
+        },
+        Command::Tournament { board, name } => {
+            view(&mut app, &board)?;
+            for line in app.tournament(&name)?.report() {
+                println!("{}", line);
+            }
+        },
+        Command::ReportResult { board, name, round, fixture, result } => {
+            select(&mut app, &board)?;
+            if round == 0 || fixture == 0 {
+                return Err("Rounds and fixtures are numbered from 1".into());
+            }
+            let tournament = app.record_tournament_result(&name, round - 1, fixture - 1, result.into())?;
+            println!("{}", tournament.describe(&tournament.rounds[round - 1][fixture - 1]));
+        },
+        Command::RemoveTournament { board, name } => {
+            select(&mut app, &board)?;
+            app.remove_tournament(&name)?;
+        },
+        Command::WritePlacements { board, name, to } => {
+            select(&mut app, &board)?;
+            app.write_placements(&name, to.as_deref().unwrap_or(&board))?;
+        },
+        Command::Export { board, output } => {
+            let csv = view(&mut app, &board)?.serialize_to_csv()?;
+            match output {
+                Some(path) => write_to_file(&csv, &path.to_string_lossy())?,
+                None => print!("{}", csv),
+            }
+        },
+        Command::Import { board, file, replace } => {
+            let csv = read_from_file(&file.to_string_lossy())?;
+            if app.find_leaderboard(&board).is_none() {
+                app.new_leaderboard(&board)?;
+            }
+            let mode = if replace { ImportMode::Replace } else { ImportMode::Merge };
+            let report = select(&mut app, &board)?.import_from_csv(&csv, mode)?;
+            println!("Imported {} entries into {}", report.imported, board);
+            for error in &report.errors {
+                eprintln!("skipped {}", error);
+            }
+            if !report.errors.is_empty() {
+                eprintln!("warning: {} rows could not be imported", report.errors.len());
+                return Ok(ExitCode::from(PARTIAL_IMPORT));
+            }
+        },
+        Command::Convert { .. } | Command::MigrateSqlite { .. } =>
+            unreachable!("handled before the App is created"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The id of the entry called `name`, which must be unique on the board.
+fn entry_id(ldb: &Leaderboard, name: &str) -> Result<ID, Box<dyn Error>> {
+    let mut found = ldb.entries().iter().filter(|node| node.name == name);
+    match (found.next(), found.next()) {
+        (Some(node), None) => Ok(node.id),
+        (Some(_), Some(_)) => Err(format!("{} has several entries named {}", ldb.name(), name).into()),
+        (None, _) => Err(format!("{} has no entry named {}", ldb.name(), name).into()),
+    }
+}
+
+fn find(app: &App, board: &str) -> Result<usize, Box<dyn Error>> {
+    app.find_leaderboard(board)
+        .ok_or_else(|| format!("No leaderboard named {}", board).into())
+}
+
+/// Makes a copy of `board` the current leaderboard of `app`, for commands
+/// that only read it.
+fn view<'b>(app: &'b mut App, board: &str) -> Result<&'b Leaderboard, Box<dyn Error>> {
+    let index = find(app, board)?;
+    app.view_leaderboard(index)
+}
+
+/// Makes `board` the current leaderboard of `app`. Edits go through the App's
+/// own copy, which saves itself after every change.
+fn select<'b>(app: &'b mut App, board: &str) -> Result<&'b mut Leaderboard, Box<dyn Error>> {
+    let index = find(app, board)?;
+    app.select_leaderboard(index)
+}
+
+/// Asks `question` on the terminal until it is answered with yes or no.
+fn ask(question: &str) -> Result<bool, Box<dyn Error>> {
+    let mut line = String::new();
+    loop {
+        print!("{} [y/n] ", question);
+        std::io::stdout().flush()?;
+        line.clear();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Err("No answer given".into());
+        }
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => {},
+        }
+    }
+}
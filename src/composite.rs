@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::Leaderboard;
+use crate::node::{Node, Rank};
+use crate::storage::Storage;
+
+/// Consensus orders up to this many entries are found by trying every order,
+/// longer ones by improving the Borda order.
+const KEMENY_EXACT_LIMIT: usize = 8;
+
+/// How the rankings of the source leaderboards are combined.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AggregationMethod {
+    /// Each source gives an entry a point for every entry ranked below it.
+    #[default]
+    Borda,
+    /// Lowest average rank first.
+    AverageRank,
+    /// Lowest median rank first.
+    MedianRank,
+    /// The order that agrees with the most pairwise preferences of the
+    /// sources.
+    Kemeny,
+}
+
+impl AggregationMethod {
+    /// The method that follows this one, used to cycle through methods in
+    /// the UI.
+    pub fn next(&self) -> AggregationMethod {
+        match self {
+            AggregationMethod::Borda => AggregationMethod::AverageRank,
+            AggregationMethod::AverageRank => AggregationMethod::MedianRank,
+            AggregationMethod::MedianRank => AggregationMethod::Kemeny,
+            AggregationMethod::Kemeny => AggregationMethod::Borda,
+        }
+    }
+
+    pub fn previous(&self) -> AggregationMethod {
+        match self {
+            AggregationMethod::Borda => AggregationMethod::Kemeny,
+            AggregationMethod::AverageRank => AggregationMethod::Borda,
+            AggregationMethod::MedianRank => AggregationMethod::AverageRank,
+            AggregationMethod::Kemeny => AggregationMethod::MedianRank,
+        }
+    }
+}
+
+impl std::fmt::Display for AggregationMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregationMethod::Borda => write!(f, "Borda count"),
+            AggregationMethod::AverageRank => write!(f, "average rank"),
+            AggregationMethod::MedianRank => write!(f, "median rank"),
+            AggregationMethod::Kemeny => write!(f, "Kemeny consensus"),
+        }
+    }
+}
+
+/// Where the entries of a composite leaderboard come from. Entries of the
+/// sources are matched by name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Composite {
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub method: AggregationMethod,
+}
+
+/// An entry of a composite leaderboard as combined from its sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combined {
+    pub name: String,
+    /// Borda points, average or median rank. Kemeny orders have no score.
+    pub score: Option<f64>,
+    /// The entry's rank on each source, `None` where it is missing.
+    pub ranks: Vec<(String, Option<Rank>)>,
+}
+
+/// A source leaderboard's ranking: the rank of each name, and how many
+/// entries it has.
+struct Ballot {
+    ranks: HashMap<String, Rank>,
+    len: usize,
+}
+
+impl Ballot {
+    fn new(entries: &[Node]) -> Self {
+        let mut ranks = HashMap::new();
+        for node in entries {
+            // Names listed twice count with their better rank.
+            ranks.entry(node.name.clone()).or_insert(node.rank);
+        }
+        Ballot { ranks, len: entries.len() }
+    }
+
+    /// The rank of `name`, counting missing entries as ranked right below
+    /// the last one.
+    fn rank_or_last(&self, name: &str) -> Rank {
+        self.ranks.get(name).copied().unwrap_or(self.len + 1)
+    }
+
+    /// Whether `a` ranks above `b`, `None` when neither is on the source.
+    fn prefers(&self, a: &str, b: &str) -> Option<bool> {
+        match (self.ranks.get(a), self.ranks.get(b)) {
+            (None, None) => None,
+            _ => Some(self.rank_or_last(a) < self.rank_or_last(b)),
+        }
+    }
+}
+
+/// Combines the rankings of `sources`, given as name and entries in rank
+/// order, into one ranking, best first. Entries missing from a source get
+/// no Borda points from it and count as ranked below its last entry.
+pub fn combine(method: AggregationMethod, sources: &[(String, Vec<Node>)]) -> Vec<Combined> {
+    let ballots: Vec<Ballot> = sources.iter().map(|(_, entries)| Ballot::new(entries)).collect();
+    let mut names: Vec<String> = Vec::new();
+    for (_, entries) in sources {
+        for node in entries {
+            if !names.contains(&node.name) {
+                names.push(node.name.clone());
+            }
+        }
+    }
+    let ranks_of = |name: &str| -> Vec<Rank> { ballots.iter().map(|ballot| ballot.rank_or_last(name)).collect() };
+    let scored: Vec<(String, Option<f64>)> = match method {
+        AggregationMethod::Borda => {
+            let mut scored: Vec<(String, f64)> = names.into_iter()
+                .map(|name| {
+                    let points = ballots.iter()
+                        .filter_map(|ballot| ballot.ranks.get(&name).map(|&rank| ballot.len.saturating_sub(rank)))
+                        .sum::<usize>();
+                    (name, points as f64)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scored.into_iter().map(|(name, points)| (name, Some(points))).collect()
+        },
+        AggregationMethod::AverageRank | AggregationMethod::MedianRank => {
+            let mut scored: Vec<(String, f64)> = names.into_iter()
+                .map(|name| {
+                    let ranks = ranks_of(&name);
+                    let rank = if method == AggregationMethod::AverageRank { average(&ranks) } else { median(ranks) };
+                    (name, (rank * 100.0).round() / 100.0)
+                })
+                .collect();
+            scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+            scored.into_iter().map(|(name, rank)| (name, Some(rank))).collect()
+        },
+        AggregationMethod::Kemeny => kemeny(&ballots, combine(AggregationMethod::Borda, sources))
+            .into_iter()
+            .map(|name| (name, None))
+            .collect(),
+    };
+    scored.into_iter()
+        .map(|(name, score)| {
+            let ranks = sources.iter().zip(&ballots)
+                .map(|((source, _), ballot)| (source.clone(), ballot.ranks.get(&name).copied()))
+                .collect();
+            Combined { name, score, ranks }
+        })
+        .collect()
+}
+
+fn average(ranks: &[Rank]) -> f64 {
+    ranks.iter().sum::<usize>() as f64 / ranks.len().max(1) as f64
+}
+
+fn median(mut ranks: Vec<Rank>) -> f64 {
+    ranks.sort();
+    match ranks.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => ranks[len / 2] as f64,
+        len => (ranks[len / 2 - 1] + ranks[len / 2]) as f64 / 2.0,
+    }
+}
+
+/// The order of the names of `start` that agrees with the most pairwise
+/// preferences of the ballots. Short lists are searched exhaustively, longer
+/// ones start from `start` and swap neighbours while that gains agreement,
+/// which is known as local Kemenization.
+fn kemeny(ballots: &[Ballot], start: Vec<Combined>) -> Vec<String> {
+    let names: Vec<String> = start.into_iter().map(|combined| combined.name).collect();
+    // agree[a][b]: how many ballots rank a above b.
+    let agree: Vec<Vec<usize>> = names.iter()
+        .map(|a| names.iter()
+            .map(|b| ballots.iter().filter(|ballot| ballot.prefers(a, b) == Some(true)).count())
+            .collect())
+        .collect();
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    if names.len() <= KEMENY_EXACT_LIMIT {
+        let mut best = (0, order.clone());
+        let mut prefix = Vec::new();
+        let mut remaining = order.clone();
+        search_orders(&agree, &mut prefix, &mut remaining, 0, &mut best);
+        order = best.1;
+    } else {
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 1..order.len() {
+                let (above, below) = (order[i - 1], order[i]);
+                if agree[below][above] > agree[above][below] {
+                    order.swap(i - 1, i);
+                    improved = true;
+                }
+            }
+        }
+    }
+    order.into_iter().map(|i| names[i].clone()).collect()
+}
+
+/// Tries every order that starts with `prefix`, keeping the one with the
+/// most agreement in `best`. The first order found wins ties, so the
+/// starting order is kept unless another one agrees more.
+fn search_orders(agree: &[Vec<usize>], prefix: &mut Vec<usize>, remaining: &mut Vec<usize>, score: usize, best: &mut (usize, Vec<usize>)) {
+    if remaining.is_empty() {
+        if score > best.0 {
+            *best = (score, prefix.clone());
+        }
+        return;
+    }
+    for position in 0..remaining.len() {
+        let next = remaining.remove(position);
+        let gained: usize = remaining.iter().map(|&below| agree[next][below]).sum();
+        prefix.push(next);
+        search_orders(agree, prefix, remaining, score + gained, best);
+        prefix.pop();
+        remaining.insert(position, next);
+    }
+}
+
+/// Combines the current rankings of the sources of `composite`. Sources
+/// missing from `boards`, the names of every leaderboard, are left out and
+/// returned next to the combined ranking.
+pub fn combine_sources(storage: &dyn Storage, composite: &Composite, boards: &[String]) -> Result<(Vec<Combined>, Vec<String>), Box<dyn Error>> {
+    let mut sources = Vec::new();
+    let mut missing = Vec::new();
+    for source in &composite.sources {
+        if boards.contains(source) {
+            let entries = storage.read_leaderboard(source)?.entries().to_vec();
+            sources.push((source.clone(), entries));
+        } else {
+            missing.push(source.clone());
+        }
+    }
+    Ok((combine(composite.method, &sources), missing))
+}
+
+/// Recombines the composite leaderboard `leaderboard` from the current
+/// rankings of its sources and saves it. Sources missing from `boards` are
+/// left out and returned. Composites of composites use the rankings their
+/// sources had when they were last opened.
+pub fn refresh(storage: &dyn Storage, leaderboard: &mut Leaderboard, boards: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let Some(composite) = leaderboard.composite() else {
+        return Err(format!("{} is not combined from other leaderboards", leaderboard.name()).into());
+    };
+    let (combined, missing) = combine_sources(storage, composite, boards)?;
+    leaderboard.set_combined_entries(combined)?;
+    Ok(missing)
+}
+
+/// The attributes showing an entry's rank on each source.
+pub(crate) fn source_attributes(combined: &Combined) -> BTreeMap<String, String> {
+    combined.ranks.iter()
+        .map(|(source, rank)| (source.clone(), rank.map_or("-".to_string(), |rank| format!("#{}", rank))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn source(name: &str, entries: &[&str]) -> (String, Vec<Node>) {
+        let nodes = entries.iter().enumerate()
+            .map(|(position, entry)| Node::new(entry, position + 1, position + 1, None))
+            .collect();
+        (name.to_string(), nodes)
+    }
+
+    fn names(combined: &[Combined]) -> Vec<&str> {
+        combined.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    fn scores(combined: &[Combined]) -> Vec<Option<f64>> {
+        combined.iter().map(|entry| entry.score).collect()
+    }
+
+    /// Three voters prefer A > B > C and two B > C > A: A beats both others
+    /// head to head, yet B gets the most Borda points.
+    fn condorcet_sources(filler: &[&str]) -> Vec<(String, Vec<Node>)> {
+        let mut sources = Vec::new();
+        for (voter, order) in ["ABC", "ABC", "ABC", "BCA", "BCA"].iter().enumerate() {
+            let mut entries: Vec<String> = order.chars().map(String::from).collect();
+            entries.extend(filler.iter().map(|entry| entry.to_string()));
+            let entries: Vec<&str> = entries.iter().map(String::as_str).collect();
+            sources.push(source(&format!("v{}", voter), &entries));
+        }
+        sources
+    }
+
+    #[test]
+    fn borda_counts_entries_ranked_below() {
+        let sources = [source("a", &["X", "Y", "Z"]), source("b", &["Y", "X", "Z"]), source("c", &["Y", "Z", "X"])];
+        let combined = combine(AggregationMethod::Borda, &sources);
+        assert_eq!(names(&combined), ["Y", "X", "Z"]);
+        assert_eq!(scores(&combined), [Some(5.0), Some(3.0), Some(1.0)]);
+    }
+
+    #[test]
+    fn missing_entries_rank_below_the_last_one() {
+        let sources = [source("a", &["X", "Y"]), source("b", &["Z"])];
+        let borda = combine(AggregationMethod::Borda, &sources);
+        assert_eq!(names(&borda), ["X", "Y", "Z"]);
+        assert_eq!(scores(&borda), [Some(1.0), Some(0.0), Some(0.0)]);
+        let average = combine(AggregationMethod::AverageRank, &sources);
+        assert_eq!(names(&average), ["X", "Y", "Z"]);
+        assert_eq!(scores(&average), [Some(1.5), Some(2.0), Some(2.0)]);
+        let z = &average[2];
+        assert_eq!(z.ranks, [("a".to_string(), None), ("b".to_string(), Some(1))]);
+        assert_eq!(source_attributes(z).into_iter().collect::<Vec<_>>(),
+            [("a".to_string(), "-".to_string()), ("b".to_string(), "#1".to_string())]);
+    }
+
+    #[test]
+    fn average_and_median_rank() {
+        let sources = [source("a", &["X", "Y", "Z"]), source("b", &["X", "Y", "Z"]), source("c", &["Z", "Y", "X"])];
+        let average = combine(AggregationMethod::AverageRank, &sources);
+        assert_eq!(names(&average), ["X", "Y", "Z"]);
+        assert_eq!(scores(&average), [Some(1.67), Some(2.0), Some(2.33)]);
+        let median = combine(AggregationMethod::MedianRank, &sources);
+        assert_eq!(names(&median), ["X", "Y", "Z"]);
+        assert_eq!(scores(&median), [Some(1.0), Some(2.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn median_of_an_even_number_of_ranks() {
+        let sources = [source("a", &["X", "Y"]), source("b", &["Y", "X"])];
+        let median = combine(AggregationMethod::MedianRank, &sources);
+        assert_eq!(scores(&median), [Some(1.5), Some(1.5)]);
+    }
+
+    #[test]
+    fn kemeny_follows_the_majority_where_borda_does_not() {
+        let sources = condorcet_sources(&[]);
+        assert_eq!(names(&combine(AggregationMethod::Borda, &sources)), ["B", "A", "C"]);
+        let kemeny = combine(AggregationMethod::Kemeny, &sources);
+        assert_eq!(names(&kemeny), ["A", "B", "C"]);
+        assert_eq!(scores(&kemeny), [None, None, None]);
+    }
+
+    #[test]
+    fn kemeny_swaps_neighbours_of_long_lists() {
+        let filler = ["F1", "F2", "F3", "F4", "F5", "F6", "F7"];
+        let sources = condorcet_sources(&filler);
+        assert!(filler.len() + 3 > KEMENY_EXACT_LIMIT);
+        assert_eq!(names(&combine(AggregationMethod::Borda, &sources))[..3], ["B", "A", "C"]);
+        let kemeny = combine(AggregationMethod::Kemeny, &sources);
+        assert_eq!(names(&kemeny), ["A", "B", "C", "F1", "F2", "F3", "F4", "F5", "F6", "F7"]);
+    }
+
+    #[test]
+    fn kemeny_keeps_the_borda_order_of_ties() {
+        let sources = [source("a", &["X", "Y"]), source("b", &["Y", "X"])];
+        assert_eq!(names(&combine(AggregationMethod::Kemeny, &sources)), ["X", "Y"]);
+    }
+
+    #[test]
+    fn refresh_follows_the_sources() {
+        let storage: Rc<dyn Storage> = Rc::new(MemoryStorage::new());
+        let mut a = Leaderboard::new("a", storage.clone());
+        a.new_entry("X", 1).unwrap();
+        a.new_entry("Y", 2).unwrap();
+        let mut b = Leaderboard::new("b", storage.clone());
+        b.new_entry("X", 1).unwrap();
+        b.new_entry("Y", 2).unwrap();
+        let boards = ["a".to_string(), "b".to_string()];
+        let composite = Composite { sources: boards.to_vec(), method: AggregationMethod::Borda };
+        let mut combined = Leaderboard::new("ab", storage.clone()).with_composite(composite);
+        assert!(refresh(storage.as_ref(), &mut combined, &boards).unwrap().is_empty());
+        assert_eq!(combined.entries().iter().map(|node| node.name.as_str()).collect::<Vec<_>>(), ["X", "Y"]);
+        let y = combined.entries()[1].id;
+
+        a.change_rank(2, 1).unwrap();
+        b.change_rank(2, 1).unwrap();
+        refresh(storage.as_ref(), &mut combined, &boards).unwrap();
+        assert_eq!(combined.entries()[0].name, "Y");
+        assert_eq!(combined.entries()[0].id, y);
+        assert!(combined.new_entry("Z", 1).is_err());
+
+        let missing = refresh(storage.as_ref(), &mut combined, &boards[..1]).unwrap();
+        assert_eq!(missing, ["b"]);
+        assert_eq!(combined.entries()[0].details.attributes.keys().collect::<Vec<_>>(), ["a"]);
+    }
+}
@@ -0,0 +1,298 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::{RankingMode, TieRule};
+use crate::rating::RatingSystem;
+use crate::search::MatchMode;
+use crate::sqlite_storage::SqliteStorage;
+use crate::storage::{read_from_file, DirStorage, Format, Storage};
+
+const APP_DIR: &str = "leaderboard-app";
+/// Where older versions kept the leaderboards, relative to the working
+/// directory.
+const LEGACY_DATA_DIR: &str = "Leaderboards";
+
+/// Settings read from `config.json` in the XDG config directory
+/// (`~/.config/leaderboard-app/config.json` by default). Every field is
+/// optional in the file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Directory holding the leaderboard files.
+    pub data_dir: PathBuf,
+    /// Log file, `app.log` in the data directory when unset.
+    pub log_file: Option<PathBuf>,
+    /// Directory the terminal UI exports CSV files to, `exports` in the data
+    /// directory when unset.
+    pub export_dir: Option<PathBuf>,
+    /// Format new leaderboard files are written in.
+    pub format: Format,
+    /// SQLite database to use instead of the data directory. Relative paths
+    /// are taken from the data directory.
+    pub database: Option<PathBuf>,
+    pub ui: UiConfig,
+    /// Whether the data directory defaulted to the legacy one because it
+    /// holds leaderboards and the XDG data directory does not exist yet.
+    #[serde(skip)]
+    pub(crate) legacy_data_dir: bool,
+}
+
+/// Defaults for the terminal UI and for leaderboards created from it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Save the undo history of new leaderboards between sessions.
+    pub keep_history: bool,
+    /// Ranking mode of new leaderboards.
+    pub ranking_mode: RankingMode,
+    /// Tie rule of new leaderboards.
+    pub tie_rule: TieRule,
+    /// Rating system of new leaderboards ranked by match results.
+    pub rating_system: RatingSystem,
+    /// Delete leaderboards and entries without asking first.
+    pub skip_confirmations: bool,
+    /// Ask for the leaderboard's name, rather than a yes, before deleting it.
+    pub confirm_with_board_name: bool,
+    /// Days deleted leaderboards and entries are kept in the trash. They
+    /// are kept until purged when unset.
+    pub trash_days: Option<u32>,
+    /// How searches match entry names until changed with <Tab>.
+    pub search_mode: MatchMode,
+    /// Start with the entry detail panel hidden, <v> toggles it.
+    pub hide_details: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::in_working_dir(Path::new("."))
+    }
+}
+
+impl Config {
+    /// Default settings when the working directory is `dir`, which decides
+    /// whether the legacy data directory is used.
+    fn in_working_dir(dir: &Path) -> Self {
+        let legacy = PathBuf::from(LEGACY_DATA_DIR);
+        let data_dir = xdg_dir("XDG_DATA_HOME", ".local/share").unwrap_or(legacy.clone());
+        // Leaderboards from older versions stay where they are until they
+        // are moved, rather than seemingly disappearing.
+        let legacy_data_dir = data_dir != legacy && dir.join(&legacy).is_dir() && !dir.join(&data_dir).exists();
+        Config {
+            data_dir: if legacy_data_dir { legacy } else { data_dir },
+            log_file: None,
+            export_dir: None,
+            format: Format::Json,
+            database: None,
+            ui: UiConfig::default(),
+            legacy_data_dir,
+        }
+    }
+}
+
+/// `$<var>/leaderboard-app`, or `$HOME/<fallback>/leaderboard-app` when the
+/// variable is not set.
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    let base = match std::env::var_os(var).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(fallback),
+    };
+    Some(base.join(APP_DIR))
+}
+
+impl Config {
+    pub fn default_location() -> Option<PathBuf> {
+        Some(xdg_dir("XDG_CONFIG_HOME", ".config")?.join("config.json"))
+    }
+
+    /// Loads the config file at `path`, or at the default location when no
+    /// path is given. A missing default config file means default settings.
+    pub fn load(path: Option<&Path>) -> Result<Config, Box<dyn Error>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Config::default_location() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let data = read_from_file(&path.to_string_lossy())
+            .map_err(|e| format!("Unable to read config {}: {}", path.display(), e))?;
+        let config = serde_json::from_str(&data)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    /// A warning about using the data directory of older versions, if it is
+    /// used because of that.
+    pub fn legacy_warning(&self) -> Option<String> {
+        if !self.legacy_data_dir || self.data_dir != Path::new(LEGACY_DATA_DIR) {
+            return None;
+        }
+        let new = xdg_dir("XDG_DATA_HOME", ".local/share")?;
+        Some(format!(
+            "Using ./{} in the working directory, where older versions kept leaderboards. \
+            Move it to {} to use the new location, or set data_dir in the config to keep it.",
+            LEGACY_DATA_DIR, new.display(),
+        ))
+    }
+
+    pub fn log_file(&self) -> PathBuf {
+        self.log_file.clone().unwrap_or_else(|| self.data_dir.join("app.log"))
+    }
+
+    pub fn export_dir(&self) -> PathBuf {
+        self.export_dir.clone().unwrap_or_else(|| self.data_dir.join("exports"))
+    }
+
+    /// The store described by this config.
+    pub fn storage(&self) -> Result<Rc<dyn Storage>, Box<dyn Error>> {
+        match &self.database {
+            Some(path) => {
+                std::fs::create_dir_all(&self.data_dir)?;
+                Ok(Rc::new(SqliteStorage::open(self.data_dir.join(path))?))
+            },
+            None => Ok(Rc::new(self.dir_storage())),
+        }
+    }
+
+    pub fn dir_storage(&self) -> DirStorage {
+        DirStorage::with_format(&self.data_dir, self.format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+    use std::sync::Mutex;
+    use clap::Parser;
+    use crate::cli::Cli;
+
+    /// Variables the config depends on. Tests that set them hold `ENV`, as
+    /// the environment is shared by every test thread.
+    const VARS: [&str; 5] = ["HOME", "XDG_DATA_HOME", "XDG_CONFIG_HOME", "LEADERBOARD_DATA_DIR", "LEADERBOARD_CONFIG"];
+    static ENV: Mutex<()> = Mutex::new(());
+
+    /// Runs `test` with `vars` set and the other [`VARS`] unset, then puts
+    /// the environment back.
+    fn with_env(vars: &[(&str, &Path)], test: impl FnOnce()) {
+        let _lock = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let saved: Vec<(&str, Option<OsString>)> = VARS.iter().map(|&var| (var, std::env::var_os(var))).collect();
+        // SAFETY: only these tests change the environment, one at a time.
+        unsafe {
+            for var in VARS {
+                std::env::remove_var(var);
+            }
+            for (var, value) in vars {
+                std::env::set_var(var, value);
+            }
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(test));
+        unsafe {
+            for (var, value) in saved {
+                match value {
+                    Some(value) => std::env::set_var(var, value),
+                    None => std::env::remove_var(var),
+                }
+            }
+        }
+        if let Err(panic) = result {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    fn parsed(args: &[&str]) -> Config {
+        let args = std::iter::once("leaderboard-app").chain(args.iter().copied()).chain(["list"]);
+        Cli::try_parse_from(args).unwrap().config().unwrap()
+    }
+
+    fn write_config(path: &Path, data_dir: &Path) {
+        let json = serde_json::json!({ "data_dir": data_dir, "format": "bincode" });
+        std::fs::write(path, json.to_string()).unwrap();
+    }
+
+    #[test]
+    fn command_line_and_environment_override_the_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (file, other) = (dir.path().join("config.json"), dir.path().join("other.json"));
+        write_config(&file, &dir.path().join("from-file"));
+        write_config(&other, &dir.path().join("from-other"));
+        let file_arg = file.to_str().unwrap();
+
+        with_env(&[("HOME", dir.path())], || {
+            let config = parsed(&["--config", file_arg]);
+            assert_eq!(config.data_dir, dir.path().join("from-file"));
+            assert_eq!(config.format, Format::Bincode);
+            assert_eq!(config.log_file(), dir.path().join("from-file/app.log"));
+            assert_eq!(config.export_dir(), dir.path().join("from-file/exports"));
+            assert_eq!(parsed(&["--config", file_arg, "--format", "json"]).format, Format::Json);
+            assert_eq!(parsed(&["--config", file_arg, "--data-dir", "flag"]).data_dir, Path::new("flag"));
+        });
+        with_env(&[("HOME", dir.path()), ("LEADERBOARD_CONFIG", &file)], || {
+            assert_eq!(parsed(&[]).data_dir, dir.path().join("from-file"));
+            assert_eq!(parsed(&["--config", other.to_str().unwrap()]).data_dir, dir.path().join("from-other"));
+        });
+        let env_dir = dir.path().join("from-env");
+        with_env(&[("HOME", dir.path()), ("LEADERBOARD_CONFIG", &file), ("LEADERBOARD_DATA_DIR", &env_dir)], || {
+            let config = parsed(&[]);
+            assert_eq!(config.data_dir, env_dir);
+            assert_eq!(config.format, Format::Bincode);
+            assert_eq!(parsed(&["--data-dir", "flag"]).data_dir, Path::new("flag"));
+        });
+    }
+
+    #[test]
+    fn xdg_directories_are_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let (data, config_home) = (dir.path().join("data"), dir.path().join("config"));
+        with_env(&[("HOME", dir.path()), ("XDG_DATA_HOME", &data), ("XDG_CONFIG_HOME", &config_home)], || {
+            assert_eq!(Config::default().data_dir, data.join("leaderboard-app"));
+            let location = Config::default_location().unwrap();
+            assert_eq!(location, config_home.join("leaderboard-app/config.json"));
+            // A missing default config file is no error, a broken one is.
+            assert_eq!(Config::load(None).unwrap().data_dir, data.join("leaderboard-app"));
+            std::fs::create_dir_all(location.parent().unwrap()).unwrap();
+            write_config(&location, Path::new("mine"));
+            assert_eq!(Config::load(None).unwrap().data_dir, Path::new("mine"));
+            std::fs::write(&location, "{").unwrap();
+            assert!(Config::load(None).unwrap_err().to_string().starts_with("Invalid config"));
+        });
+        let empty = PathBuf::new();
+        with_env(&[("HOME", dir.path()), ("XDG_DATA_HOME", &empty)], || {
+            assert_eq!(Config::default().data_dir, dir.path().join(".local/share/leaderboard-app"));
+            assert_eq!(Config::default_location().unwrap(), dir.path().join(".config/leaderboard-app/config.json"));
+            assert!(Config::load(Some(&dir.path().join("missing.json"))).is_err());
+        });
+    }
+
+    #[test]
+    fn legacy_directory_is_used_until_moved() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        with_env(&[("HOME", dir.path()), ("XDG_DATA_HOME", &data)], || {
+            let config = Config::in_working_dir(dir.path());
+            assert!(!config.legacy_data_dir);
+            assert_eq!(config.data_dir, data.join("leaderboard-app"));
+            assert_eq!(config.legacy_warning(), None);
+
+            std::fs::create_dir(dir.path().join(LEGACY_DATA_DIR)).unwrap();
+            let config = Config::in_working_dir(dir.path());
+            assert!(config.legacy_data_dir);
+            assert_eq!(config.data_dir, Path::new(LEGACY_DATA_DIR));
+            let warning = config.legacy_warning().unwrap();
+            assert!(warning.contains(&data.join("leaderboard-app").display().to_string()), "{}", warning);
+
+            std::fs::create_dir_all(data.join("leaderboard-app")).unwrap();
+            let config = Config::in_working_dir(dir.path());
+            assert!(!config.legacy_data_dir);
+            assert_eq!(config.data_dir, data.join("leaderboard-app"));
+        });
+        // Without a home there is no XDG directory to move to.
+        with_env(&[], || {
+            let config = Config::in_working_dir(dir.path());
+            assert_eq!(config.data_dir, Path::new(LEGACY_DATA_DIR));
+            assert!(!config.legacy_data_dir);
+        });
+    }
+}
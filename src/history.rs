@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::{RankingMode, TieRule};
+use crate::node::{Details, Node, Rank, ID};
+use crate::rating::{Game, RatingSystem};
+
+/// A single reversible change to a leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Command {
+    /// `node` was inserted, holding the rank it was given.
+    Insert { node: Node },
+    /// `node` was removed, holding the rank it had before removal.
+    Remove { node: Node },
+    /// The entry `id` was moved from one rank to another.
+    Move { id: ID, from: Rank, to: Rank },
+    /// The entry `id` was renamed.
+    Rename { id: ID, from: String, to: String },
+    /// The notes, tags or attributes of entry `id` were changed.
+    SetDetails { id: ID, from: Details, to: Details },
+    /// The score of entry `id` was changed.
+    SetScore { id: ID, from: Option<f64>, to: Option<f64> },
+    /// The ranking mode was changed. `ranks` keeps the ranks from before the
+    /// change so a manual order survives a round trip through a score mode.
+    SetMode { from: RankingMode, to: RankingMode, ranks: Vec<(ID, Rank)> },
+    /// The tie rule was changed.
+    SetTieRule { from: TieRule, to: TieRule },
+    /// The rating system of a rated leaderboard was changed.
+    SetRatingSystem { from: RatingSystem, to: RatingSystem },
+    /// A game was added to the end of a rated leaderboard's game log.
+    RecordGame { game: Game },
+    /// Several commands that are undone and redone as one step.
+    Batch(Vec<Command>),
+}
+
+/// Undo and redo stacks of a leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+}
+
+impl History {
+    /// Records a freshly executed command. A new command invalidates
+    /// everything that could have been redone.
+    pub fn record(&mut self, command: Command) {
+        self.undo.push(command);
+        self.redo.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Command> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Command> {
+        self.redo.pop()
+    }
+
+    pub fn push_undo(&mut self, command: Command) {
+        self.undo.push(command);
+    }
+
+    pub fn push_redo(&mut self, command: Command) {
+        self.redo.push(command);
+    }
+}
@@ -1,26 +1,318 @@
 use serde_json::Result as JSONResult;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::error::Error;
+use std::rc::Rc;
 use serde::{Deserialize, Serialize};
 use std::ops::Drop;
 
-use crate::node::Node;
-use crate::storage;
+use crate::composite::{self, Combined, Composite};
+use crate::history::{Command, History};
+use crate::node::{Details, Node, Rank, RankCause, RankChange, ID};
+use crate::pairwise::{BinaryInsertion, MergeSort};
+use crate::rating::{self, Game, Outcome, RatingSystem, INITIAL_RATING};
+use crate::storage::Storage;
+use crate::trash::{now, Tombstone};
+
+/// How the ranks of a leaderboard are decided.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankingMode {
+    /// Ranks are placed by hand, scores are informational only.
+    #[default]
+    Manual,
+    /// Lowest score ranks first (e.g. lap times).
+    Ascending,
+    /// Highest score ranks first (e.g. points).
+    Descending,
+    /// Scores are ratings computed from the games played between entries,
+    /// highest first. Leaderboards are created rated and stay rated.
+    Rated,
+}
+
+impl RankingMode {
+    pub fn is_scored(&self) -> bool {
+        *self != RankingMode::Manual
+    }
+
+    /// The mode that follows this one, used to cycle through modes in the UI.
+    pub fn next(&self) -> RankingMode {
+        match self {
+            RankingMode::Manual => RankingMode::Descending,
+            RankingMode::Descending => RankingMode::Ascending,
+            RankingMode::Ascending => RankingMode::Manual,
+            RankingMode::Rated => RankingMode::Rated,
+        }
+    }
+}
+
+impl std::fmt::Display for RankingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RankingMode::Manual => write!(f, "manual"),
+            RankingMode::Ascending => write!(f, "ascending score"),
+            RankingMode::Descending => write!(f, "descending score"),
+            RankingMode::Rated => write!(f, "match results"),
+        }
+    }
+}
+
+/// How entries with equal scores are ranked against each other.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieRule {
+    /// Standard competition ranking: 1, 2, 2, 4.
+    #[default]
+    Competition,
+    /// Dense ranking: 1, 2, 2, 3.
+    Dense,
+    /// Ordinal ranking: 1, 2, 3, 4, ties broken by insertion order.
+    Ordinal,
+    /// Fractional ranking: 1, 2.5, 2.5, 4.
+    Fractional,
+}
+
+impl TieRule {
+    /// The rule that follows this one, used to cycle through rules in the UI.
+    pub fn next(&self) -> TieRule {
+        match self {
+            TieRule::Competition => TieRule::Dense,
+            TieRule::Dense => TieRule::Ordinal,
+            TieRule::Ordinal => TieRule::Fractional,
+            TieRule::Fractional => TieRule::Competition,
+        }
+    }
+}
+
+impl std::fmt::Display for TieRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TieRule::Competition => write!(f, "competition ties"),
+            TieRule::Dense => write!(f, "dense ties"),
+            TieRule::Ordinal => write!(f, "ordinal ties"),
+            TieRule::Fractional => write!(f, "fractional ties"),
+        }
+    }
+}
+
+/// What happens to the existing entries when a CSV file is imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// Imported rows are added next to the existing entries.
+    #[default]
+    Merge,
+    /// The existing entries are replaced by the imported rows.
+    Replace,
+}
+
+impl std::fmt::Display for ImportMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportMode::Merge => write!(f, "merge"),
+            ImportMode::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+/// Outcome of a CSV import. Rows listed in `errors` were skipped.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub errors: Vec<String>,
+}
+
+impl std::fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Imported {} entries", self.imported)?;
+        if !self.errors.is_empty() {
+            write!(f, ", skipped {} rows: {}", self.errors.len(), self.errors.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A parsed CSV row waiting to be inserted.
+struct CsvRow {
+    rank: Option<usize>,
+    name: String,
+    id: Option<ID>,
+    score: Option<f64>,
+    details: Details,
+}
+
+/// Columns of a CSV file that are not attributes.
+const CSV_COLUMNS: [&str; 6] = ["rank", "name", "id", "score", "notes", "tags"];
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Leaderboard {
     name: String,
-    entries: Vec<Node>,     // Sorted by ID
+    entries: Vec<Node>,     // Sorted by rank
     next_id: usize,
+    #[serde(default)]
+    mode: RankingMode,
+    #[serde(default)]
+    tie_rule: TieRule,
+    #[serde(default)]
+    keep_history: bool,
+    /// Deleted entries that can still be restored.
+    #[serde(default)]
+    deleted: Vec<Tombstone>,
+    #[serde(default)]
+    rating_system: RatingSystem,
+    /// Every game played on a rated leaderboard, oldest first. Ratings are
+    /// replayed from it whenever it changes.
+    #[serde(default)]
+    games: Vec<Game>,
+    /// The leaderboards a composite leaderboard is combined from. Composite
+    /// leaderboards can not be edited.
+    #[serde(default)]
+    composite: Option<Composite>,
+    #[serde(skip)]
+    history: History,
+    #[serde(skip)]
+    storage: Option<Rc<dyn Storage>>,
 }
 
 impl Leaderboard {
 
-    pub fn new(n: &str) -> Self {
+    pub fn new(n: &str, storage: Rc<dyn Storage>) -> Self {
         Leaderboard {
             name: n.to_owned(),
             entries: Vec::new(),
-            next_id: 1
+            next_id: 1,
+            mode: RankingMode::Manual,
+            tie_rule: TieRule::Competition,
+            keep_history: false,
+            deleted: Vec::new(),
+            rating_system: RatingSystem::default(),
+            games: Vec::new(),
+            composite: None,
+            history: History::default(),
+            storage: Some(storage),
+        }
+    }
+
+    /// Sets the ranking of a freshly created leaderboard. Unlike
+    /// [`Leaderboard::set_ranking_mode`] this is not recorded in the history.
+    pub fn with_ranking(mut self, mode: RankingMode, tie_rule: TieRule) -> Self {
+        self.mode = mode;
+        self.tie_rule = tie_rule;
+        self.recompute_ranks();
+        self
+    }
+
+    /// Sets the rating system of a freshly created leaderboard, used when it
+    /// is rated.
+    pub fn with_rating_system(mut self, system: RatingSystem) -> Self {
+        self.rating_system = system;
+        self.recompute_ranks();
+        self
+    }
+
+    /// Makes a freshly created leaderboard a composite of other
+    /// leaderboards. Its entries are filled in by [`composite::refresh`].
+    pub fn with_composite(mut self, composite: Composite) -> Self {
+        self.mode = RankingMode::Manual;
+        self.composite = Some(composite);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Renames a detached leaderboard. Attached ones are renamed through the
+    /// store so that their files move with them.
+    pub(crate) fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn ranking_mode(&self) -> RankingMode {
+        self.mode
+    }
+
+    pub fn set_ranking_mode(&mut self, mode: RankingMode) -> Result<(), String> {
+        if mode != self.mode && (mode == RankingMode::Rated || self.mode == RankingMode::Rated) {
+            return Err("Only new leaderboards can be rated, and rated ones stay rated".to_string());
+        }
+        let ranks = self.entries.iter().map(|node| (node.id, node.rank)).collect();
+        self.execute(Command::SetMode { from: self.mode, to: mode, ranks })
+            .map_err(|e| format!("Unable to change ranking mode: {}", e))
+    }
+
+    pub fn tie_rule(&self) -> TieRule {
+        self.tie_rule
+    }
+
+    pub fn set_tie_rule(&mut self, rule: TieRule) -> Result<(), String> {
+        self.execute(Command::SetTieRule { from: self.tie_rule, to: rule })
+            .map_err(|e| format!("Unable to change tie rule: {}", e))
+    }
+
+    pub fn rating_system(&self) -> RatingSystem {
+        self.rating_system
+    }
+
+    /// Switches the rating system, replaying every game with the new one.
+    pub fn set_rating_system(&mut self, system: RatingSystem) -> Result<(), String> {
+        self.execute(Command::SetRatingSystem { from: self.rating_system, to: system })
+            .map_err(|e| format!("Unable to change rating system: {}", e))
+    }
+
+    /// The games played on a rated leaderboard, oldest first.
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// Where the entries come from, if the leaderboard is a composite.
+    pub fn composite(&self) -> Option<&Composite> {
+        self.composite.as_ref()
+    }
+
+    /// Follows a source leaderboard of a composite to its new name. Returns
+    /// whether the leaderboard combines `old`.
+    pub(crate) fn rename_source(&mut self, old: &str, name: &str) -> bool {
+        let Some(composite) = &mut self.composite else { return false };
+        let mut renamed = false;
+        for source in composite.sources.iter_mut().filter(|source| *source == old) {
+            *source = name.to_string();
+            renamed = true;
+        }
+        renamed
+    }
+
+    pub fn keeps_history(&self) -> bool {
+        self.keep_history
+    }
+
+    pub(crate) fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Hands back the undo history the leaderboard had earlier in the
+    /// session, when it was reopened from the store without it.
+    pub(crate) fn set_history(&mut self, history: History) {
+        self.history = history;
+    }
+
+    /// Whether the undo history is saved next to the leaderboard file so it
+    /// survives between sessions.
+    pub fn set_keep_history(&mut self, keep: bool) -> Result<(), Box<dyn Error>> {
+        self.keep_history = keep;
+        if !keep && let Some(storage) = &self.storage {
+            storage.remove_attachment(&self.name, "history")?;
         }
+        self.save_leaderboard()
+    }
+
+    /// The entries in rank order.
+    pub fn entries(&self) -> &[Node] {
+        &self.entries
+    }
+
+    /// The name of the entry `id`, also when it was deleted.
+    pub fn entry_name(&self, id: ID) -> Option<&str> {
+        self.entries.iter()
+            .chain(self.deleted.iter().map(|tombstone| &tombstone.node))
+            .find(|node| node.id == id)
+            .map(|node| node.name.as_str())
     }
 
     pub fn len(&self) -> usize {
@@ -33,7 +325,7 @@ impl Leaderboard {
 
     fn insert_node_at_rank(&mut self, node: Node, rank: usize) -> Result<usize, String>{
         if rank < 1 {
-            return Err(format!("Rank must be higher than 0"));
+            return Err("Rank must be higher than 0".to_string());
         }
         for node in &mut self.entries {
             if rank <= node.rank {
@@ -45,54 +337,478 @@ impl Leaderboard {
         Ok(rank)
     }
 
-    pub fn new_entry(&mut self, name: &str, rank: usize) -> Result<(), String> {
-        let rank = std::cmp::min(rank, self.entries.len()  + 1);
-        let new_node = Node {name: name.to_owned(), rank: rank, id: self.next_id};
-        self.next_id += 1;
+    fn remove_node_by_rank(&mut self, rank: usize) -> Result<Node, String> {
+        if (rank > self.entries.len() ) || (rank < 1) {
+            return Err(format!("No entry at Rank: {}, remove failed", rank));
+        }
+        let removed = self.entries.remove(rank  - 1);
+        for node in &mut self.entries {
+            if rank < node.rank {
+                node.rank -= 1;
+            }
+        }
+        Ok(removed)
+    }
 
-        match self.insert_node_at_rank(new_node, rank) {
-            Ok(_r) => (), //println!("{} successfully inserted at rank: {}", name, r),
-            Err(e) => return Err(format!("Unable to insert node at rank {}: {}", rank, e)) 
-        };
-        match self.save_leaderboard() {
-            Ok(()) => {},
-            Err(e) => return Err(format!("Unable to insert node at rank {}: {}", rank, e))
+    fn rank_of(&self, id: ID) -> Result<usize, String> {
+        self.entries.iter()
+            .find(|node| node.id == id)
+            .map(|node| node.rank)
+            .ok_or(format!("No entry with id {} in {}", id, self.name))
+    }
+
+    fn move_node(&mut self, id: ID, to_rank: usize) -> Result<(), String> {
+        let mut node = self.remove_node_by_rank(self.rank_of(id)?)?;
+        let to_rank = std::cmp::min(to_rank, self.entries.len() + 1);
+        node.rank = to_rank;
+        self.insert_node_at_rank(node, to_rank)?;
+        Ok(())
+    }
+
+    fn name_node(&mut self, id: ID, name: &str) -> Result<(), String> {
+        let rank = self.rank_of(id)?;
+        self.entries[rank-1].name = name.to_string();
+        Ok(())
+    }
+
+    fn detail_node(&mut self, id: ID, details: &Details) -> Result<(), String> {
+        let rank = self.rank_of(id)?;
+        self.entries[rank-1].details = details.clone();
+        Ok(())
+    }
+
+    fn score_node(&mut self, id: ID, score: Option<f64>) -> Result<(), String> {
+        let rank = self.rank_of(id)?;
+        self.entries[rank-1].score = score;
+        self.recompute_ranks();
+        Ok(())
+    }
+
+    /// Compares two entries by score according to the ranking mode.
+    /// Entries without a score sink below every scored entry.
+    fn compare_scores(&self, a: &Node, b: &Node) -> std::cmp::Ordering {
+        match (a.score, b.score) {
+            (Some(x), Some(y)) => match self.mode {
+                RankingMode::Ascending => x.total_cmp(&y),
+                _ => y.total_cmp(&x),
+            },
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+    }
+
+    /// Reorders the entries by score when the leaderboard is score ranked.
+    /// Entries with equal scores are ordered by id, i.e. by insertion time,
+    /// so the order is the same every time the board is loaded.
+    fn recompute_ranks(&mut self) {
+        if !self.mode.is_scored() {
+            return;
+        }
+        if self.mode == RankingMode::Rated {
+            let ratings = rating::replay(self.rating_system, &self.games);
+            for node in &mut self.entries {
+                let rating = ratings.get(&node.id).map_or(INITIAL_RATING, |rating| rating.rating);
+                node.score = Some((rating * 10.0).round() / 10.0);
+            }
+        }
+        let mut entries = std::mem::take(&mut self.entries);
+        entries.sort_by(|a, b| self.compare_scores(a, b).then(a.id.cmp(&b.id)));
+        self.entries = entries;
+        for (i, node) in self.entries.iter_mut().enumerate() {
+            node.rank = i + 1;
+        }
+    }
+
+    /// Applies a command to the entries without saving or recording it.
+    fn apply(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Insert { node } => {
+                self.insert_node_at_rank(node.clone(), node.rank)?;
+                self.recompute_ranks();
+            },
+            Command::Remove { node } => {
+                self.remove_node_by_rank(self.rank_of(node.id)?)?;
+            },
+            Command::Move { id, to, .. } => self.move_node(*id, *to)?,
+            Command::Rename { id, to, .. } => self.name_node(*id, to)?,
+            Command::SetDetails { id, to, .. } => self.detail_node(*id, to)?,
+            Command::SetScore { id, to, .. } => self.score_node(*id, *to)?,
+            Command::SetMode { to, .. } => {
+                self.mode = *to;
+                self.recompute_ranks();
+            },
+            Command::SetTieRule { to, .. } => self.tie_rule = *to,
+            Command::SetRatingSystem { to, .. } => {
+                self.rating_system = *to;
+                self.recompute_ranks();
+            },
+            Command::RecordGame { game } => {
+                self.games.push(game.clone());
+                self.recompute_ranks();
+            },
+            Command::Batch(commands) => {
+                for command in commands {
+                    self.apply(command)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Applies the inverse of a command, restoring the state before it ran.
+    fn revert(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Insert { node } => {
+                self.remove_node_by_rank(self.rank_of(node.id)?)?;
+            },
+            Command::Remove { node } => {
+                self.insert_node_at_rank(node.clone(), node.rank)?;
+                self.recompute_ranks();
+            },
+            Command::Move { id, from, .. } => self.move_node(*id, *from)?,
+            Command::Rename { id, from, .. } => self.name_node(*id, from)?,
+            Command::SetDetails { id, from, .. } => self.detail_node(*id, from)?,
+            Command::SetScore { id, from, .. } => self.score_node(*id, *from)?,
+            Command::SetMode { from, ranks, .. } => {
+                self.mode = *from;
+                for (id, rank) in ranks {
+                    if let Some(node) = self.entries.iter_mut().find(|node| node.id == *id) {
+                        node.rank = *rank;
+                    }
+                }
+                self.entries.sort();
+                self.recompute_ranks();
+            },
+            Command::SetTieRule { from, .. } => self.tie_rule = *from,
+            Command::SetRatingSystem { from, .. } => {
+                self.rating_system = *from;
+                self.recompute_ranks();
+            },
+            Command::RecordGame { game } => {
+                if self.games.last() != Some(game) {
+                    return Err("The last game recorded is not the one being undone".to_string());
+                }
+                self.games.pop();
+                self.recompute_ranks();
+            },
+            Command::Batch(commands) => {
+                for command in commands.iter().rev() {
+                    self.revert(command)?;
+                }
+            },
         }
         Ok(())
     }
 
-    fn remove_node_by_rank(&mut self, rank: usize) -> Result<(String, usize), String> {
+    fn ranks(&self) -> HashMap<ID, Rank> {
+        self.entries.iter().map(|node| (node.id, node.rank)).collect()
+    }
+
+    /// Adds a rank change to every entry whose rank differs from `before`.
+    /// `cause` tells why entry `id` moved from one rank to another.
+    fn record_rank_changes(&mut self, before: &HashMap<ID, Rank>, cause: impl Fn(ID, Rank, Rank) -> RankCause) {
+        let at = chrono::Utc::now().timestamp();
+        for node in &mut self.entries {
+            if let Some(&from) = before.get(&node.id)
+                && from != node.rank {
+                let cause = cause(node.id, from, node.rank);
+                node.record_rank_change(RankChange { at, from, to: node.rank, cause });
+            }
+        }
+    }
+
+    /// Applies a command, records it in the history and saves the leaderboard.
+    fn execute(&mut self, command: Command) -> Result<(), String> {
+        if self.composite.is_some() {
+            return Err(format!("{} is combined from other leaderboards and can not be edited", self.name));
+        }
+        let before = self.ranks();
+        self.apply(&command)?;
+        self.record_rank_changes(&before, |id, from, to| rank_cause(&command, id, from, to));
+        self.history.record(command);
+        self.save_leaderboard()
+            .map_err(|e| format!("Unable to save {}: {}", self.name, e))
+    }
+
+    pub fn undo(&mut self) -> Result<(), String> {
+        let command = self.history.pop_undo().ok_or("Nothing to undo")?;
+        let before = self.ranks();
+        if let Err(e) = self.revert(&command) {
+            self.history.push_undo(command);
+            return Err(format!("Undo failed: {}", e));
+        }
+        self.record_rank_changes(&before, |_, _, _| RankCause::Undone);
+        self.history.push_redo(command);
+        self.save_leaderboard()
+            .map_err(|e| format!("Unable to save {}: {}", self.name, e))
+    }
+
+    pub fn redo(&mut self) -> Result<(), String> {
+        let command = self.history.pop_redo().ok_or("Nothing to redo")?;
+        let before = self.ranks();
+        if let Err(e) = self.apply(&command) {
+            self.history.push_redo(command);
+            return Err(format!("Redo failed: {}", e));
+        }
+        self.record_rank_changes(&before, |id, from, to| rank_cause(&command, id, from, to));
+        self.history.push_undo(command);
+        self.save_leaderboard()
+            .map_err(|e| format!("Unable to save {}: {}", self.name, e))
+    }
+
+    pub fn new_entry(&mut self, name: &str, rank: usize) -> Result<(), String> {
+        self.new_scored_entry(name, rank, None)
+    }
+
+    /// Adds an entry with an optional score. On score ranked leaderboards the
+    /// requested rank is ignored and the entry is placed by its score.
+    pub fn new_scored_entry(&mut self, name: &str, rank: usize, score: Option<f64>) -> Result<(), String> {
+        let rank = if self.mode.is_scored() {
+            self.entries.len() + 1
+        } else {
+            std::cmp::min(rank, self.entries.len() + 1)
+        };
+        let new_node = Node::new(name, rank, self.next_id, score);
+        self.next_id += 1;
+
+        self.execute(Command::Insert { node: new_node })
+            .map_err(|e| format!("Unable to insert node at rank {}: {}", rank, e))
+    }
+
+    pub fn remove(&mut self, rank: usize) -> Result<(), String> {
         if (rank > self.entries.len() ) || (rank < 1) {
             return Err(format!("No entry at Rank: {}, remove failed", rank));
         }
-        let removed_name = self.entries[rank-1].name.clone();
-        self.entries.remove(rank  - 1);
-        for node in &mut self.entries {
-            if rank < node.rank {
-                node.rank -= 1;
+        let node = self.entries[rank-1].clone();
+        // An entry deleted again keeps only its latest tombstone.
+        let deleted = self.deleted.clone();
+        self.deleted.retain(|tombstone| tombstone.node.id != node.id);
+        self.deleted.push(Tombstone { node: node.clone(), deleted_at: now() });
+        self.execute(Command::Remove { node }).inspect_err(|_| {
+            self.deleted = deleted;
+        })
+    }
+
+    /// Entries deleted from this leaderboard, oldest first. Deletions that
+    /// were undone and entries that were restored are left out; their
+    /// tombstones stay so undoing the restore puts them back in the trash.
+    pub fn deleted_entries(&self) -> impl Iterator<Item = &Tombstone> {
+        self.deleted.iter().filter(|tombstone| self.rank_of(tombstone.node.id).is_err())
+    }
+
+    /// Puts a deleted entry back at the rank it was deleted from, or where
+    /// its score places it on score ranked leaderboards.
+    pub fn restore_entry(&mut self, id: ID) -> Result<(), String> {
+        let position = self.deleted.iter().rposition(|tombstone| tombstone.node.id == id)
+            .ok_or(format!("No deleted entry with id {} in {}", id, self.name))?;
+        if self.rank_of(id).is_ok() {
+            return Err(format!("{} is already on {}", self.deleted[position].node.name, self.name));
+        }
+        let mut node = self.deleted[position].node.clone();
+        node.rank = if self.mode.is_scored() {
+            self.entries.len() + 1
+        } else {
+            std::cmp::min(node.rank, self.entries.len() + 1)
+        };
+        self.execute(Command::Insert { node })
+    }
+
+    /// Forgets a deleted entry for good.
+    pub fn purge_entry(&mut self, id: ID) -> Result<(), Box<dyn Error>> {
+        self.deleted.retain(|tombstone| tombstone.node.id != id);
+        self.save_leaderboard()
+    }
+
+    /// Forgets every entry deleted before `cutoff`, returning how many there
+    /// were. Tombstones of restored entries are kept while the entry is on
+    /// the leaderboard.
+    pub fn purge_entries_deleted_before(&mut self, cutoff: i64) -> Result<usize, Box<dyn Error>> {
+        let before = self.deleted.len();
+        let live: HashSet<ID> = self.entries.iter().map(|node| node.id).collect();
+        self.deleted.retain(|tombstone| tombstone.deleted_at >= cutoff || live.contains(&tombstone.node.id));
+        if self.deleted.len() != before {
+            self.save_leaderboard()?;
+        }
+        Ok(before - self.deleted.len())
+    }
+
+    pub fn set_score(&mut self, rank: usize, score: Option<f64>) -> Result<(), String> {
+        if (rank > self.entries.len()) || (rank < 1) {
+            return Err(format!("No entry at Rank: {}, score change failed", rank));
+        }
+        if self.mode == RankingMode::Rated {
+            return Err(format!("Scores of {} are ratings computed from its games", self.name));
+        }
+        let node = &self.entries[rank-1];
+        self.execute(Command::SetScore { id: node.id, from: node.score, to: score })
+    }
+
+    pub fn rename_entry(&mut self, id: ID, name: &str) -> Result<(), String> {
+        let node = &self.entries[self.rank_of(id)? - 1];
+        self.update_entry(id, name, node.score, node.details.clone())
+    }
+
+    pub fn set_details(&mut self, id: ID, details: Details) -> Result<(), String> {
+        let node = &self.entries[self.rank_of(id)? - 1];
+        self.update_entry(id, &node.name.clone(), node.score, details)
+    }
+
+    /// Changes the name, score and details of the entry `id` in place,
+    /// keeping its id. The changes are undone together.
+    pub fn update_entry(&mut self, id: ID, name: &str, score: Option<f64>, details: Details) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Entry name must not be empty".to_string());
+        }
+        let node = &self.entries[self.rank_of(id)? - 1];
+        let mut commands = Vec::new();
+        if node.name != name {
+            commands.push(Command::Rename { id, from: node.name.clone(), to: name.to_string() });
+        }
+        if node.score != score {
+            if self.mode == RankingMode::Rated {
+                return Err(format!("Scores of {} are ratings computed from its games", self.name));
             }
+            commands.push(Command::SetScore { id, from: node.score, to: score });
+        }
+        if node.details != details {
+            commands.push(Command::SetDetails { id, from: node.details.clone(), to: details });
+        }
+        match commands.len() {
+            0 => Ok(()),
+            1 => self.execute(commands.remove(0)),
+            _ => self.execute(Command::Batch(commands)),
         }
-        self.save_leaderboard().unwrap();
-        Ok((removed_name, rank))
     }
 
-    pub fn remove(&mut self, rank:usize) {
-        match self.remove_node_by_rank(rank) {
-            Ok((_n, _r)) => (),//println!("{} removed from rank: {}", n, rank),
-            Err(s) => println!("{}", s)
+    /// Records a game between the entries `first` and `second` of a rated
+    /// leaderboard and updates the ratings.
+    pub fn record_game(&mut self, first: ID, second: ID, outcome: Outcome) -> Result<(), String> {
+        if self.mode != RankingMode::Rated {
+            return Err(format!("{} is not rated by match results", self.name));
         }
+        if first == second {
+            return Err("An entry can not play against itself".to_string());
+        }
+        self.rank_of(first)?;
+        self.rank_of(second)?;
+        self.execute(Command::RecordGame { game: Game { first, second, outcome, played_at: now() } })
+            .map_err(|e| format!("Unable to record game: {}", e))
     }
 
     pub fn change_rank(&mut self, rank:usize, to_rank: usize) -> Result<(), String> {
-        let mut temp = self.entries[rank-1].clone();
-        if let Err(err) = self.remove_node_by_rank(rank) {
-            return Err(format!("Change failed: {}", err));
+        if self.mode.is_scored() {
+            return Err(format!("Ranks of {} are computed from scores ({})", self.name, self.mode));
         }
-        temp.rank = to_rank;
-        if let Err(err) = self.insert_node_at_rank(temp, to_rank) {
-            return Err(format!("Change failed: {}", err));
+        if (rank > self.entries.len()) || (rank < 1) {
+            return Err(format!("Change failed: No entry at Rank: {}", rank));
         }
-        Ok(())
+        if to_rank < 1 {
+            return Err("Change failed: Rank must be higher than 0".to_string());
+        }
+        let to = std::cmp::min(to_rank, self.entries.len());
+        let id = self.entries[rank-1].id;
+        self.execute(Command::Move { id, from: rank, to })
+            .map_err(|e| format!("Change failed: {}", e))
+    }
+
+    /// Adds an entry at the rank a finished binary insertion against the
+    /// entries found for it.
+    pub fn finish_insertion(&mut self, name: &str, insertion: &BinaryInsertion) -> Result<Rank, String> {
+        if self.mode.is_scored() {
+            return Err(format!("Ranks of {} are computed from scores ({})", self.name, self.mode));
+        }
+        let rank = insertion.rank().ok_or("Not every comparison is answered yet")?;
+        self.new_entry(name, rank)?;
+        Ok(rank)
+    }
+
+    /// Puts the entries in the order a finished merge sort of their ids
+    /// found.
+    pub fn finish_sort(&mut self, sort: MergeSort<ID>) -> Result<(), String> {
+        self.reorder(&sort.result().ok_or("Not every comparison is answered yet")?)
+    }
+
+    /// Adds an entry at the rank found by binary insertion, where
+    /// `ranks_above` tells whether the new entry ranks above an existing one.
+    pub fn insert_by_comparison(&mut self, name: &str, mut ranks_above: impl FnMut(&Node) -> Result<bool, Box<dyn Error>>) -> Result<Rank, Box<dyn Error>> {
+        let mut insertion = BinaryInsertion::new(self.entries.len());
+        while let Some(position) = insertion.question() {
+            insertion.answer(ranks_above(&self.entries[position])?);
+        }
+        Ok(self.finish_insertion(name, &insertion)?)
+    }
+
+    /// Reorders every entry by asking which of two entries ranks higher,
+    /// returning how many questions were asked.
+    pub fn sort_by_comparison(&mut self, mut first_ranks_above: impl FnMut(&Node, &Node) -> Result<bool, Box<dyn Error>>) -> Result<usize, Box<dyn Error>> {
+        let mut sort = MergeSort::new(self.entries.iter().map(|node| node.id).collect());
+        while let Some((&a, &b)) = sort.question() {
+            let above = first_ranks_above(&self.entries[self.rank_of(a)? - 1], &self.entries[self.rank_of(b)? - 1])?;
+            sort.answer(above);
+        }
+        let asked = sort.asked();
+        self.finish_sort(sort)?;
+        Ok(asked)
+    }
+
+    /// Puts the entries in the order of `ids`, which must list every entry
+    /// once. The reordering is undone as one step.
+    pub fn reorder(&mut self, ids: &[ID]) -> Result<(), String> {
+        if self.mode.is_scored() {
+            return Err(format!("Ranks of {} are computed from scores ({})", self.name, self.mode));
+        }
+        let unique: HashSet<ID> = ids.iter().copied().collect();
+        if unique.len() != ids.len() || ids.len() != self.entries.len() || ids.iter().any(|&id| self.rank_of(id).is_err()) {
+            return Err(format!("The new order must list every entry of {} once", self.name));
+        }
+        // Moving each entry to its place from the top down leaves the ones
+        // already placed alone, so each move can be recorded with the rank
+        // it starts from at that point.
+        let mut ranks: Vec<ID> = self.entries.iter().map(|node| node.id).collect();
+        let mut moves = Vec::new();
+        for (position, &id) in ids.iter().enumerate() {
+            let from = ranks.iter().position(|&other| other == id).unwrap_or(position);
+            if from != position {
+                let id = ranks.remove(from);
+                ranks.insert(position, id);
+                moves.push(Command::Move { id, from: from + 1, to: position + 1 });
+            }
+        }
+        if moves.is_empty() {
+            return Ok(());
+        }
+        self.execute(Command::Batch(moves))
+    }
+
+    /// Replaces the entries of a composite leaderboard with the combined
+    /// ranking of its sources and saves it. Entries keep their ids and
+    /// history while their name stays on a source.
+    pub(crate) fn set_combined_entries(&mut self, combined: Vec<Combined>) -> Result<(), Box<dyn Error>> {
+        if self.composite.is_none() {
+            return Err(format!("{} is not combined from other leaderboards", self.name).into());
+        }
+        let before = self.ranks();
+        let mut previous: HashMap<String, Node> = self.entries.drain(..)
+            .map(|node| (node.name.clone(), node))
+            .collect();
+        for (position, entry) in combined.into_iter().enumerate() {
+            let mut node = match previous.remove(&entry.name) {
+                Some(node) => node,
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    Node::new(&entry.name, position + 1, id, None)
+                },
+            };
+            node.rank = position + 1;
+            node.score = entry.score;
+            node.details.attributes = composite::source_attributes(&entry);
+            self.entries.push(node);
+        }
+        self.record_rank_changes(&before, |_, _, _| RankCause::Combined);
+        self.save_leaderboard()
     }
 
     pub fn debug_pretty(&self) {
@@ -103,16 +819,49 @@ impl Leaderboard {
 
     pub fn display(&self){
         println!("========={}===========", self.name);
-        for entry in &self.entries {
-            println!("{}: {}", entry.rank, entry.name);
+        for line in self.write_to_vector() {
+            print!("{}", line);
         }
         println!("========================");
     }
 
+    /// The rank shown for each entry, in list order. Manual leaderboards
+    /// show their positions; score ranked leaderboards apply the tie rule.
+    pub fn display_ranks(&self) -> Vec<String> {
+        if !self.mode.is_scored() {
+            return self.entries.iter().map(|e| e.rank.to_string()).collect();
+        }
+        let mut ranks = Vec::with_capacity(self.entries.len());
+        let mut start = 0;
+        let mut dense = 0;
+        while start < self.entries.len() {
+            let mut end = start + 1;
+            while end < self.entries.len()
+                && self.compare_scores(&self.entries[start], &self.entries[end]).is_eq() {
+                end += 1;
+            }
+            dense += 1;
+            for position in start..end {
+                let rank = match self.tie_rule {
+                    TieRule::Competition => (start + 1).to_string(),
+                    TieRule::Dense => dense.to_string(),
+                    TieRule::Ordinal => (position + 1).to_string(),
+                    TieRule::Fractional => ((start + 1 + end) as f64 / 2.0).to_string(),
+                };
+                ranks.push(rank);
+            }
+            start = end;
+        }
+        ranks
+    }
+
     pub fn write_to_vector(&self) -> Vec<String> {
         let mut s = Vec::new();
-        for entry in &self.entries {
-            s.push(format!("{}: {}\n", entry.rank, entry.name));
+        for (entry, rank) in self.entries.iter().zip(self.display_ranks()) {
+            match entry.score {
+                Some(score) => s.push(format!("{}: {} ({})\n", rank, entry.name, score)),
+                None => s.push(format!("{}: {}\n", rank, entry.name)),
+            }
         }
         s
     }
@@ -127,30 +876,510 @@ impl Leaderboard {
         Ok(leaderboard)
     }
 
-    fn get_leaderboard_file_location(name: &str) -> String {
-        let mut file_location = "Leaderboards/".to_owned();
-        file_location.push_str(name);
-        file_location.push_str(".json");
-        file_location
+    pub fn serialize_to_bincode(&self) -> Result<Vec<u8>, bincode::error::EncodeError> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard())
+    }
+
+    pub fn intialize_from_bincode(data: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        let (leaderboard, _) = bincode::serde::decode_from_slice(data, bincode::config::standard())?;
+        Ok(leaderboard)
+    }
+
+    /// Writes the entries as CSV with a `rank,name,id,score,notes,tags`
+    /// header followed by a column for each attribute any entry has. Tags
+    /// are separated by commas.
+    pub fn serialize_to_csv(&self) -> Result<String, Box<dyn Error>> {
+        let keys: BTreeSet<&String> = self.entries.iter()
+            .flat_map(|entry| entry.details.attributes.keys())
+            .collect();
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(CSV_COLUMNS.iter().copied().chain(keys.iter().map(|key| key.as_str())))?;
+        for entry in &self.entries {
+            let score = entry.score.map(|s| s.to_string()).unwrap_or_default();
+            let tags: Vec<&str> = entry.details.tags.iter().map(String::as_str).collect();
+            let mut record = vec![entry.rank.to_string(), entry.name.clone(), entry.id.to_string(), score,
+                entry.details.notes.clone(), tags.join(", ")];
+            record.extend(keys.iter().map(|&key| entry.details.attributes.get(key).cloned().unwrap_or_default()));
+            writer.write_record(&record)?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    /// Imports entries from CSV. Only the `name` column is required; `rank`,
+    /// `id`, `score`, `notes` and `tags` are used when present and every
+    /// other column is an attribute, set for the rows that have a value in
+    /// it. Malformed rows and duplicate names are skipped and listed in the
+    /// report. Ids from the file are kept unless they clash with an entry
+    /// already on the board. The whole import is a single undo step.
+    pub fn import_from_csv(&mut self, data: &str, mode: ImportMode) -> Result<ImportReport, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes());
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let name_column = column("name").ok_or("CSV file has no name column")?;
+        let (rank_column, id_column, score_column) = (column("rank"), column("id"), column("score"));
+        let (notes_column, tags_column) = (column("notes"), column("tags"));
+        // A column named like one of the others a second time is an
+        // attribute, so that attributes with such names survive an export.
+        let known: Vec<usize> = CSV_COLUMNS.iter().filter_map(|&name| column(name)).collect();
+        let attribute_columns: Vec<(usize, &str)> = headers.iter().enumerate()
+            .filter(|(c, _)| !known.contains(c))
+            .collect();
+
+        let mut report = ImportReport::default();
+        let mut names: HashSet<String> = HashSet::new();
+        if mode == ImportMode::Merge {
+            names.extend(self.entries.iter().map(|e| e.name.clone()));
+        }
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    report.errors.push(e.to_string());
+                    continue;
+                }
+            };
+            let line = record.position().map(|p| p.line()).unwrap_or_default();
+            let field = |column: Option<usize>| column
+                .and_then(|c| record.get(c))
+                .filter(|value| !value.is_empty());
+            let name = field(Some(name_column)).unwrap_or_default().to_string();
+            if name.is_empty() {
+                report.errors.push(format!("line {}: missing name", line));
+                continue;
+            }
+            let rank = match field(rank_column).map(|r| r.parse::<usize>()) {
+                Some(Ok(0)) | Some(Err(_)) => {
+                    report.errors.push(format!("line {}: invalid rank for {}", line, name));
+                    continue;
+                },
+                Some(Ok(rank)) => Some(rank),
+                None => None,
+            };
+            let score = match field(score_column).map(|s| s.parse::<f64>()) {
+                Some(Err(_)) => {
+                    report.errors.push(format!("line {}: invalid score for {}", line, name));
+                    continue;
+                },
+                Some(Ok(score)) => Some(score),
+                None => None,
+            };
+            let id = match field(id_column).map(|id| id.parse::<ID>()) {
+                // The next id handed out comes after the largest one in use.
+                Some(Err(_)) | Some(Ok(ID::MAX)) => {
+                    report.errors.push(format!("line {}: invalid id for {}", line, name));
+                    continue;
+                },
+                Some(Ok(id)) => Some(id),
+                None => None,
+            };
+            if !names.insert(name.clone()) {
+                report.errors.push(format!("line {}: duplicate entry {}", line, name));
+                continue;
+            }
+            let details = Details {
+                notes: field(notes_column).unwrap_or_default().to_string(),
+                tags: Details::parse_tags(field(tags_column).unwrap_or_default()),
+                attributes: attribute_columns.iter()
+                    .filter_map(|&(c, key)| Some((key.to_string(), field(Some(c))?.to_string())))
+                    .collect(),
+            };
+            rows.push(CsvRow { rank, name, id, score, details });
+        }
+        // Rows with a rank go first in rank order, the rest keep file order.
+        rows.sort_by_key(|row| row.rank.unwrap_or(usize::MAX));
+
+        let mut commands = Vec::new();
+        let mut used_ids: HashSet<ID> = HashSet::new();
+        let mut len = self.entries.len();
+        if mode == ImportMode::Replace {
+            for node in self.entries.iter().rev() {
+                commands.push(Command::Remove { node: node.clone() });
+            }
+            len = 0;
+        } else {
+            used_ids.extend(self.entries.iter().map(|e| e.id));
+        }
+        let mut next_id = self.next_id;
+        for row in rows {
+            let id = match row.id {
+                Some(id) if used_ids.insert(id) => id,
+                _ => {
+                    while used_ids.contains(&next_id) {
+                        next_id += 1;
+                    }
+                    used_ids.insert(next_id);
+                    next_id
+                },
+            };
+            next_id = std::cmp::max(next_id, id + 1);
+            let rank = match row.rank {
+                Some(rank) if !self.mode.is_scored() => std::cmp::min(rank, len + 1),
+                _ => len + 1,
+            };
+            let mut node = Node::new(&row.name, rank, id, row.score);
+            node.details = row.details;
+            commands.push(Command::Insert { node });
+            len += 1;
+            report.imported += 1;
+        }
+        if !commands.is_empty() {
+            // Set before executing so the saved leaderboard has it, and put
+            // back when the import fails.
+            let previous = std::mem::replace(&mut self.next_id, next_id);
+            if let Err(e) = self.execute(Command::Batch(commands)) {
+                self.next_id = previous;
+                return Err(e.into());
+            }
+        }
+        Ok(report)
     }
 
+    /// Saves the leaderboard to the storage it was opened from. Leaderboards
+    /// without storage are never written.
     pub fn save_leaderboard(&self) -> Result<(), Box<dyn Error>> {
-        let data = self.serialize_to_json()?;
-        let file_location = Leaderboard::get_leaderboard_file_location(&self.name);
-        storage::write_to_file(&data, &file_location)?;
+        let Some(storage) = &self.storage else { return Ok(()) };
+        storage.write_leaderboard(self)?;
+        if self.keep_history {
+            let history = serde_json::to_string(&self.history)?;
+            storage.write_attachment(&self.name, "history", &history)?;
+        }
         Ok(())
     }
 
-    pub fn open_leaderboard(name: &str) -> Result<Leaderboard, Box<dyn Error>>  {
-        let file_location = Leaderboard::get_leaderboard_file_location(name);
-        let data = storage::read_from_file(&file_location)?;
-        Ok(Leaderboard::intialize_from_json(&data)?)
+    pub fn open_leaderboard(name: &str, storage: Rc<dyn Storage>) -> Result<Leaderboard, Box<dyn Error>>  {
+        let mut leaderboard = storage.read_leaderboard(name)?;
+        if leaderboard.keep_history
+            && let Some(history) = storage.read_attachment(name, "history")? {
+            leaderboard.history = serde_json::from_str(&history)?;
+        }
+        leaderboard.storage = Some(storage);
+        Ok(leaderboard)
     }
 
 }
 
+/// Why `command` moved entry `id` from rank `from` to `to`.
+fn rank_cause(command: &Command, id: ID, from: Rank, to: Rank) -> RankCause {
+    match command {
+        Command::Move { id: moved, .. } if *moved == id => RankCause::Moved,
+        Command::SetScore { .. } | Command::SetMode { .. } | Command::SetRatingSystem { .. } => RankCause::Rescored,
+        Command::RecordGame { .. } => RankCause::Played,
+        Command::Batch(commands) => commands.iter()
+            .map(|command| rank_cause(command, id, from, to))
+            .find(|cause| matches!(cause, RankCause::Moved | RankCause::Rescored))
+            .unwrap_or(if to > from { RankCause::InsertedAbove } else { RankCause::RemovedAbove }),
+        _ if to > from => RankCause::InsertedAbove,
+        _ => RankCause::RemovedAbove,
+    }
+}
+
 impl Drop for Leaderboard {
     fn drop(&mut self) {
         self.save_leaderboard().unwrap();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn leaderboard(names: &[&str]) -> Leaderboard {
+        let mut leaderboard = Leaderboard::new("board", Rc::new(MemoryStorage::new()));
+        for (position, name) in names.iter().enumerate() {
+            leaderboard.new_entry(name, position + 1).unwrap();
+        }
+        leaderboard
+    }
+
+    fn names(leaderboard: &Leaderboard) -> Vec<&str> {
+        leaderboard.entries().iter().map(|node| node.name.as_str()).collect()
+    }
+
+    fn ranks(leaderboard: &Leaderboard) -> Vec<Rank> {
+        leaderboard.entries().iter().map(|node| node.rank).collect()
+    }
+
+    /// Undoes the last command and redoes it, checking the entries before
+    /// and after it.
+    fn round_trip(leaderboard: &mut Leaderboard, before: &[&str], after: &[&str]) {
+        assert_eq!(names(leaderboard), after);
+        leaderboard.undo().unwrap();
+        assert_eq!(names(leaderboard), before);
+        assert_eq!(ranks(leaderboard), (1..=before.len()).collect::<Vec<_>>());
+        leaderboard.redo().unwrap();
+        assert_eq!(names(leaderboard), after);
+    }
+
+    #[test]
+    fn undo_and_redo_inserts() {
+        let mut leaderboard = leaderboard(&["A", "C"]);
+        leaderboard.new_entry("B", 2).unwrap();
+        round_trip(&mut leaderboard, &["A", "C"], &["A", "B", "C"]);
+        // The id stays with the entry when it comes back.
+        assert_eq!(leaderboard.entries()[1].id, 3);
+    }
+
+    #[test]
+    fn undo_and_redo_removals() {
+        let mut leaderboard = leaderboard(&["A", "B", "C"]);
+        leaderboard.remove(2).unwrap();
+        round_trip(&mut leaderboard, &["A", "B", "C"], &["A", "C"]);
+    }
+
+    #[test]
+    fn undo_and_redo_moves() {
+        let mut leaderboard = leaderboard(&["A", "B", "C"]);
+        leaderboard.change_rank(3, 1).unwrap();
+        round_trip(&mut leaderboard, &["A", "B", "C"], &["C", "A", "B"]);
+    }
+
+    #[test]
+    fn batches_are_undone_as_one_step() {
+        let mut leaderboard = leaderboard(&["A", "B", "C", "D"]);
+        leaderboard.reorder(&[4, 3, 2, 1]).unwrap();
+        round_trip(&mut leaderboard, &["A", "B", "C", "D"], &["D", "C", "B", "A"]);
+        leaderboard.undo().unwrap();
+        leaderboard.undo().unwrap();
+        assert_eq!(names(&leaderboard), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn undoing_a_mode_change_restores_the_manual_order() {
+        let mut leaderboard = leaderboard(&[]);
+        leaderboard.new_scored_entry("A", 1, Some(1.0)).unwrap();
+        leaderboard.new_scored_entry("B", 2, Some(3.0)).unwrap();
+        leaderboard.new_scored_entry("C", 3, Some(2.0)).unwrap();
+        leaderboard.set_ranking_mode(RankingMode::Descending).unwrap();
+        round_trip(&mut leaderboard, &["A", "B", "C"], &["B", "C", "A"]);
+        assert_eq!(leaderboard.ranking_mode(), RankingMode::Descending);
+        leaderboard.undo().unwrap();
+        assert_eq!(leaderboard.ranking_mode(), RankingMode::Manual);
+    }
+
+    #[test]
+    fn insert_by_comparison_places_the_entry_at_its_rank() {
+        for rank in 1..=5 {
+            let mut leaderboard = leaderboard(&["A", "B", "C", "E"]);
+            let mut asked = 0;
+            let found = leaderboard.insert_by_comparison("D", |node| {
+                asked += 1;
+                Ok(node.rank >= rank)
+            });
+            assert_eq!(found.unwrap(), rank);
+            assert_eq!(leaderboard.entries()[rank - 1].name, "D");
+            assert!(asked <= 3);
+        }
+        let mut leaderboard = leaderboard(&["A", "B", "C", "E"]);
+        leaderboard.insert_by_comparison("D", |node| Ok(node.name == "E")).unwrap();
+        round_trip(&mut leaderboard, &["A", "B", "C", "E"], &["A", "B", "C", "D", "E"]);
+    }
+
+    #[test]
+    fn insert_by_comparison_stops_at_the_first_error() {
+        let mut leaderboard = leaderboard(&["A", "B"]);
+        let result = leaderboard.insert_by_comparison("C", |_| Err("No answer given".into()));
+        assert_eq!(result.unwrap_err().to_string(), "No answer given");
+        assert_eq!(names(&leaderboard), ["A", "B"]);
+    }
+
+    #[test]
+    fn sort_by_comparison_is_one_undo_step() {
+        let mut leaderboard = leaderboard(&["C", "A", "D", "B"]);
+        let asked = leaderboard.sort_by_comparison(|a, b| Ok(a.name < b.name)).unwrap();
+        assert!(asked <= 5, "asked {} questions", asked);
+        round_trip(&mut leaderboard, &["C", "A", "D", "B"], &["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn scored_boards_can_not_be_ranked_by_comparison() {
+        let mut leaderboard = leaderboard(&[]);
+        leaderboard.new_scored_entry("A", 1, Some(1.0)).unwrap();
+        leaderboard.set_ranking_mode(RankingMode::Descending).unwrap();
+        assert!(leaderboard.insert_by_comparison("B", |_| Ok(true)).is_err());
+        assert_eq!(names(&leaderboard), ["A"]);
+    }
+
+    fn import(leaderboard: &mut Leaderboard, csv: &str, mode: ImportMode) -> ImportReport {
+        leaderboard.import_from_csv(csv, mode).unwrap()
+    }
+
+    #[test]
+    fn merging_a_csv_file_keeps_the_existing_entries() {
+        let mut leaderboard = leaderboard(&["A", "B"]);
+        let report = import(&mut leaderboard, "rank,name,id\n1,C,7\n,D,\n", ImportMode::Merge);
+        assert_eq!(report.imported, 2);
+        assert!(report.errors.is_empty());
+        round_trip(&mut leaderboard, &["A", "B"], &["C", "A", "B", "D"]);
+        let ids: Vec<ID> = leaderboard.entries().iter().map(|node| node.id).collect();
+        assert_eq!(ids, [7, 1, 2, 8]);
+    }
+
+    #[test]
+    fn merging_keeps_ids_apart() {
+        let mut leaderboard = leaderboard(&["A", "B"]);
+        import(&mut leaderboard, "name,id\nC,1\n", ImportMode::Merge);
+        assert_eq!(names(&leaderboard), ["A", "B", "C"]);
+        assert_eq!(leaderboard.entries()[2].id, 3);
+    }
+
+    #[test]
+    fn replacing_with_a_csv_file_drops_the_existing_entries() {
+        let mut leaderboard = leaderboard(&["A", "B"]);
+        let report = import(&mut leaderboard, "name,score\nB,2\nC,1.5\n", ImportMode::Replace);
+        assert_eq!(report.imported, 2);
+        round_trip(&mut leaderboard, &["A", "B"], &["B", "C"]);
+        assert_eq!(leaderboard.entries()[1].score, Some(1.5));
+    }
+
+    #[test]
+    fn duplicate_names_are_skipped() {
+        let mut leaderboard = leaderboard(&["A"]);
+        let report = import(&mut leaderboard, "name\nA\nB\nB\n", ImportMode::Merge);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.errors, ["line 2: duplicate entry A", "line 4: duplicate entry B"]);
+        assert_eq!(names(&leaderboard), ["A", "B"]);
+
+        let report = import(&mut leaderboard, "name\nA\nA\n", ImportMode::Replace);
+        assert_eq!(report.errors, ["line 3: duplicate entry A"]);
+        assert_eq!(names(&leaderboard), ["A"]);
+    }
+
+    #[test]
+    fn malformed_cells_are_reported() {
+        let csv = "rank,name,id,score\n\
+            1,Good,,\n\
+            x,Rank,,\n\
+            0,Zero,,\n\
+            ,Id,x,\n\
+            ,Negative,-1,\n\
+            ,Largest,18446744073709551615,\n\
+            ,Score,,x\n\
+            2,,,\n\
+            3,Short\n";
+        let mut leaderboard = leaderboard(&[]);
+        let report = import(&mut leaderboard, csv, ImportMode::Merge);
+        assert_eq!(report.imported, 1);
+        assert_eq!(names(&leaderboard), ["Good"]);
+        assert_eq!(report.errors.len(), 8);
+        assert_eq!(report.errors[..7], [
+            "line 3: invalid rank for Rank",
+            "line 4: invalid rank for Zero",
+            "line 5: invalid id for Id",
+            "line 6: invalid id for Negative",
+            "line 7: invalid id for Largest",
+            "line 8: invalid score for Score",
+            "line 9: missing name",
+        ]);
+        assert!(report.errors[7].contains("line: 10"), "{}", report.errors[7]);
+    }
+
+    #[test]
+    fn csv_files_keep_notes_tags_and_attributes() {
+        let mut source = leaderboard(&["A", "B"]);
+        source.entries[0].details = Details {
+            notes: "First line\nsecond, with \"quotes\"".to_string(),
+            tags: Details::parse_tags("classic, replay"),
+            attributes: Details::parse_attributes(["year: 1998", "name: Alpha", "url: https://example.com/a,b"]).unwrap(),
+        };
+        source.entries[1].details.attributes = Details::parse_attributes(["platform: PC"]).unwrap();
+        let csv = source.serialize_to_csv().unwrap();
+        assert!(csv.starts_with("rank,name,id,score,notes,tags,name,platform,url,year\n"), "{}", csv);
+
+        let mut leaderboard = leaderboard(&[]);
+        let report = import(&mut leaderboard, &csv, ImportMode::Replace);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(names(&leaderboard), ["A", "B"]);
+        for (imported, original) in leaderboard.entries().iter().zip(source.entries()) {
+            assert_eq!(imported.details, original.details);
+        }
+    }
+
+    #[test]
+    fn empty_csv_cells_set_no_attribute() {
+        let mut leaderboard = leaderboard(&[]);
+        import(&mut leaderboard, "name,tags,platform,year\nA,,PC,\nB, x ,,2001\n", ImportMode::Merge);
+        let [a, b] = leaderboard.entries() else { panic!("expected two entries") };
+        assert!(a.details.tags.is_empty());
+        assert_eq!(a.details.attributes.keys().collect::<Vec<_>>(), ["platform"]);
+        assert_eq!(b.details.tags, Details::parse_tags("x"));
+        assert_eq!(b.details.attributes.keys().collect::<Vec<_>>(), ["year"]);
+    }
+
+    #[test]
+    fn csv_files_need_a_name_column() {
+        let mut leaderboard = leaderboard(&["A"]);
+        assert!(leaderboard.import_from_csv("rank,id\n1,1\n", ImportMode::Replace).is_err());
+        assert_eq!(names(&leaderboard), ["A"]);
+    }
+
+    #[test]
+    fn new_commands_clear_what_could_be_redone() {
+        let mut leaderboard = leaderboard(&["A"]);
+        assert!(leaderboard.redo().is_err());
+        leaderboard.undo().unwrap();
+        assert!(leaderboard.undo().is_err());
+        leaderboard.new_entry("B", 1).unwrap();
+        assert!(leaderboard.redo().is_err());
+        assert_eq!(names(&leaderboard), ["B"]);
+    }
+
+    fn trash(leaderboard: &Leaderboard) -> Vec<&str> {
+        leaderboard.deleted_entries().map(|tombstone| tombstone.node.name.as_str()).collect()
+    }
+
+    #[test]
+    fn undoing_a_restore_puts_the_entry_back_in_the_trash() {
+        let mut leaderboard = leaderboard(&["A", "B", "C"]);
+        leaderboard.remove(2).unwrap();
+        assert_eq!(trash(&leaderboard), ["B"]);
+        leaderboard.restore_entry(2).unwrap();
+        assert_eq!(names(&leaderboard), ["A", "B", "C"]);
+        assert!(trash(&leaderboard).is_empty());
+        assert!(leaderboard.restore_entry(2).is_err());
+
+        leaderboard.undo().unwrap();
+        assert_eq!(names(&leaderboard), ["A", "C"]);
+        assert_eq!(trash(&leaderboard), ["B"]);
+        leaderboard.redo().unwrap();
+        assert!(trash(&leaderboard).is_empty());
+
+        // Deleting it again leaves a single tombstone.
+        leaderboard.remove(2).unwrap();
+        assert_eq!(trash(&leaderboard), ["B"]);
+        assert_eq!(leaderboard.deleted.len(), 1);
+    }
+
+    #[test]
+    fn restored_entries_go_back_to_their_rank() {
+        let mut leaderboard = leaderboard(&["A", "B", "C"]);
+        leaderboard.remove(3).unwrap();
+        leaderboard.remove(1).unwrap();
+        leaderboard.restore_entry(3).unwrap();
+        assert_eq!(names(&leaderboard), ["B", "C"]);
+        leaderboard.restore_entry(1).unwrap();
+        assert_eq!(names(&leaderboard), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn purged_and_expired_entries_are_gone_for_good() {
+        let mut leaderboard = leaderboard(&["A", "B", "C", "D"]);
+        leaderboard.remove(1).unwrap();
+        leaderboard.remove(1).unwrap();
+        leaderboard.remove(1).unwrap();
+        leaderboard.purge_entry(2).unwrap();
+        assert_eq!(trash(&leaderboard), ["A", "C"]);
+        assert!(leaderboard.restore_entry(2).is_err());
+
+        leaderboard.restore_entry(3).unwrap();
+        assert_eq!(leaderboard.purge_entries_deleted_before(now() - 60).unwrap(), 0);
+        assert_eq!(leaderboard.purge_entries_deleted_before(now() + 1).unwrap(), 1);
+        assert!(trash(&leaderboard).is_empty());
+        // The restored entry keeps its tombstone for undo.
+        leaderboard.undo().unwrap();
+        assert_eq!(trash(&leaderboard), ["C"]);
+        assert_eq!(leaderboard.entry_name(1), None);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod leaderboard;
+pub mod storage;
+pub mod sqlite_storage;
+pub mod node;
+pub mod app;
+pub mod log;
+pub mod history;
+pub mod cli;
+pub mod config;
+pub mod trash;
+pub mod search;
+pub mod snapshot;
+pub mod pairwise;
+pub mod rating;
+pub mod matches;
+pub mod tournament;
+pub mod composite;
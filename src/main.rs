@@ -1,23 +1,34 @@
-mod leaderboard;
-mod storage;
-mod node;
-mod app;
-mod log;
-
 use std::{error::Error};
-use app::App;
-use log::Log;
+use std::process::ExitCode;
+use clap::Parser;
+use leaderboard_app::app::App;
+use leaderboard_app::cli::{self, Cli};
+use leaderboard_app::log::Log;
 
-fn main() -> color_eyre::Result<(), Box<dyn Error>> {
+fn main() -> color_eyre::Result<ExitCode, Box<dyn Error>> {
 
     color_eyre::install()?;
-    let log = Log::new("app.log").unwrap();
-    let mut app = App::new(log)?;
+    let cli = Cli::parse();
+    let config = cli.config()?;
+    let log_file = config.log_file();
+    if let Some(dir) = log_file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut log = Log::new(log_file).unwrap();
+    if let Some(warning) = config.legacy_warning() {
+        eprintln!("warning: {}", warning);
+        log.write(warning);
+    }
+    if let Some(command) = cli.command {
+        return Ok(cli::run(command, &config, log));
+    }
+    let mut app = App::new(log, config.storage()?, config.ui.clone())?
+        .with_export_dir(config.export_dir());
     let terminal = ratatui::init();
-    let result = app.run(terminal)?;
+    app.run(terminal)?;
     ratatui::restore();
 
     // we must be able to create new leaderboards from the App module
 
-    Ok(result)
+    Ok(ExitCode::SUCCESS)
 }
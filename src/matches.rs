@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::Leaderboard;
+use crate::node::{Node, ID};
+use crate::storage::Storage;
+use crate::trash::now;
+
+/// League points for a win and a draw, a loss gives none.
+pub const WIN_POINTS: u32 = 3;
+pub const DRAW_POINTS: u32 = 1;
+
+/// A match between entries of a leaderboard, kept in the "matches"
+/// attachment of the leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Match {
+    /// Identifies the match among the matches of its leaderboard.
+    pub id: usize,
+    pub participants: Vec<Participant>,
+    /// Unix time the match was recorded.
+    pub played_at: i64,
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Participant {
+    pub id: ID,
+    pub score: f64,
+}
+
+impl Match {
+    /// The highest score of the match. Everyone who got it won, unless
+    /// everyone got it, which is a draw.
+    fn winning_score(&self) -> Option<f64> {
+        self.participants.iter().map(|participant| participant.score).max_by(f64::total_cmp)
+    }
+
+    fn is_draw(&self) -> bool {
+        let winning = self.winning_score();
+        self.participants.iter().all(|participant| Some(participant.score) == winning)
+    }
+
+    /// Describes the result with the participants' names, e.g.
+    /// "Ann 3 : 1 Bob", or "Ann 3, Bob 1, Cid 0" for more than two.
+    pub fn describe(&self, name: impl Fn(ID) -> String) -> String {
+        match self.participants.as_slice() {
+            [first, second] => format!("{} {} : {} {}", name(first.id), first.score, second.score, name(second.id)),
+            participants => participants.iter()
+                .map(|participant| format!("{} {}", name(participant.id), participant.score))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+/// The matches of the leaderboard `board`, oldest first.
+pub fn read_matches(storage: &dyn Storage, board: &str) -> Result<Vec<Match>, Box<dyn Error>> {
+    match storage.read_attachment(board, "matches")? {
+        Some(data) => Ok(serde_json::from_str(&data)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn write_matches(storage: &dyn Storage, board: &str, matches: &[Match]) -> Result<(), Box<dyn Error>> {
+    if matches.is_empty() {
+        storage.remove_attachment(board, "matches")
+    } else {
+        storage.write_attachment(board, "matches", &serde_json::to_string(matches)?)
+    }
+}
+
+/// Checks that a match has at least two different participants, all of
+/// them entries of `leaderboard`.
+fn check_participants(leaderboard: &Leaderboard, participants: &[Participant]) -> Result<(), Box<dyn Error>> {
+    if participants.len() < 2 {
+        return Err("A match needs at least two participants".into());
+    }
+    let mut seen = HashSet::new();
+    for participant in participants {
+        if !leaderboard.entries().iter().any(|node| node.id == participant.id) {
+            return Err(format!("{} has no entry with id {}", leaderboard.name(), participant.id).into());
+        }
+        if !seen.insert(participant.id) {
+            return Err("An entry can only take part in a match once".into());
+        }
+    }
+    Ok(())
+}
+
+/// Records a match on `leaderboard` and returns it.
+pub fn add_match(storage: &dyn Storage, leaderboard: &Leaderboard, participants: Vec<Participant>, notes: &str) -> Result<Match, Box<dyn Error>> {
+    check_participants(leaderboard, &participants)?;
+    let mut matches = read_matches(storage, leaderboard.name())?;
+    let id = matches.iter().map(|m| m.id).max().map_or(1, |id| id + 1);
+    let new = Match { id, participants, played_at: now(), notes: notes.trim().to_string() };
+    matches.push(new.clone());
+    write_matches(storage, leaderboard.name(), &matches)?;
+    Ok(new)
+}
+
+/// Replaces the participants, scores and notes of the match `id`, keeping
+/// the time it was played.
+pub fn update_match(storage: &dyn Storage, leaderboard: &Leaderboard, id: usize, participants: Vec<Participant>, notes: &str) -> Result<(), Box<dyn Error>> {
+    check_participants(leaderboard, &participants)?;
+    let mut matches = read_matches(storage, leaderboard.name())?;
+    let Some(existing) = matches.iter_mut().find(|m| m.id == id) else {
+        return Err(format!("{} has no match {}", leaderboard.name(), id).into());
+    };
+    existing.participants = participants;
+    existing.notes = notes.trim().to_string();
+    write_matches(storage, leaderboard.name(), &matches)
+}
+
+pub fn remove_match(storage: &dyn Storage, board: &str, id: usize) -> Result<(), Box<dyn Error>> {
+    let mut matches = read_matches(storage, board)?;
+    let before = matches.len();
+    matches.retain(|m| m.id != id);
+    if matches.len() == before {
+        return Err(format!("{} has no match {}", board, id).into());
+    }
+    write_matches(storage, board, &matches)
+}
+
+/// Reads "name: score" lines into participants, looking the names up on
+/// `leaderboard`. Names must be unique on the leaderboard.
+pub fn parse_participants<'a>(leaderboard: &Leaderboard, lines: impl IntoIterator<Item = &'a str>) -> Result<Vec<Participant>, String> {
+    let mut participants = Vec::new();
+    for line in lines.into_iter().map(str::trim).filter(|line| !line.is_empty()) {
+        let Some((name, score)) = line.rsplit_once(':') else {
+            return Err(format!("Expected \"name: score\", got \"{}\"", line));
+        };
+        let (name, score) = (name.trim(), score.trim());
+        let score = score.parse::<f64>().map_err(|_| format!("Score of {} must be a number!", name))?;
+        let mut found = leaderboard.entries().iter().filter(|node| node.name == name);
+        let id = match (found.next(), found.next()) {
+            (Some(node), None) => node.id,
+            (Some(_), Some(_)) => return Err(format!("{} has several entries named {}", leaderboard.name(), name)),
+            (None, _) => return Err(format!("{} has no entry named {}", leaderboard.name(), name)),
+        };
+        participants.push(Participant { id, score });
+    }
+    Ok(participants)
+}
+
+/// An entry's record over every match it took part in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Standing {
+    pub id: ID,
+    pub name: String,
+    pub played: u32,
+    pub won: u32,
+    pub drawn: u32,
+    pub lost: u32,
+    pub points: u32,
+    /// Sum of the entry's own scores.
+    pub scored: f64,
+    /// Sum of the scores of its opponents.
+    pub conceded: f64,
+}
+
+/// The standings table of the entries of a leaderboard, best first: by
+/// points, then by score difference, then by the leaderboard's own order.
+/// Matches of deleted entries still count for their opponents.
+pub fn standings(entries: &[Node], matches: &[Match]) -> Vec<Standing> {
+    let mut table: Vec<Standing> = entries.iter()
+        .map(|node| Standing { id: node.id, name: node.name.clone(), ..Default::default() })
+        .collect();
+    let positions: HashMap<ID, usize> = entries.iter().enumerate().map(|(position, node)| (node.id, position)).collect();
+    for m in matches {
+        let winning = m.winning_score();
+        let draw = m.is_draw();
+        let total: f64 = m.participants.iter().map(|participant| participant.score).sum();
+        for participant in &m.participants {
+            let Some(standing) = positions.get(&participant.id).and_then(|&position| table.get_mut(position)) else { continue };
+            standing.played += 1;
+            if draw {
+                standing.drawn += 1;
+                standing.points += DRAW_POINTS;
+            } else if Some(participant.score) == winning {
+                standing.won += 1;
+                standing.points += WIN_POINTS;
+            } else {
+                standing.lost += 1;
+            }
+            standing.scored += participant.score;
+            standing.conceded += total - participant.score;
+        }
+    }
+    table.sort_by(|a, b| b.points.cmp(&a.points)
+        .then((b.scored - b.conceded).total_cmp(&(a.scored - a.conceded))));
+    table
+}
+
+/// Header and rows of a standings table, aligned in columns.
+pub fn format_standings(standings: &[Standing]) -> Vec<String> {
+    let width = standings.iter().map(|standing| standing.name.chars().count()).max().unwrap_or(0).max(4);
+    let mut lines = vec![format!("{:>3}  {:<width$}  {:>3} {:>3} {:>3} {:>3} {:>4} {:>11}", "#", "Name", "P", "W", "D", "L", "Pts", "Scores")];
+    for (position, standing) in standings.iter().enumerate() {
+        lines.push(format!(
+            "{:>3}  {:<width$}  {:>3} {:>3} {:>3} {:>3} {:>4} {:>11}",
+            position + 1, standing.name, standing.played, standing.won, standing.drawn, standing.lost, standing.points,
+            format!("{}:{}", standing.scored, standing.conceded),
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::storage::MemoryStorage;
+
+    fn leaderboard(storage: &Rc<MemoryStorage>, names: &[&str]) -> Leaderboard {
+        let storage: Rc<dyn Storage> = storage.clone();
+        let mut leaderboard = Leaderboard::new("league", storage);
+        for (position, name) in names.iter().enumerate() {
+            leaderboard.new_entry(name, position + 1).unwrap();
+        }
+        leaderboard
+    }
+
+    fn result(leaderboard: &Leaderboard, lines: &[&str]) -> Vec<Participant> {
+        parse_participants(leaderboard, lines.iter().copied()).unwrap()
+    }
+
+    fn played(participants: Vec<Participant>) -> Match {
+        Match { id: 1, participants, played_at: 0, notes: String::new() }
+    }
+
+    /// Names in standings order with their won, drawn, lost and points.
+    fn table(standings: &[Standing]) -> Vec<(&str, u32, u32, u32, u32)> {
+        standings.iter()
+            .map(|standing| (standing.name.as_str(), standing.won, standing.drawn, standing.lost, standing.points))
+            .collect()
+    }
+
+    #[test]
+    fn a_higher_score_wins() {
+        let storage = Rc::new(MemoryStorage::new());
+        let leaderboard = leaderboard(&storage, &["Ann", "Bob"]);
+        let m = played(result(&leaderboard, &["Bob: 1", "Ann: 3"]));
+        assert_eq!(m.winning_score(), Some(3.0));
+        assert!(!m.is_draw());
+        let standings = standings(leaderboard.entries(), &[m]);
+        assert_eq!(table(&standings), [("Ann", 1, 0, 0, WIN_POINTS), ("Bob", 0, 0, 1, 0)]);
+        assert_eq!((standings[0].scored, standings[0].conceded), (3.0, 1.0));
+    }
+
+    #[test]
+    fn equal_scores_are_a_draw() {
+        let storage = Rc::new(MemoryStorage::new());
+        let leaderboard = leaderboard(&storage, &["Ann", "Bob"]);
+        let m = played(result(&leaderboard, &["Ann: 2", "Bob: 2"]));
+        assert!(m.is_draw());
+        let standings = standings(leaderboard.entries(), &[m]);
+        assert_eq!(table(&standings), [("Ann", 0, 1, 0, DRAW_POINTS), ("Bob", 0, 1, 0, DRAW_POINTS)]);
+    }
+
+    #[test]
+    fn everyone_with_the_top_score_wins() {
+        let storage = Rc::new(MemoryStorage::new());
+        let leaderboard = leaderboard(&storage, &["Ann", "Bob", "Cid"]);
+        let m = played(result(&leaderboard, &["Ann: 1", "Bob: 4", "Cid: 4"]));
+        assert_eq!(m.winning_score(), Some(4.0));
+        assert!(!m.is_draw());
+        assert_eq!(m.describe(|id| leaderboard.entry_name(id).unwrap().to_string()), "Ann 1, Bob 4, Cid 4");
+        let standings = standings(leaderboard.entries(), &[m]);
+        assert_eq!(table(&standings), [
+            ("Bob", 1, 0, 0, WIN_POINTS),
+            ("Cid", 1, 0, 0, WIN_POINTS),
+            ("Ann", 0, 0, 1, 0),
+        ]);
+        assert_eq!((standings[0].scored, standings[0].conceded), (4.0, 5.0));
+    }
+
+    #[test]
+    fn standings_follow_edited_and_removed_matches() {
+        let storage = Rc::new(MemoryStorage::new());
+        let leaderboard = leaderboard(&storage, &["Ann", "Bob"]);
+        let first = add_match(storage.as_ref(), &leaderboard, result(&leaderboard, &["Ann: 3", "Bob: 1"]), "").unwrap();
+        let second = add_match(storage.as_ref(), &leaderboard, result(&leaderboard, &["Ann: 2", "Bob: 0"]), " final ").unwrap();
+        assert_eq!((first.id, second.id), (1, 2));
+        assert_eq!(second.notes, "final");
+        let table_now = || standings(leaderboard.entries(), &read_matches(storage.as_ref(), "league").unwrap());
+        assert_eq!(table(&table_now()), [("Ann", 2, 0, 0, 2 * WIN_POINTS), ("Bob", 0, 0, 2, 0)]);
+
+        update_match(storage.as_ref(), &leaderboard, 1, result(&leaderboard, &["Ann: 0", "Bob: 5"]), "replayed").unwrap();
+        let matches = read_matches(storage.as_ref(), "league").unwrap();
+        assert_eq!(matches[0].played_at, first.played_at);
+        assert_eq!(matches[0].notes, "replayed");
+        assert_eq!(table(&table_now()), [("Bob", 1, 0, 1, WIN_POINTS), ("Ann", 1, 0, 1, WIN_POINTS)]);
+
+        remove_match(storage.as_ref(), "league", 2).unwrap();
+        assert_eq!(table(&table_now()), [("Bob", 1, 0, 0, WIN_POINTS), ("Ann", 0, 0, 1, 0)]);
+        remove_match(storage.as_ref(), "league", 1).unwrap();
+        assert_eq!(storage.read_attachment("league", "matches").unwrap(), None);
+        assert!(remove_match(storage.as_ref(), "league", 1).is_err());
+        assert!(update_match(storage.as_ref(), &leaderboard, 1, result(&leaderboard, &["Ann: 1", "Bob: 0"]), "").is_err());
+    }
+
+    #[test]
+    fn matches_of_deleted_entries_count_for_their_opponents() {
+        let storage = Rc::new(MemoryStorage::new());
+        let mut leaderboard = leaderboard(&storage, &["Ann", "Bob", "Cid"]);
+        let matches = [
+            played(result(&leaderboard, &["Ann: 1", "Bob: 2"])),
+            played(result(&leaderboard, &["Bob: 0", "Cid: 0"])),
+        ];
+        leaderboard.remove(2).unwrap();
+        let standings = standings(leaderboard.entries(), &matches);
+        assert_eq!(table(&standings), [("Cid", 0, 1, 0, DRAW_POINTS), ("Ann", 0, 0, 1, 0)]);
+        assert_eq!(standings[1].played, 1);
+    }
+
+    #[test]
+    fn participants_must_be_entries_of_the_leaderboard() {
+        let storage = Rc::new(MemoryStorage::new());
+        let leaderboard = leaderboard(&storage, &["Ann", "Bob", "Bob"]);
+        assert_eq!(parse_participants(&leaderboard, ["Ann 1"]).unwrap_err(), "Expected \"name: score\", got \"Ann 1\"");
+        assert_eq!(parse_participants(&leaderboard, ["Ann: x"]).unwrap_err(), "Score of Ann must be a number!");
+        assert_eq!(parse_participants(&leaderboard, ["Dan: 1"]).unwrap_err(), "league has no entry named Dan");
+        assert_eq!(parse_participants(&leaderboard, ["Bob: 1"]).unwrap_err(), "league has several entries named Bob");
+
+        let ann = result(&leaderboard, &["Ann: 1"]);
+        assert!(add_match(storage.as_ref(), &leaderboard, ann.clone(), "").is_err());
+        let twice = [ann.clone(), ann].concat();
+        assert!(add_match(storage.as_ref(), &leaderboard, twice, "").is_err());
+        let stranger = vec![Participant { id: 1, score: 1.0 }, Participant { id: 99, score: 0.0 }];
+        assert!(add_match(storage.as_ref(), &leaderboard, stranger, "").is_err());
+        assert!(read_matches(storage.as_ref(), "league").unwrap().is_empty());
+    }
+}
@@ -1,19 +1,193 @@
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
-type ID = usize;
-type RANK = usize;
+pub type ID = usize;
+pub type Rank = usize;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Node {
     pub name: String,
-    pub rank: RANK,
-    pub id: ID
+    pub rank: Rank,
+    pub id: ID,
+    #[serde(default)]
+    pub score: Option<f64>,
+    #[serde(default)]
+    pub details: Details,
+    /// Unix time the entry was added, unknown for entries from older files.
+    #[serde(default)]
+    pub added_at: Option<i64>,
+    /// The most recent rank changes, oldest first.
+    #[serde(default)]
+    pub rank_history: Vec<RankChange>,
 }
 
+/// How many rank changes are kept per entry.
+pub const RANK_HISTORY_LIMIT: usize = 100;
+
+/// A change of an entry's rank.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RankChange {
+    /// Unix time of the change.
+    pub at: i64,
+    pub from: Rank,
+    pub to: Rank,
+    #[serde(default)]
+    pub cause: RankCause,
+}
+
+/// Why an entry's rank changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankCause {
+    #[default]
+    Unknown,
+    /// The entry itself was moved with `change_rank`.
+    Moved,
+    /// An entry was inserted or moved above it.
+    InsertedAbove,
+    /// An entry above it was removed or moved below it.
+    RemovedAbove,
+    /// Scores or the ranking mode changed.
+    Rescored,
+    /// An earlier change was undone.
+    Undone,
+    /// A game was recorded and the ratings changed.
+    Played,
+    /// The leaderboards a composite leaderboard combines changed.
+    Combined,
+}
+
+impl std::fmt::Display for RankCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RankCause::Unknown => write!(f, "unknown"),
+            RankCause::Moved => write!(f, "moved"),
+            RankCause::InsertedAbove => write!(f, "entry added above"),
+            RankCause::RemovedAbove => write!(f, "entry removed above"),
+            RankCause::Rescored => write!(f, "rescored"),
+            RankCause::Undone => write!(f, "undo"),
+            RankCause::Played => write!(f, "game played"),
+            RankCause::Combined => write!(f, "sources changed"),
+        }
+    }
+}
+
+/// How an entry's rank compares to its rank at some earlier time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    /// The entry was added since.
+    New,
+    Up(usize),
+    Down(usize),
+    Same,
+}
+
+impl std::fmt::Display for Movement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Movement::New => write!(f, "NEW"),
+            Movement::Up(by) => write!(f, "▲{}", by),
+            Movement::Down(by) => write!(f, "▼{}", by),
+            Movement::Same => Ok(()),
+        }
+    }
+}
+
+impl Node {
+    /// A new entry, added now.
+    pub fn new(name: &str, rank: Rank, id: ID, score: Option<f64>) -> Self {
+        Node {
+            name: name.to_string(),
+            rank,
+            id,
+            score,
+            details: Details::default(),
+            added_at: Some(chrono::Utc::now().timestamp()),
+            rank_history: Vec::new(),
+        }
+    }
+
+    /// Unix time of the last rank change.
+    pub fn moved_at(&self) -> Option<i64> {
+        self.rank_history.last().map(|change| change.at)
+    }
+
+    /// The rank the entry had at unix time `at`, `None` if it was added
+    /// later. Entries from older files count as always having been there.
+    pub fn rank_at(&self, at: i64) -> Option<Rank> {
+        if self.added_at.is_some_and(|added_at| added_at > at) {
+            return None;
+        }
+        Some(self.rank_history.iter()
+            .find(|change| change.at > at)
+            .map_or(self.rank, |change| change.from))
+    }
+
+    /// How the entry moved since unix time `at`.
+    pub fn movement_since(&self, at: i64) -> Movement {
+        match self.rank_at(at) {
+            None => Movement::New,
+            Some(then) if then > self.rank => Movement::Up(then - self.rank),
+            Some(then) if then < self.rank => Movement::Down(self.rank - then),
+            Some(_) => Movement::Same,
+        }
+    }
+
+    /// Records a rank change, dropping the oldest ones past
+    /// [`RANK_HISTORY_LIMIT`].
+    pub fn record_rank_change(&mut self, change: RankChange) {
+        self.rank_history.push(change);
+        if self.rank_history.len() > RANK_HISTORY_LIMIT {
+            self.rank_history.drain(..self.rank_history.len() - RANK_HISTORY_LIMIT);
+        }
+    }
+}
+
+/// Free-form information about an entry that plays no part in ranking it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Details {
+    pub notes: String,
+    pub tags: BTreeSet<String>,
+    /// Named values such as platform, year or URL.
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Details {
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty() && self.tags.is_empty() && self.attributes.is_empty()
+    }
+
+    /// Tags from a comma separated list.
+    pub fn parse_tags(text: &str) -> BTreeSet<String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Attributes from `key: value` lines, blank lines are skipped.
+    pub fn parse_attributes<'l>(lines: impl IntoIterator<Item = &'l str>) -> Result<BTreeMap<String, String>, String> {
+        let mut attributes = BTreeMap::new();
+        for line in lines.into_iter().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once(':')
+                .ok_or(format!("Attribute \"{}\" should look like key: value", line.trim()))?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(format!("Attribute \"{}\" has no name", line.trim()));
+            }
+            attributes.insert(key.to_string(), value.trim().to_string());
+        }
+        Ok(attributes)
+    }
+}
+
+/// Nodes order by rank, and by id when two nodes share a rank, so that
+/// sorting is deterministic.
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.rank.cmp(&other.rank)
+        self.rank.cmp(&other.rank).then(self.id.cmp(&other.id))
     }
 }
 
@@ -22,12 +196,12 @@ impl Eq for Node {
 
 impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.rank.cmp(&other.rank))
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for Node {
     fn eq(&self, other: &Self) -> bool {
-        self.rank == other.rank
+        self.rank == other.rank && self.id == other.id
     }
 }
@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+
+use crate::node::Rank;
+
+/// Finds the rank of a new entry among `len` ranked entries by asking
+/// whether it ranks above the entry in the middle of the remaining range,
+/// which takes about log2(len) questions.
+#[derive(Debug, Clone)]
+pub struct BinaryInsertion {
+    /// Positions `low..high` of the ranked entries are still candidates for
+    /// the entry right below the new one.
+    low: usize,
+    high: usize,
+}
+
+impl BinaryInsertion {
+    pub fn new(len: usize) -> Self {
+        BinaryInsertion { low: 0, high: len }
+    }
+
+    /// Position of the entry the new one should be compared with next, or
+    /// `None` once its rank is known.
+    pub fn question(&self) -> Option<usize> {
+        (self.low < self.high).then(|| (self.low + self.high) / 2)
+    }
+
+    /// Answers the current question: whether the new entry ranks above the
+    /// entry it was compared with.
+    pub fn answer(&mut self, ranks_above: bool) {
+        let Some(middle) = self.question() else { return };
+        if ranks_above {
+            self.high = middle;
+        } else {
+            self.low = middle + 1;
+        }
+    }
+
+    /// The rank of the new entry, once every question is answered.
+    pub fn rank(&self) -> Option<Rank> {
+        self.question().is_none().then_some(self.low + 1)
+    }
+
+    /// Upper bound on the questions still to come.
+    pub fn remaining(&self) -> usize {
+        let mut remaining = 0;
+        let mut range = self.high - self.low;
+        while range > 0 {
+            range /= 2;
+            remaining += 1;
+        }
+        remaining
+    }
+}
+
+/// Orders a list by asking which of two items ranks higher, merging sorted
+/// runs of items the way a merge sort does.
+#[derive(Debug, Clone)]
+pub struct MergeSort<T> {
+    /// Sorted runs waiting to be merged.
+    runs: VecDeque<Vec<T>>,
+    /// The merge in progress: the two runs being merged and the merged part.
+    left: VecDeque<T>,
+    right: VecDeque<T>,
+    merged: Vec<T>,
+    asked: usize,
+}
+
+impl<T> MergeSort<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        let mut sort = MergeSort {
+            runs: items.into_iter().map(|item| vec![item]).collect(),
+            left: VecDeque::new(),
+            right: VecDeque::new(),
+            merged: Vec::new(),
+            asked: 0,
+        };
+        sort.advance();
+        sort
+    }
+
+    /// The two items to compare next, or `None` once the list is sorted.
+    pub fn question(&self) -> Option<(&T, &T)> {
+        Some((self.left.front()?, self.right.front()?))
+    }
+
+    /// Answers the current question: whether the first item ranks above the
+    /// second.
+    pub fn answer(&mut self, first_ranks_above: bool) {
+        if self.question().is_none() {
+            return;
+        }
+        let winner = if first_ranks_above { self.left.pop_front() } else { self.right.pop_front() };
+        self.merged.extend(winner);
+        self.asked += 1;
+        self.advance();
+    }
+
+    /// Finishes merges that need no more answers and starts the next one.
+    fn advance(&mut self) {
+        loop {
+            if !self.left.is_empty() && !self.right.is_empty() {
+                return;
+            }
+            if !self.left.is_empty() || !self.right.is_empty() || !self.merged.is_empty() {
+                let mut run = std::mem::take(&mut self.merged);
+                run.extend(self.left.drain(..));
+                run.extend(self.right.drain(..));
+                self.runs.push_back(run);
+            }
+            if self.runs.len() < 2 {
+                return;
+            }
+            self.left = self.runs.pop_front().unwrap_or_default().into();
+            self.right = self.runs.pop_front().unwrap_or_default().into();
+        }
+    }
+
+    /// How many questions have been answered.
+    pub fn asked(&self) -> usize {
+        self.asked
+    }
+
+    /// The sorted list, once every question is answered.
+    pub fn result(self) -> Option<Vec<T>> {
+        if self.question().is_some() {
+            return None;
+        }
+        let mut runs = self.runs;
+        Some(runs.pop_front().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Places an entry meant for `rank` among `len` entries, returning the
+    /// rank found and how many questions it took.
+    fn insert(len: usize, rank: Rank) -> (Option<Rank>, usize) {
+        let mut insertion = BinaryInsertion::new(len);
+        let bound = insertion.remaining();
+        let mut asked = 0;
+        while let Some(position) = insertion.question() {
+            insertion.answer(position + 1 >= rank);
+            asked += 1;
+            assert!(asked <= bound, "asked more than {} questions", bound);
+        }
+        (insertion.rank(), asked)
+    }
+
+    /// Sorts `items` by comparing them, returning the result and how many
+    /// questions it took.
+    fn sort(items: Vec<u32>) -> (Option<Vec<u32>>, usize) {
+        let mut sort = MergeSort::new(items);
+        while let Some((a, b)) = sort.question() {
+            let above = a < b;
+            sort.answer(above);
+        }
+        let asked = sort.asked();
+        (sort.result(), asked)
+    }
+
+    fn permutations(items: Vec<u32>) -> Vec<Vec<u32>> {
+        if items.len() <= 1 {
+            return vec![items];
+        }
+        let mut all = Vec::new();
+        for i in 0..items.len() {
+            let mut rest = items.clone();
+            let first = rest.remove(i);
+            for mut permutation in permutations(rest) {
+                permutation.insert(0, first);
+                all.push(permutation);
+            }
+        }
+        all
+    }
+
+    #[test]
+    fn binary_insertion_finds_every_rank() {
+        for len in 0..=16 {
+            for rank in 1..=len + 1 {
+                let (found, asked) = insert(len, rank);
+                assert_eq!(found, Some(rank), "{} entries", len);
+                assert!(asked <= (len + 1).next_power_of_two().trailing_zeros() as usize, "{} entries, rank {}", len, rank);
+            }
+        }
+    }
+
+    #[test]
+    fn binary_insertion_into_an_empty_list_asks_nothing() {
+        let insertion = BinaryInsertion::new(0);
+        assert_eq!(insertion.question(), None);
+        assert_eq!(insertion.remaining(), 0);
+        assert_eq!(insertion.rank(), Some(1));
+    }
+
+    #[test]
+    fn binary_insertion_has_no_rank_until_finished() {
+        let mut insertion = BinaryInsertion::new(4);
+        assert_eq!(insertion.question(), Some(2));
+        assert_eq!(insertion.rank(), None);
+        insertion.answer(true);
+        assert_eq!(insertion.question(), Some(1));
+        insertion.answer(false);
+        assert_eq!(insertion.rank(), Some(3));
+        assert_eq!(insertion.remaining(), 0);
+    }
+
+    #[test]
+    fn merge_sort_orders_every_permutation() {
+        for len in 0..=6 {
+            let sorted: Vec<u32> = (0..len).collect();
+            let bound = (len as usize) * (len as usize).next_power_of_two().trailing_zeros() as usize;
+            for items in permutations(sorted.clone()) {
+                let (result, asked) = sort(items.clone());
+                assert_eq!(result.as_ref(), Some(&sorted), "sorting {:?}", items);
+                assert!(asked <= bound, "sorting {:?} took {} questions", items, asked);
+            }
+        }
+    }
+
+    #[test]
+    fn merge_sort_question_counts() {
+        assert_eq!(sort(vec![]).1, 0);
+        assert_eq!(sort(vec![1]).1, 0);
+        assert_eq!(sort(vec![2, 1]).1, 1);
+        assert_eq!(sort((0..8).collect()).1, 12);
+        assert_eq!(sort((0..8).rev().collect()).1, 12);
+        assert_eq!(sort(vec![0, 2, 4, 6, 1, 3, 5, 7]).1, 15);
+    }
+
+    #[test]
+    fn merge_sort_has_no_result_until_finished() {
+        let mut sort = MergeSort::new(vec!["b", "a", "c"]);
+        assert_eq!(sort.question(), Some((&"b", &"a")));
+        sort.answer(false);
+        assert_eq!(sort.asked(), 1);
+        assert!(sort.clone().result().is_none());
+        while sort.question().is_some() {
+            sort.answer(false);
+        }
+        assert_eq!(sort.result(), Some(vec!["a", "b", "c"]));
+    }
+}
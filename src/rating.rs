@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+use crate::node::ID;
+
+/// Rating every player starts from, in both systems.
+pub const INITIAL_RATING: f64 = 1500.0;
+/// How far a single Elo game can move a rating.
+pub const ELO_K: f64 = 32.0;
+/// Glicko-2 rating deviation and volatility of a new player.
+const GLICKO_DEVIATION: f64 = 350.0;
+const GLICKO_VOLATILITY: f64 = 0.06;
+/// Glicko-2 system constant, limiting how fast volatility changes.
+const GLICKO_TAU: f64 = 0.5;
+/// Converts between the Glicko and Glicko-2 rating scales.
+const GLICKO_SCALE: f64 = 173.7178;
+
+/// How the ratings of a match ranked leaderboard are computed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RatingSystem {
+    #[default]
+    Elo,
+    /// Glicko-2, treating every game as its own rating period.
+    Glicko2,
+}
+
+impl RatingSystem {
+    /// The system that follows this one, used to cycle through systems in
+    /// the UI.
+    pub fn next(&self) -> RatingSystem {
+        match self {
+            RatingSystem::Elo => RatingSystem::Glicko2,
+            RatingSystem::Glicko2 => RatingSystem::Elo,
+        }
+    }
+}
+
+impl std::fmt::Display for RatingSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RatingSystem::Elo => write!(f, "Elo"),
+            RatingSystem::Glicko2 => write!(f, "Glicko-2"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    FirstWins,
+    SecondWins,
+    Draw,
+}
+
+/// A game between two entries of a match ranked leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Game {
+    pub first: ID,
+    pub second: ID,
+    pub outcome: Outcome,
+    /// Unix time the game was recorded.
+    pub played_at: i64,
+}
+
+impl Game {
+    /// The points `first` and `second` got from the game.
+    fn points(&self) -> (f64, f64) {
+        match self.outcome {
+            Outcome::FirstWins => (1.0, 0.0),
+            Outcome::SecondWins => (0.0, 1.0),
+            Outcome::Draw => (0.5, 0.5),
+        }
+    }
+}
+
+/// A player's rating. Elo only uses `rating`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating { rating: INITIAL_RATING, deviation: GLICKO_DEVIATION, volatility: GLICKO_VOLATILITY }
+    }
+}
+
+/// Computes the ratings of every player from scratch by playing `games` in
+/// order. Players without games are left out and keep the initial rating.
+pub fn replay(system: RatingSystem, games: &[Game]) -> HashMap<ID, Rating> {
+    let mut ratings: HashMap<ID, Rating> = HashMap::new();
+    for game in games {
+        let first = ratings.get(&game.first).copied().unwrap_or_default();
+        let second = ratings.get(&game.second).copied().unwrap_or_default();
+        let (first_points, second_points) = game.points();
+        let (first, second) = match system {
+            RatingSystem::Elo => (elo(first, second, first_points), elo(second, first, second_points)),
+            RatingSystem::Glicko2 => (glicko2(first, &[(second, first_points)]), glicko2(second, &[(first, second_points)])),
+        };
+        ratings.insert(game.first, first);
+        ratings.insert(game.second, second);
+    }
+    ratings
+}
+
+fn elo(player: Rating, opponent: Rating, points: f64) -> Rating {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent.rating - player.rating) / 400.0));
+    Rating { rating: player.rating + ELO_K * (points - expected), ..player }
+}
+
+/// One Glicko-2 rating period in which `player` played the opponents of
+/// `results` and got the points next to them, following Glickman's
+/// "Example of the Glicko-2 system". Replays use one game per period.
+fn glicko2(player: Rating, results: &[(Rating, f64)]) -> Rating {
+    let mu = (player.rating - INITIAL_RATING) / GLICKO_SCALE;
+    let phi = player.deviation / GLICKO_SCALE;
+
+    let mut information = 0.0;
+    let mut improvement = 0.0;
+    for (opponent, points) in results {
+        let opponent_mu = (opponent.rating - INITIAL_RATING) / GLICKO_SCALE;
+        let opponent_phi = opponent.deviation / GLICKO_SCALE;
+        let g = 1.0 / (1.0 + 3.0 * opponent_phi.powi(2) / PI.powi(2)).sqrt();
+        let expected = 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp());
+        information += g.powi(2) * expected * (1.0 - expected);
+        improvement += g * (points - expected);
+    }
+    let variance = 1.0 / information;
+    let delta = variance * improvement;
+
+    let volatility = glicko2_volatility(phi, player.volatility, variance, delta);
+    let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+    let new_phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / variance).sqrt();
+    let new_mu = mu + new_phi.powi(2) * improvement;
+    Rating {
+        rating: new_mu * GLICKO_SCALE + INITIAL_RATING,
+        deviation: new_phi * GLICKO_SCALE,
+        volatility,
+    }
+}
+
+/// The new volatility, found with the Illinois algorithm.
+fn glicko2_volatility(phi: f64, sigma: f64, variance: f64, delta: f64) -> f64 {
+    const EPSILON: f64 = 0.000001;
+    let a = sigma.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - variance - ex) / (2.0 * (phi.powi(2) + variance + ex).powi(2))
+            - (x - a) / GLICKO_TAU.powi(2)
+    };
+    let mut big_a = a;
+    let mut big_b = if delta.powi(2) > phi.powi(2) + variance {
+        (delta.powi(2) - phi.powi(2) - variance).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * GLICKO_TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * GLICKO_TAU
+    };
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > EPSILON {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    (big_a / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating { rating, deviation, ..Rating::default() }
+    }
+
+    fn game(first: ID, second: ID, outcome: Outcome) -> Game {
+        Game { first, second, outcome, played_at: 0 }
+    }
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "expected {} within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn glicko2_matches_glickmans_example() {
+        let player = rating(1500.0, 200.0);
+        let results = [(rating(1400.0, 30.0), 1.0), (rating(1550.0, 100.0), 0.0), (rating(1700.0, 300.0), 0.0)];
+        let new = glicko2(player, &results);
+        assert_near(new.rating, 1464.06, 0.01);
+        assert_near(new.deviation, 151.52, 0.01);
+        assert_near(new.volatility, 0.05999, 0.00001);
+    }
+
+    #[test]
+    fn glicko2_volatility_matches_glickmans_example() {
+        // Steps 3 and 4 of the example: v = 1.7785, delta = -0.4834.
+        let volatility = glicko2_volatility(200.0 / GLICKO_SCALE, 0.06, 1.7785, -0.4834);
+        assert_near(volatility, 0.05999, 0.00001);
+    }
+
+    #[test]
+    fn elo_moves_equal_players_by_half_of_k() {
+        let ratings = replay(RatingSystem::Elo, &[game(1, 2, Outcome::FirstWins)]);
+        assert_near(ratings[&1].rating, INITIAL_RATING + ELO_K / 2.0, 1e-9);
+        assert_near(ratings[&2].rating, INITIAL_RATING - ELO_K / 2.0, 1e-9);
+    }
+
+    #[test]
+    fn elo_draw_between_equals_changes_nothing() {
+        let ratings = replay(RatingSystem::Elo, &[game(1, 2, Outcome::Draw)]);
+        assert_near(ratings[&1].rating, INITIAL_RATING, 1e-9);
+        assert_near(ratings[&2].rating, INITIAL_RATING, 1e-9);
+    }
+
+    #[test]
+    fn elo_keeps_the_rating_total() {
+        let games = [
+            game(1, 2, Outcome::FirstWins),
+            game(2, 3, Outcome::SecondWins),
+            game(3, 1, Outcome::Draw),
+            game(1, 2, Outcome::SecondWins),
+        ];
+        let ratings = replay(RatingSystem::Elo, &games);
+        assert_near(ratings.values().map(|rating| rating.rating).sum(), 3.0 * INITIAL_RATING, 1e-9);
+        assert!(ratings[&3].rating > ratings[&2].rating);
+    }
+
+    #[test]
+    fn replay_plays_games_in_order() {
+        let first = replay(RatingSystem::Elo, &[game(1, 2, Outcome::FirstWins), game(1, 3, Outcome::SecondWins)]);
+        let second = replay(RatingSystem::Elo, &[game(1, 3, Outcome::SecondWins), game(1, 2, Outcome::FirstWins)]);
+        assert_ne!(first[&2].rating, second[&2].rating);
+        assert!(!first.contains_key(&4));
+    }
+
+    #[test]
+    fn glicko2_replay_treats_new_players_alike() {
+        let ratings = replay(RatingSystem::Glicko2, &[game(1, 2, Outcome::FirstWins)]);
+        let (winner, loser) = (ratings[&1], ratings[&2]);
+        assert!(winner.rating > INITIAL_RATING);
+        assert_near(winner.rating - INITIAL_RATING, INITIAL_RATING - loser.rating, 1e-6);
+        assert_near(winner.deviation, loser.deviation, 1e-9);
+        assert!(winner.deviation < Rating::default().deviation);
+    }
+}
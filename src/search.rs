@@ -0,0 +1,207 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::node::{Node, Rank, ID};
+use crate::storage::Storage;
+
+/// How a search query is matched against entry names.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// The name contains the query, ignoring case.
+    #[default]
+    IgnoreCase,
+    /// The name contains the query exactly.
+    Exact,
+    /// The name is the query, looked up by the store's index of names
+    /// where it has one.
+    Whole,
+    /// The characters of the query appear in the name in order, ignoring
+    /// case, so "mrk" finds "Mark".
+    Fuzzy,
+}
+
+impl MatchMode {
+    /// The mode that follows this one, used to cycle through modes in the UI.
+    pub fn next(&self) -> MatchMode {
+        match self {
+            MatchMode::IgnoreCase => MatchMode::Exact,
+            MatchMode::Exact => MatchMode::Whole,
+            MatchMode::Whole => MatchMode::Fuzzy,
+            MatchMode::Fuzzy => MatchMode::IgnoreCase,
+        }
+    }
+}
+
+impl std::fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchMode::IgnoreCase => write!(f, "ignore case"),
+            MatchMode::Exact => write!(f, "exact"),
+            MatchMode::Whole => write!(f, "whole name"),
+            MatchMode::Fuzzy => write!(f, "fuzzy"),
+        }
+    }
+}
+
+/// Whether `name` matches `query`. An empty query matches nothing.
+pub fn matches(name: &str, query: &str, mode: MatchMode) -> bool {
+    if query.is_empty() {
+        return false;
+    }
+    match mode {
+        MatchMode::Exact => name.contains(query),
+        MatchMode::Whole => name == query,
+        MatchMode::IgnoreCase => name.to_lowercase().contains(&query.to_lowercase()),
+        MatchMode::Fuzzy => {
+            let mut chars = name.chars().flat_map(char::to_lowercase);
+            query.chars()
+                .flat_map(char::to_lowercase)
+                .filter(|c| !c.is_whitespace())
+                .all(|q| chars.any(|c| c == q))
+        },
+    }
+}
+
+/// Positions of the entries whose names match `query`, in rank order.
+pub fn find_matches(entries: &[Node], query: &str, mode: MatchMode) -> Vec<usize> {
+    entries.iter()
+        .enumerate()
+        .filter(|(_, node)| matches(&node.name, query, mode))
+        .map(|(position, _)| position)
+        .collect()
+}
+
+/// An entry found by [`search_boards`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub board: String,
+    pub rank: Rank,
+    pub id: ID,
+    pub name: String,
+}
+
+impl std::fmt::Display for SearchHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}. {} (id {})", self.board, self.rank, self.name, self.id)
+    }
+}
+
+/// Every entry matching `query` in the leaderboards called `boards`, in the
+/// order of the boards and then by rank.
+pub fn search_boards(storage: &dyn Storage, boards: &[String], query: &str, mode: MatchMode) -> Result<Vec<SearchHit>, Box<dyn Error>> {
+    if mode == MatchMode::Whole {
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut hits: Vec<(usize, SearchHit)> = storage.find_entries(query)?.into_iter()
+            .filter_map(|(board, rank, id)| {
+                let position = boards.iter().position(|name| *name == board)?;
+                Some((position, SearchHit { board, rank, id, name: query.to_string() }))
+            })
+            .collect();
+        hits.sort_by_key(|(position, hit)| (*position, hit.rank));
+        return Ok(hits.into_iter().map(|(_, hit)| hit).collect());
+    }
+    let mut hits = Vec::new();
+    for board in boards {
+        let leaderboard = storage.read_leaderboard(board)?;
+        for position in find_matches(leaderboard.entries(), query, mode) {
+            let node = &leaderboard.entries()[position];
+            hits.push(SearchHit { board: board.clone(), rank: node.rank, id: node.id, name: node.name.clone() });
+        }
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::leaderboard::Leaderboard;
+    use crate::storage::MemoryStorage;
+
+    fn entries(names: &[&str]) -> Vec<Node> {
+        names.iter().enumerate()
+            .map(|(position, name)| Node::new(name, position + 1, position + 1, None))
+            .collect()
+    }
+
+    /// A store holding a leaderboard for each of `boards`, listed in its
+    /// index in that order.
+    fn storage(boards: &[(&str, &[&str])]) -> Rc<dyn Storage> {
+        let storage: Rc<dyn Storage> = Rc::new(MemoryStorage::new());
+        for (board, names) in boards {
+            let mut leaderboard = Leaderboard::new(board, storage.clone());
+            for (position, name) in names.iter().enumerate() {
+                leaderboard.new_entry(name, position + 1).unwrap();
+            }
+        }
+        let index: Vec<String> = boards.iter().map(|(board, _)| board.to_string()).collect();
+        storage.write_index(&index).unwrap();
+        storage
+    }
+
+    fn found(storage: &Rc<dyn Storage>, boards: &[&str], query: &str, mode: MatchMode) -> Vec<String> {
+        let boards: Vec<String> = boards.iter().map(|board| board.to_string()).collect();
+        search_boards(storage.as_ref(), &boards, query, mode).unwrap().iter().map(SearchHit::to_string).collect()
+    }
+
+    #[test]
+    fn each_mode_matches_names_its_own_way() {
+        let entries = entries(&["Mark", "mario", "Amara", "Ma rk", "Mark"]);
+        assert_eq!(find_matches(&entries, "mar", MatchMode::IgnoreCase), [0, 1, 2, 4]);
+        assert_eq!(find_matches(&entries, "Mar", MatchMode::Exact), [0, 4]);
+        assert_eq!(find_matches(&entries, "Mark", MatchMode::Whole), [0, 4]);
+        assert_eq!(find_matches(&entries, "mark", MatchMode::Whole), Vec::<usize>::new());
+        assert_eq!(find_matches(&entries, "mrk", MatchMode::Fuzzy), [0, 3, 4]);
+        assert_eq!(find_matches(&entries, "m rk", MatchMode::Fuzzy), [0, 3, 4]);
+        assert_eq!(find_matches(&entries, "km", MatchMode::Fuzzy), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn an_empty_query_matches_nothing() {
+        let entries = entries(&["Mark", ""]);
+        for mode in [MatchMode::IgnoreCase, MatchMode::Exact, MatchMode::Whole, MatchMode::Fuzzy] {
+            assert!(find_matches(&entries, "", mode).is_empty(), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn modes_cycle_through_all_of_them() {
+        let mut mode = MatchMode::default();
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(mode);
+            mode = mode.next();
+        }
+        assert_eq!(mode, MatchMode::default());
+        assert_eq!(seen, [MatchMode::IgnoreCase, MatchMode::Exact, MatchMode::Whole, MatchMode::Fuzzy]);
+    }
+
+    #[test]
+    fn searching_every_board_lists_hits_by_board_then_rank() {
+        let storage = storage(&[("games", &["Zelda", "Mario Kart", "Metroid"]), ("players", &["mario", "Link"])]);
+        let boards = ["players", "games"];
+        assert_eq!(found(&storage, &boards, "mario", MatchMode::IgnoreCase), [
+            "players: 1. mario (id 1)",
+            "games: 2. Mario Kart (id 2)",
+        ]);
+        assert_eq!(found(&storage, &boards, "Mario", MatchMode::Exact), ["games: 2. Mario Kart (id 2)"]);
+        assert_eq!(found(&storage, &boards, "mario", MatchMode::Whole), ["players: 1. mario (id 1)"]);
+        assert_eq!(found(&storage, &boards, "mt", MatchMode::Fuzzy), [
+            "games: 2. Mario Kart (id 2)",
+            "games: 3. Metroid (id 3)",
+        ]);
+        assert!(found(&storage, &boards, "", MatchMode::Whole).is_empty());
+    }
+
+    #[test]
+    fn searching_skips_boards_not_asked_for() {
+        let storage = storage(&[("a", &["Link"]), ("b", &["Link", "Zelda"])]);
+        for mode in [MatchMode::IgnoreCase, MatchMode::Exact, MatchMode::Whole, MatchMode::Fuzzy] {
+            assert_eq!(found(&storage, &["b"], "Link", mode), ["b: 1. Link (id 1)"], "{}", mode);
+        }
+        assert!(search_boards(storage.as_ref(), &["missing".to_string()], "Link", MatchMode::Exact).is_err());
+    }
+}
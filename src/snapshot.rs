@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::Leaderboard;
+use crate::node::{Node, Rank, ID};
+use crate::storage::Storage;
+use crate::trash::{format_time, now};
+
+/// A named copy of a leaderboard's ranking at some point in time, kept in
+/// the "snapshots" attachment of the leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub name: String,
+    /// Unix time the snapshot was taken.
+    pub taken_at: i64,
+    pub entries: Vec<SnapshotEntry>,
+}
+
+/// An entry as it was when a snapshot was taken.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotEntry {
+    pub id: ID,
+    pub name: String,
+    pub rank: Rank,
+    #[serde(default)]
+    pub score: Option<f64>,
+}
+
+impl From<&Node> for SnapshotEntry {
+    fn from(node: &Node) -> Self {
+        SnapshotEntry { id: node.id, name: node.name.clone(), rank: node.rank, score: node.score }
+    }
+}
+
+impl Snapshot {
+    pub fn of(leaderboard: &Leaderboard, name: &str) -> Self {
+        Snapshot {
+            name: name.to_string(),
+            taken_at: now(),
+            entries: leaderboard.entries().iter().map(SnapshotEntry::from).collect(),
+        }
+    }
+}
+
+/// The snapshots of the leaderboard `board`, oldest first.
+pub fn read_snapshots(storage: &dyn Storage, board: &str) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    match storage.read_attachment(board, "snapshots")? {
+        Some(data) => Ok(serde_json::from_str(&data)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn write_snapshots(storage: &dyn Storage, board: &str, snapshots: &[Snapshot]) -> Result<(), Box<dyn Error>> {
+    if snapshots.is_empty() {
+        storage.remove_attachment(board, "snapshots")
+    } else {
+        storage.write_attachment(board, "snapshots", &serde_json::to_string(snapshots)?)
+    }
+}
+
+/// Stores a snapshot of `leaderboard` called `name`. Snapshot names are
+/// unique per leaderboard.
+pub fn take_snapshot(storage: &dyn Storage, leaderboard: &Leaderboard, name: &str) -> Result<(), Box<dyn Error>> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Snapshot name must not be empty".into());
+    }
+    let mut snapshots = read_snapshots(storage, leaderboard.name())?;
+    if snapshots.iter().any(|snapshot| snapshot.name == name) {
+        return Err(format!("{} already has a snapshot named {}", leaderboard.name(), name).into());
+    }
+    snapshots.push(Snapshot::of(leaderboard, name));
+    write_snapshots(storage, leaderboard.name(), &snapshots)
+}
+
+pub fn remove_snapshot(storage: &dyn Storage, board: &str, name: &str) -> Result<(), Box<dyn Error>> {
+    let mut snapshots = read_snapshots(storage, board)?;
+    let before = snapshots.len();
+    snapshots.retain(|snapshot| snapshot.name != name);
+    if snapshots.len() == before {
+        return Err(format!("{} has no snapshot named {}", board, name).into());
+    }
+    write_snapshots(storage, board, &snapshots)
+}
+
+/// How the entries of a leaderboard changed between two rankings.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Describes the older ranking.
+    pub from: String,
+    /// Describes the newer ranking.
+    pub to: String,
+    pub added: Vec<SnapshotEntry>,
+    pub removed: Vec<SnapshotEntry>,
+    /// Entries that rose, with their old rank.
+    pub moved_up: Vec<(SnapshotEntry, Rank)>,
+    /// Entries that fell, with their old rank.
+    pub moved_down: Vec<(SnapshotEntry, Rank)>,
+    /// Entries whose name changed, with their old name.
+    pub renamed: Vec<(SnapshotEntry, String)>,
+}
+
+impl Comparison {
+    /// Compares two rankings of the same leaderboard, matching entries by id.
+    pub fn between(from: &str, old: &[SnapshotEntry], to: &str, new: &[SnapshotEntry]) -> Self {
+        let old_entries: HashMap<ID, &SnapshotEntry> = old.iter().map(|entry| (entry.id, entry)).collect();
+        let new_ranks: HashMap<ID, Rank> = new.iter().map(|entry| (entry.id, entry.rank)).collect();
+        let mut comparison = Comparison {
+            from: from.to_string(),
+            to: to.to_string(),
+            added: Vec::new(),
+            removed: old.iter().filter(|entry| !new_ranks.contains_key(&entry.id)).cloned().collect(),
+            moved_up: Vec::new(),
+            moved_down: Vec::new(),
+            renamed: Vec::new(),
+        };
+        for entry in new {
+            let Some(previous) = old_entries.get(&entry.id) else {
+                comparison.added.push(entry.clone());
+                continue;
+            };
+            if previous.rank > entry.rank {
+                comparison.moved_up.push((entry.clone(), previous.rank));
+            } else if previous.rank < entry.rank {
+                comparison.moved_down.push((entry.clone(), previous.rank));
+            }
+            if previous.name != entry.name {
+                comparison.renamed.push((entry.clone(), previous.name.clone()));
+            }
+        }
+        comparison
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved_up.is_empty() && self.moved_down.is_empty()
+            && self.renamed.is_empty()
+    }
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Changes from {} to {}", self.from, self.to)?;
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        if !self.added.is_empty() {
+            writeln!(f, "Added:")?;
+            for entry in &self.added {
+                writeln!(f, "  {}: {}", entry.rank, entry.name)?;
+            }
+        }
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed:")?;
+            for entry in &self.removed {
+                writeln!(f, "  {}: {}", entry.rank, entry.name)?;
+            }
+        }
+        if !self.moved_up.is_empty() {
+            writeln!(f, "Moved up:")?;
+            for (entry, from) in &self.moved_up {
+                writeln!(f, "  {}: {} ▲{} (was {})", entry.rank, entry.name, from - entry.rank, from)?;
+            }
+        }
+        if !self.moved_down.is_empty() {
+            writeln!(f, "Moved down:")?;
+            for (entry, from) in &self.moved_down {
+                writeln!(f, "  {}: {} ▼{} (was {})", entry.rank, entry.name, entry.rank - from, from)?;
+            }
+        }
+        if !self.renamed.is_empty() {
+            writeln!(f, "Renamed:")?;
+            for (entry, from) in &self.renamed {
+                writeln!(f, "  {}: {} (was {})", entry.rank, entry.name, from)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares the snapshot `from` of `leaderboard` with the snapshot `to`, or
+/// with the live leaderboard when `to` is `None`.
+pub fn compare(storage: &dyn Storage, leaderboard: &Leaderboard, from: &str, to: Option<&str>) -> Result<Comparison, Box<dyn Error>> {
+    let snapshots = read_snapshots(storage, leaderboard.name())?;
+    let find = |name: &str| snapshots.iter()
+        .find(|snapshot| snapshot.name == name)
+        .ok_or_else(|| format!("{} has no snapshot named {}", leaderboard.name(), name));
+    let old = find(from)?;
+    let from = format!("{} ({})", old.name, format_time(old.taken_at));
+    Ok(match to {
+        Some(to) => {
+            let new = find(to)?;
+            Comparison::between(&from, &old.entries, &format!("{} ({})", new.name, format_time(new.taken_at)), &new.entries)
+        },
+        None => {
+            let live: Vec<SnapshotEntry> = leaderboard.entries().iter().map(SnapshotEntry::from).collect();
+            Comparison::between(&from, &old.entries, "now", &live)
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::storage::MemoryStorage;
+
+    fn entry(id: ID, name: &str, rank: Rank) -> SnapshotEntry {
+        SnapshotEntry { id, name: name.to_string(), rank, score: None }
+    }
+
+    fn names<T>(entries: &[(SnapshotEntry, T)]) -> Vec<&str> {
+        entries.iter().map(|(entry, _)| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn the_same_ranking_has_no_changes() {
+        let ranking = [entry(1, "A", 1), entry(2, "B", 2)];
+        let comparison = Comparison::between("old", &ranking, "new", &ranking);
+        assert!(comparison.is_empty());
+        assert_eq!(comparison.to_string(), "Changes from old to new\nNo changes\n");
+    }
+
+    #[test]
+    fn entries_are_matched_by_id() {
+        let old = [entry(1, "A", 1), entry(2, "B", 2), entry(3, "C", 3), entry(4, "D", 4)];
+        let new = [entry(3, "C", 1), entry(5, "E", 2), entry(1, "Alpha", 3), entry(4, "D", 4)];
+        let comparison = Comparison::between("old", &old, "new", &new);
+        assert_eq!(comparison.added, [entry(5, "E", 2)]);
+        assert_eq!(comparison.removed, [entry(2, "B", 2)]);
+        assert_eq!(comparison.moved_up, [(entry(3, "C", 1), 3)]);
+        assert_eq!(comparison.moved_down, [(entry(1, "Alpha", 3), 1)]);
+        assert_eq!(comparison.renamed, [(entry(1, "Alpha", 3), "A".to_string())]);
+        assert_eq!(comparison.to_string(), "Changes from old to new\n\
+            Added:\n  2: E\n\
+            Removed:\n  2: B\n\
+            Moved up:\n  1: C ▲2 (was 3)\n\
+            Moved down:\n  3: Alpha ▼2 (was 1)\n\
+            Renamed:\n  3: Alpha (was A)\n");
+    }
+
+    #[test]
+    fn an_entry_renamed_in_place_has_not_moved() {
+        let comparison = Comparison::between("old", &[entry(1, "A", 1)], "new", &[entry(1, "B", 1)]);
+        assert!(comparison.moved_up.is_empty() && comparison.moved_down.is_empty());
+        assert_eq!(names(&comparison.renamed), ["B"]);
+        assert!(!comparison.is_empty());
+    }
+
+    #[test]
+    fn snapshots_compare_with_each_other_and_the_live_leaderboard() {
+        let storage = Rc::new(MemoryStorage::new());
+        let mut leaderboard = Leaderboard::new("board", storage.clone());
+        leaderboard.new_entry("A", 1).unwrap();
+        leaderboard.new_entry("B", 2).unwrap();
+        take_snapshot(storage.as_ref(), &leaderboard, "before").unwrap();
+        leaderboard.change_rank(2, 1).unwrap();
+        leaderboard.new_entry("C", 3).unwrap();
+        take_snapshot(storage.as_ref(), &leaderboard, " after ").unwrap();
+        leaderboard.remove(1).unwrap();
+        leaderboard.rename_entry(1, "Alpha").unwrap();
+
+        let between = compare(storage.as_ref(), &leaderboard, "before", Some("after")).unwrap();
+        assert!(between.to.starts_with("after ("), "{}", between.to);
+        assert_eq!(between.added, [entry(3, "C", 3)]);
+        assert_eq!(names(&between.moved_up), ["B"]);
+        assert_eq!(names(&between.moved_down), ["A"]);
+        assert!(between.removed.is_empty() && between.renamed.is_empty());
+
+        let live = compare(storage.as_ref(), &leaderboard, "after", None).unwrap();
+        assert_eq!(live.to, "now");
+        assert_eq!(live.removed, [entry(2, "B", 1)]);
+        assert_eq!(names(&live.moved_up), ["Alpha", "C"]);
+        assert_eq!(names(&live.renamed), ["Alpha"]);
+        assert!(live.added.is_empty() && live.moved_down.is_empty());
+
+        assert!(compare(storage.as_ref(), &leaderboard, "missing", None).is_err());
+        assert!(compare(storage.as_ref(), &leaderboard, "before", Some("missing")).is_err());
+    }
+
+    #[test]
+    fn snapshot_names_are_unique_and_not_empty() {
+        let storage = Rc::new(MemoryStorage::new());
+        let leaderboard = Leaderboard::new("board", storage.clone());
+        assert!(take_snapshot(storage.as_ref(), &leaderboard, "  ").is_err());
+        take_snapshot(storage.as_ref(), &leaderboard, "first").unwrap();
+        assert!(take_snapshot(storage.as_ref(), &leaderboard, "first").is_err());
+        remove_snapshot(storage.as_ref(), "board", "first").unwrap();
+        assert!(remove_snapshot(storage.as_ref(), "board", "first").is_err());
+        assert_eq!(storage.read_attachment("board", "snapshots").unwrap(), None);
+    }
+}
@@ -0,0 +1,326 @@
+use std::error::Error;
+use std::path::Path;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::leaderboard::Leaderboard;
+use crate::node::{Rank, ID};
+use crate::storage::{EntryLocation, Storage};
+use crate::trash::TrashedBoard;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS board_index (
+        position INTEGER PRIMARY KEY,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS leaderboards (
+        name TEXT PRIMARY KEY,
+        settings TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS entries (
+        board TEXT NOT NULL REFERENCES leaderboards(name) ON DELETE CASCADE,
+        id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        rank INTEGER NOT NULL,
+        score REAL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (board, id)
+    );
+    CREATE INDEX IF NOT EXISTS entries_by_name ON entries(name);
+    CREATE TABLE IF NOT EXISTS attachments (
+        board TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (board, kind)
+    );
+    CREATE TABLE IF NOT EXISTS trash (
+        position INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+";
+
+/// Keeps every leaderboard in a single SQLite database.
+///
+/// Entries get a row each, with the full entry kept as JSON next to the
+/// columns used for lookups, and the rest of a leaderboard is kept as JSON in
+/// `leaderboards.settings`. Every write runs in a transaction.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStorage { connection })
+    }
+
+    /// Splits a leaderboard into its settings, kept without entries, and
+    /// its entries.
+    fn split_entries(leaderboard: &Leaderboard) -> Result<(serde_json::Value, serde_json::Value), Box<dyn Error>> {
+        let mut settings = serde_json::to_value(leaderboard)?;
+        let entries = settings["entries"].take();
+        settings["entries"] = serde_json::Value::Array(Vec::new());
+        Ok((settings, entries))
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read_index(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare("SELECT name FROM board_index ORDER BY position")?;
+        let rows = statement.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn write_index(&self, names: &[String]) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute("DELETE FROM board_index", [])?;
+        for (position, name) in names.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO board_index (position, name) VALUES (?1, ?2)",
+                params![position as i64, name],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn read_leaderboard(&self, name: &str) -> Result<Leaderboard, Box<dyn Error>> {
+        let settings: String = self.connection
+            .query_row("SELECT settings FROM leaderboards WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| format!("No leaderboard named {}", name))?;
+        let mut leaderboard: serde_json::Value = serde_json::from_str(&settings)?;
+        let mut statement = self.connection.prepare("SELECT data FROM entries WHERE board = ?1 ORDER BY rank, id")?;
+        let entries = statement
+            .query_map(params![name], |row| row.get::<_, String>(0))?
+            .map(|data| Ok(serde_json::from_str(&data?)?))
+            .collect::<Result<Vec<serde_json::Value>, Box<dyn Error>>>()?;
+        leaderboard["entries"] = serde_json::Value::Array(entries);
+        Ok(serde_json::from_value(leaderboard)?)
+    }
+
+    fn write_leaderboard(&self, leaderboard: &Leaderboard) -> Result<(), Box<dyn Error>> {
+        let (settings, entries) = Self::split_entries(leaderboard)?;
+
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute(
+            "INSERT INTO leaderboards (name, settings) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET settings = excluded.settings",
+            params![leaderboard.name(), settings.to_string()],
+        )?;
+        transaction.execute("DELETE FROM entries WHERE board = ?1", params![leaderboard.name()])?;
+        for entry in entries.as_array().into_iter().flatten() {
+            transaction.execute(
+                "INSERT INTO entries (board, id, name, rank, score, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    leaderboard.name(),
+                    entry["id"].as_i64(),
+                    entry["name"].as_str(),
+                    entry["rank"].as_i64(),
+                    entry["score"].as_f64(),
+                    entry.to_string(),
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn remove_leaderboard(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute("DELETE FROM attachments WHERE board = ?1", params![name])?;
+        let removed = transaction.execute("DELETE FROM leaderboards WHERE name = ?1", params![name])?;
+        if removed == 0 {
+            return Err(format!("No leaderboard named {}", name).into());
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn rename_leaderboard(&self, old: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        let exists: Option<String> = transaction
+            .query_row("SELECT name FROM leaderboards WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?;
+        if exists.is_some() {
+            return Err(format!("Leaderboard named {} already exists!", name).into());
+        }
+        let mut leaderboard = self.read_leaderboard(old)?;
+        leaderboard.set_name(name);
+        let (settings, _) = Self::split_entries(&leaderboard)?;
+        // Entries refer to their leaderboard, so they move over to a new row
+        // before the old one goes.
+        transaction.execute("INSERT INTO leaderboards (name, settings) VALUES (?1, ?2)", params![name, settings.to_string()])?;
+        transaction.execute("UPDATE entries SET board = ?2 WHERE board = ?1", params![old, name])?;
+        transaction.execute("DELETE FROM leaderboards WHERE name = ?1", params![old])?;
+        transaction.execute("UPDATE attachments SET board = ?2 WHERE board = ?1", params![old, name])?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn read_attachment(&self, name: &str, kind: &str) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.connection
+            .query_row(
+                "SELECT data FROM attachments WHERE board = ?1 AND kind = ?2",
+                params![name, kind],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn write_attachment(&self, name: &str, kind: &str, data: &str) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "INSERT INTO attachments (board, kind, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(board, kind) DO UPDATE SET data = excluded.data",
+            params![name, kind, data],
+        )?;
+        Ok(())
+    }
+
+    fn remove_attachment(&self, name: &str, kind: &str) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "DELETE FROM attachments WHERE board = ?1 AND kind = ?2",
+            params![name, kind],
+        )?;
+        Ok(())
+    }
+
+    /// Looks entries up by the index on their names.
+    fn find_entries(&self, name: &str) -> Result<Vec<EntryLocation>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT entries.board, entries.rank, entries.id FROM entries
+             JOIN board_index ON board_index.name = entries.board
+             WHERE entries.name = ?1 ORDER BY board_index.position, entries.rank"
+        )?;
+        let rows = statement.query_map(params![name], |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as Rank, row.get::<_, i64>(2)? as ID))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn read_trash(&self) -> Result<Vec<TrashedBoard>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare("SELECT data FROM trash ORDER BY position")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+
+    fn write_trash(&self, trash: &[TrashedBoard]) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute("DELETE FROM trash", [])?;
+        for (position, trashed) in trash.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO trash (position, data) VALUES (?1, ?2)",
+                params![position as i64, serde_json::to_string(trashed)?],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::search::{search_boards, MatchMode};
+    use crate::storage::{copy_store, DirStorage};
+
+    fn open(dir: &tempfile::TempDir) -> Rc<dyn Storage> {
+        Rc::new(SqliteStorage::open(dir.path().join("leaderboards.db")).unwrap())
+    }
+
+    #[test]
+    fn reads_back_leaderboards_and_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = open(&dir);
+        let mut leaderboard = Leaderboard::new("board", storage.clone());
+        leaderboard.new_entry("first", 1).unwrap();
+        leaderboard.new_entry("second", 1).unwrap();
+        leaderboard.remove(2).unwrap();
+        let written = leaderboard.serialize_to_json().unwrap();
+        drop(leaderboard);
+        storage.write_index(&["board".to_string()]).unwrap();
+        storage.write_attachment("board", "matches", "[1]").unwrap();
+        storage.write_attachment("board", "matches", "[2]").unwrap();
+
+        // A fresh connection sees everything.
+        let storage = open(&dir);
+        assert_eq!(storage.read_leaderboard("board").unwrap().serialize_to_json().unwrap(), written);
+        assert_eq!(storage.read_index().unwrap(), ["board"]);
+        assert_eq!(storage.read_attachment("board", "matches").unwrap().as_deref(), Some("[2]"));
+        storage.remove_attachment("board", "matches").unwrap();
+        assert!(storage.read_attachment("board", "matches").unwrap().is_none());
+
+        storage.write_attachment("board", "history", "{}").unwrap();
+        storage.remove_leaderboard("board").unwrap();
+        assert!(storage.read_leaderboard("board").is_err());
+        assert!(storage.read_attachment("board", "history").unwrap().is_none());
+        assert!(storage.remove_leaderboard("board").is_err());
+    }
+
+    #[test]
+    fn migrates_from_a_directory_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Rc<dyn Storage> = Rc::new(DirStorage::new(dir.path().join("files")));
+        let mut leaderboard = Leaderboard::new("board", files.clone());
+        leaderboard.new_entry("only", 1).unwrap();
+        let written = leaderboard.serialize_to_json().unwrap();
+        drop(leaderboard);
+        files.write_index(&["board".to_string()]).unwrap();
+        files.write_attachment("board", "snapshots", "[]").unwrap();
+
+        let storage = open(&dir);
+        assert_eq!(copy_store(files.as_ref(), storage.as_ref()).unwrap(), ["board"]);
+        assert_eq!(storage.read_leaderboard("board").unwrap().serialize_to_json().unwrap(), written);
+        assert_eq!(storage.read_attachment("board", "snapshots").unwrap().as_deref(), Some("[]"));
+    }
+
+    #[test]
+    fn renames_in_one_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = open(&dir);
+        let mut leaderboard = Leaderboard::new("old", storage.clone());
+        leaderboard.new_entry("first", 1).unwrap();
+        drop(leaderboard);
+        Leaderboard::new("taken", storage.clone()).save_leaderboard().unwrap();
+        storage.write_attachment("old", "matches", "[]").unwrap();
+
+        assert!(storage.rename_leaderboard("old", "taken").is_err());
+        assert!(storage.rename_leaderboard("missing", "new").is_err());
+        storage.rename_leaderboard("old", "new").unwrap();
+        assert!(storage.read_leaderboard("old").is_err());
+        let renamed = storage.read_leaderboard("new").unwrap();
+        assert_eq!(renamed.name(), "new");
+        assert_eq!(renamed.entries()[0].name, "first");
+        assert_eq!(storage.read_attachment("new", "matches").unwrap().as_deref(), Some("[]"));
+        assert!(storage.read_attachment("old", "matches").unwrap().is_none());
+    }
+
+    #[test]
+    fn finds_entries_by_name_across_boards() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = open(&dir);
+        for (board, names) in [("b", ["X", "Y"]), ("a", ["Y", "X"]), ("hidden", ["X", "Z"])] {
+            let mut leaderboard = Leaderboard::new(board, storage.clone());
+            for (position, name) in names.iter().enumerate() {
+                leaderboard.new_entry(name, position + 1).unwrap();
+            }
+        }
+        // Boards missing from the index are left out, the rest come in index
+        // order.
+        storage.write_index(&["b".to_string(), "a".to_string()]).unwrap();
+        assert_eq!(storage.find_entries("X").unwrap(), [("b".to_string(), 1, 1), ("a".to_string(), 2, 2)]);
+        assert_eq!(storage.find_entries("x").unwrap(), []);
+        assert_eq!(storage.find_entries("Z").unwrap(), []);
+
+        let hits = search_boards(storage.as_ref(), &["a".to_string()], "Y", MatchMode::Whole).unwrap();
+        assert_eq!(hits.iter().map(ToString::to_string).collect::<Vec<_>>(), ["a: 1. Y (id 1)"]);
+        let plan: String = SqliteStorage::open(dir.path().join("leaderboards.db")).unwrap().connection
+            .query_row("EXPLAIN QUERY PLAN SELECT id FROM entries WHERE name = 'X'", [], |row| row.get(3))
+            .unwrap();
+        assert!(plan.contains("entries_by_name"), "{}", plan);
+    }
+}
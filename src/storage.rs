@@ -1,13 +1,772 @@
-use std::io::{Write};
+use std::io::{ErrorKind, Write};
 use std::fs::File;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
 
+use crate::leaderboard::Leaderboard;
+use crate::node::{Rank, ID};
+use crate::trash::TrashedBoard;
+
+fn sibling(file_location: &str, extension: &str) -> String {
+    format!("{}.{}", file_location, extension)
+}
+
+/// Writes a file atomically, see [`write_bytes_to_file`].
 pub fn write_to_file(str: &str, file_location: &str) -> std::io::Result<()> {
-    let mut file = File::create(file_location)?;
-    file.write_all(str.as_bytes())?;
+    write_bytes_to_file(str.as_bytes(), file_location)
+}
+
+/// Writes a file atomically: the data goes to a temporary file that is
+/// synced to disk and then renamed over the target, so a crash leaves either
+/// the old or the new contents, never a partial file.
+pub fn write_bytes_to_file(data: &[u8], file_location: &str) -> std::io::Result<()> {
+    let temp_location = sibling(file_location, "tmp");
+    let mut file = File::create(&temp_location)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(&temp_location, file_location)?;
+    // Persist the rename itself. Directories cannot be opened on every
+    // platform, in which case there is nothing more we can do.
+    let dir = Path::new(file_location).parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Ok(dir) = File::open(dir.unwrap_or(Path::new("."))) {
+        let _ = dir.sync_all();
+    }
     Ok(())
 }
 
+/// Like [`write_bytes_to_file`], but first keeps the current contents of the
+/// file in `<file>.bak`.
+pub fn write_to_file_with_backup(data: &[u8], file_location: &str) -> std::io::Result<()> {
+    if Path::new(file_location).exists() {
+        std::fs::copy(file_location, sibling(file_location, "bak"))?;
+    }
+    write_bytes_to_file(data, file_location)
+}
+
 pub fn read_from_file(file_location: &str) -> std::io::Result<String> {
     let str = std::fs::read_to_string(file_location)?;
     Ok(str)
-}
\ No newline at end of file
+}
+
+/// Reads and parses a file written by [`write_to_file_with_backup`]. When the
+/// file cannot be read or parsed the backup is tried instead, and if that
+/// works the file is restored from it. A missing file is not recovered, so
+/// deleted files stay deleted.
+pub fn read_with_recovery<T>(file_location: &str, parse: impl Fn(&[u8]) -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+    let error = match std::fs::read(file_location) {
+        Ok(data) => match parse(&data) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(e.into()),
+        Err(e) => e.into(),
+    };
+    let backup_location = sibling(file_location, "bak");
+    let Ok(backup) = std::fs::read(&backup_location) else { return Err(error) };
+    let Ok(value) = parse(&backup) else { return Err(error) };
+    write_bytes_to_file(&backup, file_location)?;
+    Ok(value)
+}
+
+/// Moves a file along with its backup, if there is a file to move. Every
+/// rename done is recorded in `moved`, so it can be undone.
+fn move_file(from: &Path, to: &Path, moved: &mut Vec<(PathBuf, PathBuf)>) -> std::io::Result<()> {
+    if !from.exists() {
+        return Ok(());
+    }
+    let backup = |location: &Path| PathBuf::from(sibling(&location.to_string_lossy(), "bak"));
+    for (from, to) in [(backup(from), backup(to)), (from.to_path_buf(), to.to_path_buf())] {
+        if from.exists() {
+            std::fs::rename(&from, &to)?;
+            moved.push((from, to));
+        }
+    }
+    Ok(())
+}
+
+/// Removes a file along with its backup.
+pub fn remove_file(file_location: &str) -> std::io::Result<()> {
+    let backup_location = sibling(file_location, "bak");
+    if Path::new(&backup_location).exists() {
+        std::fs::remove_file(backup_location)?;
+    }
+    std::fs::remove_file(file_location)
+}
+
+/// Where an entry was found, as (leaderboard, rank, id).
+pub type EntryLocation = (String, Rank, ID);
+
+/// Where leaderboards are kept.
+///
+/// Besides the leaderboards themselves a store keeps an index of leaderboard
+/// names, in the order they are shown, and named attachments per leaderboard
+/// such as its undo history.
+pub trait Storage: Debug {
+    fn read_index(&self) -> Result<Vec<String>, Box<dyn Error>>;
+    fn write_index(&self, names: &[String]) -> Result<(), Box<dyn Error>>;
+
+    /// Reads a leaderboard. The returned leaderboard is not attached to any
+    /// storage, use [`Leaderboard::open_leaderboard`] to get an attached one.
+    fn read_leaderboard(&self, name: &str) -> Result<Leaderboard, Box<dyn Error>>;
+    fn write_leaderboard(&self, leaderboard: &Leaderboard) -> Result<(), Box<dyn Error>>;
+    /// Removes a leaderboard together with all of its attachments.
+    fn remove_leaderboard(&self, name: &str) -> Result<(), Box<dyn Error>>;
+    /// Renames the leaderboard `old` to `name` together with all of its
+    /// attachments, leaving either the old or the new leaderboard behind.
+    /// Refuses to overwrite an existing leaderboard.
+    fn rename_leaderboard(&self, old: &str, name: &str) -> Result<(), Box<dyn Error>>;
+
+    fn read_attachment(&self, name: &str, kind: &str) -> Result<Option<String>, Box<dyn Error>>;
+    fn write_attachment(&self, name: &str, kind: &str, data: &str) -> Result<(), Box<dyn Error>>;
+    fn remove_attachment(&self, name: &str, kind: &str) -> Result<(), Box<dyn Error>>;
+
+    /// Every entry called exactly `name` in the leaderboards of the index,
+    /// by leaderboard and then rank. Stores that index entry names look them
+    /// up rather than reading every leaderboard.
+    fn find_entries(&self, name: &str) -> Result<Vec<EntryLocation>, Box<dyn Error>> {
+        let mut found = Vec::new();
+        for board in self.read_index()? {
+            let leaderboard = self.read_leaderboard(&board)?;
+            found.extend(leaderboard.entries().iter()
+                .filter(|node| node.name == name)
+                .map(|node| (board.clone(), node.rank, node.id)));
+        }
+        Ok(found)
+    }
+
+    /// Deleted leaderboards, oldest first.
+    fn read_trash(&self) -> Result<Vec<TrashedBoard>, Box<dyn Error>>;
+    fn write_trash(&self, trash: &[TrashedBoard]) -> Result<(), Box<dyn Error>>;
+}
+
+/// Kinds of attachment a leaderboard can have.
+pub const ATTACHMENT_KINDS: &[&str] = &["history", "visits", "snapshots", "matches", "tournaments"];
+
+/// File format of stored leaderboards.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Human readable JSON.
+    #[default]
+    Json,
+    /// Compact binary encoding, for large leaderboards. Bincode files carry
+    /// no field names, so they can only be read by the version that wrote
+    /// them; convert back to JSON before upgrading.
+    Bincode,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Bincode => "bin",
+        }
+    }
+
+    fn other(&self) -> Format {
+        match self {
+            Format::Json => Format::Bincode,
+            Format::Bincode => Format::Json,
+        }
+    }
+}
+
+/// Marks leaderboard files written in the bincode format.
+const BINCODE_MAGIC: &[u8] = b"LDB\x01";
+
+pub fn encode_leaderboard(leaderboard: &Leaderboard, format: Format) -> Result<Vec<u8>, Box<dyn Error>> {
+    match format {
+        Format::Json => Ok(leaderboard.serialize_to_json()?.into_bytes()),
+        Format::Bincode => {
+            let mut data = BINCODE_MAGIC.to_vec();
+            data.extend(leaderboard.serialize_to_bincode()?);
+            Ok(data)
+        },
+    }
+}
+
+/// Decodes a leaderboard in either format, telling them apart by the bincode
+/// header.
+pub fn decode_leaderboard(data: &[u8]) -> Result<Leaderboard, Box<dyn Error>> {
+    match data.strip_prefix(BINCODE_MAGIC) {
+        Some(data) => Ok(Leaderboard::intialize_from_bincode(data)?),
+        None => Ok(Leaderboard::intialize_from_json(std::str::from_utf8(data)?)?),
+    }
+}
+
+/// Copies the index, every leaderboard and their attachments from one store
+/// into another, returning the names of the copied leaderboards.
+pub fn copy_store(from: &dyn Storage, to: &dyn Storage) -> Result<Vec<String>, Box<dyn Error>> {
+    let names = from.read_index()?;
+    for name in &names {
+        to.write_leaderboard(&from.read_leaderboard(name)?)?;
+        for kind in ATTACHMENT_KINDS {
+            if let Some(data) = from.read_attachment(name, kind)? {
+                to.write_attachment(name, kind, &data)?;
+            }
+        }
+    }
+    to.write_index(&names)?;
+    to.write_trash(&from.read_trash()?)?;
+    Ok(names)
+}
+
+/// Copies the leaderboard `from` and its attachments to a new leaderboard
+/// called `to` in the same store. Refuses to overwrite an existing
+/// leaderboard.
+pub fn copy_leaderboard(storage: &dyn Storage, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+    if storage.read_leaderboard(to).is_ok() {
+        return Err(format!("Leaderboard named {} already exists!", to).into());
+    }
+    let mut leaderboard = storage.read_leaderboard(from)?;
+    leaderboard.set_name(to);
+    storage.write_leaderboard(&leaderboard)?;
+    for kind in ATTACHMENT_KINDS {
+        if let Some(data) = storage.read_attachment(from, kind)? {
+            storage.write_attachment(to, kind, &data)?;
+        }
+    }
+    Ok(())
+}
+
+/// One file per leaderboard in a directory, with the index kept in
+/// `Leaderboards.json`, attachments in `<name>.<kind>.json` and deleted
+/// leaderboards in `.trash/trash.json`.
+///
+/// Leaderboards are written in the format of the store, as `<name>.json` or
+/// `<name>.bin`, and read in whichever format they were found in.
+#[derive(Debug, Clone)]
+pub struct DirStorage {
+    dir: PathBuf,
+    format: Format,
+}
+
+impl DirStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirStorage::with_format(dir, Format::Json)
+    }
+
+    pub fn with_format(dir: impl Into<PathBuf>, format: Format) -> Self {
+        DirStorage { dir: dir.into(), format }
+    }
+
+    /// Rewrites every leaderboard of the store in `format`, returning the
+    /// names of the converted leaderboards.
+    pub fn convert(&self, format: Format) -> Result<Vec<String>, Box<dyn Error>> {
+        let target = DirStorage::with_format(&self.dir, format);
+        let names = self.read_index()?;
+        for name in &names {
+            let leaderboard = self.read_leaderboard(name)?;
+            target.write_leaderboard(&leaderboard)?;
+        }
+        Ok(names)
+    }
+
+    fn index_location(&self) -> PathBuf {
+        self.dir.join("Leaderboards.json")
+    }
+
+    fn leaderboard_location(&self, name: &str, format: Format) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, format.extension()))
+    }
+
+    fn attachment_location(&self, name: &str, kind: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}.json", name, kind))
+    }
+
+    fn trash_location(&self) -> PathBuf {
+        self.dir.join(".trash").join("trash.json")
+    }
+
+    fn write(&self, data: &[u8], file_location: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(file_location.parent().unwrap_or(&self.dir))?;
+        write_to_file_with_backup(data, &file_location.to_string_lossy())?;
+        Ok(())
+    }
+}
+
+impl Storage for DirStorage {
+    fn read_index(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let file_location = self.index_location();
+        if !file_location.exists() {
+            return Ok(Vec::new());
+        }
+        let hmap: HashMap<String, Vec<String>> = read_with_recovery(
+            &file_location.to_string_lossy(),
+            |data| Ok(serde_json::from_slice(data)?),
+        )?;
+        Ok(hmap.get("leaderboards").cloned().unwrap_or_default())
+    }
+
+    fn write_index(&self, names: &[String]) -> Result<(), Box<dyn Error>> {
+        let mut hmap: HashMap<String, &[String]> = HashMap::new();
+        hmap.insert("leaderboards".to_owned(), names);
+        self.write(serde_json::to_string(&hmap)?.as_bytes(), &self.index_location())
+    }
+
+    fn read_leaderboard(&self, name: &str) -> Result<Leaderboard, Box<dyn Error>> {
+        let mut file_location = self.leaderboard_location(name, self.format);
+        if !file_location.exists() {
+            file_location = self.leaderboard_location(name, self.format.other());
+        }
+        if !file_location.exists() {
+            return Err(format!("No leaderboard file for {}", name).into());
+        }
+        read_with_recovery(&file_location.to_string_lossy(), decode_leaderboard)
+    }
+
+    fn write_leaderboard(&self, leaderboard: &Leaderboard) -> Result<(), Box<dyn Error>> {
+        let data = encode_leaderboard(leaderboard, self.format)?;
+        self.write(&data, &self.leaderboard_location(leaderboard.name(), self.format))?;
+        // A leaderboard lives in one format at a time.
+        let other_location = self.leaderboard_location(leaderboard.name(), self.format.other());
+        if other_location.exists() {
+            remove_file(&other_location.to_string_lossy())?;
+        }
+        Ok(())
+    }
+
+    fn remove_leaderboard(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        for kind in ATTACHMENT_KINDS {
+            self.remove_attachment(name, kind)?;
+        }
+        let mut removed = false;
+        for format in [self.format, self.format.other()] {
+            let file_location = self.leaderboard_location(name, format);
+            if file_location.exists() {
+                remove_file(&file_location.to_string_lossy())?;
+                removed = true;
+            }
+        }
+        if !removed {
+            return Err(format!("No leaderboard file for {}", name).into());
+        }
+        Ok(())
+    }
+
+    /// Attachments are moved with a rename each, then the leaderboard is
+    /// written under its new name and the old file removed. Should a step
+    /// fail, the steps before it are undone.
+    fn rename_leaderboard(&self, old: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        if self.read_leaderboard(name).is_ok() {
+            return Err(format!("Leaderboard named {} already exists!", name).into());
+        }
+        let mut leaderboard = self.read_leaderboard(old)?;
+        leaderboard.set_name(name);
+        let mut moved = Vec::new();
+        let mut written = false;
+        let result = ATTACHMENT_KINDS.iter()
+            .try_for_each(|kind| move_file(&self.attachment_location(old, kind), &self.attachment_location(name, kind), &mut moved))
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|()| self.write_leaderboard(&leaderboard))
+            .and_then(|()| {
+                written = true;
+                self.remove_leaderboard(old)
+            });
+        if result.is_err() {
+            if written {
+                let _ = remove_file(&self.leaderboard_location(name, self.format).to_string_lossy());
+            }
+            for (from, to) in moved.iter().rev() {
+                let _ = std::fs::rename(to, from);
+            }
+        }
+        result
+    }
+
+    fn read_attachment(&self, name: &str, kind: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let file_location = self.attachment_location(name, kind);
+        if !file_location.exists() {
+            return Ok(None);
+        }
+        Ok(Some(read_with_recovery(&file_location.to_string_lossy(), |data| Ok(String::from_utf8(data.to_vec())?))?))
+    }
+
+    fn write_attachment(&self, name: &str, kind: &str, data: &str) -> Result<(), Box<dyn Error>> {
+        self.write(data.as_bytes(), &self.attachment_location(name, kind))
+    }
+
+    fn remove_attachment(&self, name: &str, kind: &str) -> Result<(), Box<dyn Error>> {
+        let file_location = self.attachment_location(name, kind);
+        if file_location.exists() {
+            remove_file(&file_location.to_string_lossy())?;
+        }
+        Ok(())
+    }
+
+    fn read_trash(&self) -> Result<Vec<TrashedBoard>, Box<dyn Error>> {
+        let file_location = self.trash_location();
+        if !file_location.exists() {
+            return Ok(Vec::new());
+        }
+        read_with_recovery(&file_location.to_string_lossy(), |data| Ok(serde_json::from_slice(data)?))
+    }
+
+    fn write_trash(&self, trash: &[TrashedBoard]) -> Result<(), Box<dyn Error>> {
+        self.write(serde_json::to_string(trash)?.as_bytes(), &self.trash_location())
+    }
+}
+
+/// Everything a store holds, used by the stores that keep it all in one place.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Contents {
+    leaderboards: Vec<String>,
+    #[serde(default)]
+    boards: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    attachments: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    trash: Vec<TrashedBoard>,
+}
+
+impl Contents {
+    fn read_leaderboard(&self, name: &str) -> Result<Leaderboard, Box<dyn Error>> {
+        let data = self.boards.get(name)
+            .ok_or_else(|| format!("No leaderboard named {}", name))?;
+        Ok(serde_json::from_value(data.clone())?)
+    }
+
+    fn remove_leaderboard(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.attachments.remove(name);
+        self.boards.remove(name)
+            .ok_or_else(|| format!("No leaderboard named {}", name))?;
+        Ok(())
+    }
+
+    fn rename_leaderboard(&mut self, old: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        if self.boards.contains_key(name) {
+            return Err(format!("Leaderboard named {} already exists!", name).into());
+        }
+        let mut leaderboard = self.read_leaderboard(old)?;
+        leaderboard.set_name(name);
+        let data = serde_json::to_value(&leaderboard)?;
+        self.boards.remove(old);
+        self.boards.insert(name.to_string(), data);
+        if let Some(attachments) = self.attachments.remove(old) {
+            self.attachments.insert(name.to_string(), attachments);
+        }
+        Ok(())
+    }
+
+    fn remove_attachment(&mut self, name: &str, kind: &str) {
+        if let Some(attachments) = self.attachments.get_mut(name) {
+            attachments.remove(kind);
+        }
+    }
+}
+
+/// Keeps everything in memory, nothing touches the disk.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    contents: RefCell<Contents>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read_index(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.contents.borrow().leaderboards.clone())
+    }
+
+    fn write_index(&self, names: &[String]) -> Result<(), Box<dyn Error>> {
+        self.contents.borrow_mut().leaderboards = names.to_vec();
+        Ok(())
+    }
+
+    fn read_leaderboard(&self, name: &str) -> Result<Leaderboard, Box<dyn Error>> {
+        self.contents.borrow().read_leaderboard(name)
+    }
+
+    fn write_leaderboard(&self, leaderboard: &Leaderboard) -> Result<(), Box<dyn Error>> {
+        let data = serde_json::to_value(leaderboard)?;
+        self.contents.borrow_mut().boards.insert(leaderboard.name().to_string(), data);
+        Ok(())
+    }
+
+    fn remove_leaderboard(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.contents.borrow_mut().remove_leaderboard(name)
+    }
+
+    fn rename_leaderboard(&self, old: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        self.contents.borrow_mut().rename_leaderboard(old, name)
+    }
+
+    fn read_attachment(&self, name: &str, kind: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let contents = self.contents.borrow();
+        Ok(contents.attachments.get(name).and_then(|a| a.get(kind)).cloned())
+    }
+
+    fn write_attachment(&self, name: &str, kind: &str, data: &str) -> Result<(), Box<dyn Error>> {
+        self.contents.borrow_mut().attachments
+            .entry(name.to_string())
+            .or_default()
+            .insert(kind.to_string(), data.to_string());
+        Ok(())
+    }
+
+    fn remove_attachment(&self, name: &str, kind: &str) -> Result<(), Box<dyn Error>> {
+        self.contents.borrow_mut().remove_attachment(name, kind);
+        Ok(())
+    }
+
+    fn read_trash(&self) -> Result<Vec<TrashedBoard>, Box<dyn Error>> {
+        Ok(self.contents.borrow().trash.clone())
+    }
+
+    fn write_trash(&self, trash: &[TrashedBoard]) -> Result<(), Box<dyn Error>> {
+        self.contents.borrow_mut().trash = trash.to_vec();
+        Ok(())
+    }
+}
+
+/// Keeps the index, all leaderboards and their attachments in a single JSON
+/// file. Every write rewrites the whole file.
+#[derive(Debug, Clone)]
+pub struct SingleFileStorage {
+    path: PathBuf,
+}
+
+impl SingleFileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SingleFileStorage { path: path.into() }
+    }
+
+    fn load(&self) -> Result<Contents, Box<dyn Error>> {
+        if !self.path.exists() {
+            return Ok(Contents::default());
+        }
+        read_with_recovery(&self.path.to_string_lossy(), |data| Ok(serde_json::from_slice(data)?))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Contents) -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+        let mut contents = self.load()?;
+        f(&mut contents)?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_to_file_with_backup(serde_json::to_string(&contents)?.as_bytes(), &self.path.to_string_lossy())?;
+        Ok(())
+    }
+}
+
+impl Storage for SingleFileStorage {
+    fn read_index(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.load()?.leaderboards)
+    }
+
+    fn write_index(&self, names: &[String]) -> Result<(), Box<dyn Error>> {
+        self.update(|contents| {
+            contents.leaderboards = names.to_vec();
+            Ok(())
+        })
+    }
+
+    fn read_leaderboard(&self, name: &str) -> Result<Leaderboard, Box<dyn Error>> {
+        self.load()?.read_leaderboard(name)
+    }
+
+    fn write_leaderboard(&self, leaderboard: &Leaderboard) -> Result<(), Box<dyn Error>> {
+        let data = serde_json::to_value(leaderboard)?;
+        self.update(|contents| {
+            contents.boards.insert(leaderboard.name().to_string(), data);
+            Ok(())
+        })
+    }
+
+    fn remove_leaderboard(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contents| contents.remove_leaderboard(name))
+    }
+
+    fn rename_leaderboard(&self, old: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contents| contents.rename_leaderboard(old, name))
+    }
+
+    fn read_attachment(&self, name: &str, kind: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let contents = self.load()?;
+        Ok(contents.attachments.get(name).and_then(|a| a.get(kind)).cloned())
+    }
+
+    fn write_attachment(&self, name: &str, kind: &str, data: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contents| {
+            contents.attachments
+                .entry(name.to_string())
+                .or_default()
+                .insert(kind.to_string(), data.to_string());
+            Ok(())
+        })
+    }
+
+    fn remove_attachment(&self, name: &str, kind: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|contents| {
+            contents.remove_attachment(name, kind);
+            Ok(())
+        })
+    }
+
+    fn read_trash(&self) -> Result<Vec<TrashedBoard>, Box<dyn Error>> {
+        Ok(self.load()?.trash)
+    }
+
+    fn write_trash(&self, trash: &[TrashedBoard]) -> Result<(), Box<dyn Error>> {
+        self.update(|contents| {
+            contents.trash = trash.to_vec();
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Writes a leaderboard with a couple of entries to `storage`.
+    fn write_sample(storage: &Rc<dyn Storage>, name: &str) -> String {
+        let mut leaderboard = Leaderboard::new(name, storage.clone());
+        leaderboard.new_entry("first", 1).unwrap();
+        leaderboard.new_entry("second", 2).unwrap();
+        leaderboard.serialize_to_json().unwrap()
+    }
+
+    fn read_json(storage: &dyn Storage, name: &str) -> String {
+        storage.read_leaderboard(name).unwrap().serialize_to_json().unwrap()
+    }
+
+    #[test]
+    fn dir_storage_reads_back_what_it_wrote() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage: Rc<dyn Storage> = Rc::new(DirStorage::new(dir.path()));
+        let written = write_sample(&storage, "board");
+        storage.write_index(&["board".to_string()]).unwrap();
+        storage.write_attachment("board", "matches", "[]").unwrap();
+
+        assert_eq!(read_json(storage.as_ref(), "board"), written);
+        assert_eq!(storage.read_index().unwrap(), ["board"]);
+        assert_eq!(storage.read_attachment("board", "matches").unwrap().as_deref(), Some("[]"));
+        assert!(storage.read_attachment("board", "history").unwrap().is_none());
+
+        storage.remove_leaderboard("board").unwrap();
+        assert!(storage.read_leaderboard("board").is_err());
+        assert!(storage.read_attachment("board", "matches").unwrap().is_none());
+    }
+
+    #[test]
+    fn memory_storage_reads_back_what_it_wrote() {
+        let storage: Rc<dyn Storage> = Rc::new(MemoryStorage::new());
+        let written = write_sample(&storage, "board");
+        storage.write_attachment("board", "history", "{}").unwrap();
+        assert_eq!(read_json(storage.as_ref(), "board"), written);
+        assert_eq!(storage.read_attachment("board", "history").unwrap().as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn corrupted_files_are_recovered_from_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage: Rc<dyn Storage> = Rc::new(DirStorage::new(dir.path()));
+        // Saving the leaderboard again when it is dropped keeps a copy in
+        // the backup.
+        let written = write_sample(&storage, "board");
+        let location = dir.path().join("board.json");
+        std::fs::write(&location, b"{\"name\": \"bo").unwrap();
+
+        assert_eq!(read_json(storage.as_ref(), "board"), written);
+        // The file itself is restored too.
+        assert!(decode_leaderboard(&std::fs::read(&location).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn recovery_needs_a_readable_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("data.json");
+        let location = location.to_string_lossy();
+        let parse = |data: &[u8]| -> Result<Vec<u32>, Box<dyn Error>> { Ok(serde_json::from_slice(data)?) };
+
+        assert!(read_with_recovery(&location, parse).is_err());
+        write_to_file_with_backup(b"[1]", &location).unwrap();
+        write_to_file_with_backup(b"[2]", &location).unwrap();
+        assert_eq!(read_with_recovery(&location, parse).unwrap(), [2]);
+
+        std::fs::write(location.as_ref(), b"[").unwrap();
+        assert_eq!(read_with_recovery(&location, parse).unwrap(), [1]);
+        std::fs::write(location.as_ref(), b"[").unwrap();
+        std::fs::write(sibling(&location, "bak"), b"[").unwrap();
+        assert!(read_with_recovery(&location, parse).is_err());
+        // Deleted files stay deleted.
+        remove_file(&location).unwrap();
+        write_to_file("[3]", &sibling(&location, "bak")).unwrap();
+        assert!(read_with_recovery(&location, parse).is_err());
+    }
+
+    #[test]
+    fn formats_are_told_apart_by_the_bincode_header() {
+        let memory: Rc<dyn Storage> = Rc::new(MemoryStorage::new());
+        let written = write_sample(&memory, "board");
+        let leaderboard = memory.read_leaderboard("board").unwrap();
+
+        let binary = encode_leaderboard(&leaderboard, Format::Bincode).unwrap();
+        assert!(binary.starts_with(BINCODE_MAGIC));
+        assert_eq!(decode_leaderboard(&binary).unwrap().serialize_to_json().unwrap(), written);
+        let json = encode_leaderboard(&leaderboard, Format::Json).unwrap();
+        assert_eq!(decode_leaderboard(&json).unwrap().serialize_to_json().unwrap(), written);
+        assert!(decode_leaderboard(&binary[BINCODE_MAGIC.len()..]).is_err());
+    }
+
+    #[test]
+    fn converting_rewrites_every_leaderboard_in_the_other_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let json: Rc<dyn Storage> = Rc::new(DirStorage::new(dir.path()));
+        let written = write_sample(&json, "board");
+        json.write_index(&["board".to_string()]).unwrap();
+
+        let bincode = DirStorage::with_format(dir.path(), Format::Bincode);
+        assert_eq!(read_json(&bincode, "board"), written);
+        assert_eq!(DirStorage::new(dir.path()).convert(Format::Bincode).unwrap(), ["board"]);
+        assert!(!dir.path().join("board.json").exists());
+        assert!(std::fs::read(dir.path().join("board.bin")).unwrap().starts_with(BINCODE_MAGIC));
+        // Either store reads the other's files.
+        assert_eq!(read_json(json.as_ref(), "board"), written);
+
+        bincode.convert(Format::Json).unwrap();
+        assert!(!dir.path().join("board.bin").exists());
+        assert_eq!(read_json(&bincode, "board"), written);
+    }
+
+    /// Renames a sample leaderboard with an attachment in `storage`.
+    fn check_rename(storage: Rc<dyn Storage>) {
+        let written = write_sample(&storage, "old");
+        write_sample(&storage, "taken");
+        storage.write_attachment("old", "history", "{}").unwrap();
+
+        assert!(storage.rename_leaderboard("old", "taken").is_err());
+        assert!(storage.rename_leaderboard("missing", "new").is_err());
+        storage.rename_leaderboard("old", "new").unwrap();
+        assert!(storage.read_leaderboard("old").is_err());
+        assert!(storage.read_attachment("old", "history").unwrap().is_none());
+        let renamed = storage.read_leaderboard("new").unwrap();
+        assert_eq!(renamed.name(), "new");
+        assert_eq!(renamed.serialize_to_json().unwrap(), written.replace("\"old\"", "\"new\""));
+        assert_eq!(storage.read_attachment("new", "history").unwrap().as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn every_store_renames_leaderboards_with_their_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        check_rename(Rc::new(MemoryStorage::new()));
+        check_rename(Rc::new(SingleFileStorage::new(dir.path().join("all.json"))));
+        check_rename(Rc::new(DirStorage::new(dir.path().join("json"))));
+        check_rename(Rc::new(DirStorage::with_format(dir.path().join("bincode"), Format::Bincode)));
+        // Backups move along.
+        assert!(dir.path().join("json").join("new.history.json").exists());
+        assert!(!dir.path().join("json").join("old.json.bak").exists());
+        assert!(!dir.path().join("json").join("old.json").exists());
+    }
+}
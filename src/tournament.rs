@@ -0,0 +1,757 @@
+use std::collections::HashSet;
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::Leaderboard;
+use crate::matches::{DRAW_POINTS, WIN_POINTS};
+use crate::node::ID;
+use crate::rating::Outcome;
+use crate::storage::Storage;
+use crate::trash::now;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TournamentFormat {
+    /// Everyone plays everyone once.
+    #[default]
+    RoundRobin,
+    /// Losing once knocks a player out.
+    SingleElimination,
+    /// Losing twice knocks a player out. Players who lose once drop into the
+    /// losers bracket, whose last player meets the winners bracket's last
+    /// player in the final.
+    DoubleElimination,
+    /// Players with the same number of points meet each other, for about
+    /// log2(players) rounds.
+    Swiss,
+}
+
+impl TournamentFormat {
+    /// The format that follows this one, used to cycle through formats in
+    /// the UI.
+    pub fn next(&self) -> TournamentFormat {
+        match self {
+            TournamentFormat::RoundRobin => TournamentFormat::SingleElimination,
+            TournamentFormat::SingleElimination => TournamentFormat::DoubleElimination,
+            TournamentFormat::DoubleElimination => TournamentFormat::Swiss,
+            TournamentFormat::Swiss => TournamentFormat::RoundRobin,
+        }
+    }
+
+    fn allows_draws(&self) -> bool {
+        matches!(self, TournamentFormat::RoundRobin | TournamentFormat::Swiss)
+    }
+
+    /// How many losses knock a player out, if any do.
+    fn lives(&self) -> Option<usize> {
+        match self {
+            TournamentFormat::SingleElimination => Some(1),
+            TournamentFormat::DoubleElimination => Some(2),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TournamentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TournamentFormat::RoundRobin => write!(f, "round robin"),
+            TournamentFormat::SingleElimination => write!(f, "single elimination"),
+            TournamentFormat::DoubleElimination => write!(f, "double elimination"),
+            TournamentFormat::Swiss => write!(f, "Swiss"),
+        }
+    }
+}
+
+/// An entry taking part in a tournament. Players are kept in seed order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Player {
+    pub id: ID,
+    pub name: String,
+}
+
+/// Which part of an elimination tournament a fixture belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bracket {
+    #[default]
+    Main,
+    Losers,
+    Final,
+}
+
+/// A game of a tournament between two players, given by their seed
+/// positions. A fixture without an `away` player is a bye, which `home`
+/// wins without playing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub home: usize,
+    pub away: Option<usize>,
+    pub result: Option<Outcome>,
+    #[serde(default)]
+    pub bracket: Bracket,
+}
+
+impl Fixture {
+    fn new(home: usize, away: Option<usize>, bracket: Bracket) -> Self {
+        let result = away.is_none().then_some(Outcome::FirstWins);
+        Fixture { home, away, result, bracket }
+    }
+
+    fn is_bye(&self) -> bool {
+        self.away.is_none()
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        match self.result? {
+            Outcome::FirstWins => Some(self.home),
+            Outcome::SecondWins => self.away,
+            Outcome::Draw => None,
+        }
+    }
+
+    pub fn loser(&self) -> Option<usize> {
+        match self.result? {
+            Outcome::FirstWins => self.away,
+            Outcome::SecondWins => Some(self.home),
+            Outcome::Draw => None,
+        }
+    }
+}
+
+/// A tournament between the entries of a leaderboard, seeded by their
+/// ranks and kept in the "tournaments" attachment of the leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tournament {
+    pub name: String,
+    pub format: TournamentFormat,
+    /// Unix time the tournament was created.
+    pub created_at: i64,
+    pub players: Vec<Player>,
+    /// Fixtures by round. Round robins are drawn in full up front, the other
+    /// formats draw a round once the one before it is complete.
+    pub rounds: Vec<Vec<Fixture>>,
+}
+
+/// A player's record in a tournament.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Record {
+    won: u32,
+    drawn: u32,
+    lost: u32,
+    /// Round the player was knocked out in.
+    out_in: Option<usize>,
+    byes: u32,
+}
+
+impl Record {
+    fn points(&self) -> u32 {
+        self.won * WIN_POINTS + self.drawn * DRAW_POINTS
+    }
+}
+
+impl Tournament {
+    /// Draws the first round of a tournament between `players`, the best
+    /// seed first.
+    pub fn new(name: &str, format: TournamentFormat, players: Vec<Player>) -> Result<Self, String> {
+        if players.len() < 2 {
+            return Err("A tournament needs at least two players".to_string());
+        }
+        let mut tournament = Tournament { name: name.to_string(), format, created_at: now(), players, rounds: Vec::new() };
+        let count = tournament.players.len();
+        match format {
+            TournamentFormat::RoundRobin => tournament.rounds = round_robin(count),
+            TournamentFormat::SingleElimination | TournamentFormat::DoubleElimination => {
+                let slots = seeding_order(count.next_power_of_two());
+                tournament.rounds.push(slots.chunks(2)
+                    .map(|pair| match (pair[0] < count, pair[1] < count) {
+                        (true, true) => Fixture::new(pair[0], Some(pair[1]), Bracket::Main),
+                        (true, false) => Fixture::new(pair[0], None, Bracket::Main),
+                        _ => Fixture::new(pair[1], None, Bracket::Main),
+                    })
+                    .collect());
+            },
+            TournamentFormat::Swiss => {
+                // The top half meets the bottom half in the first round.
+                let half = count.div_ceil(2);
+                let mut round: Vec<Fixture> = (0..count / 2).map(|i| Fixture::new(i, Some(i + half), Bracket::Main)).collect();
+                if count % 2 == 1 {
+                    round.push(Fixture::new(half - 1, None, Bracket::Main));
+                }
+                tournament.rounds.push(round);
+            },
+        }
+        Ok(tournament)
+    }
+
+    /// Rounds a Swiss tournament lasts.
+    fn swiss_rounds(&self) -> usize {
+        self.players.len().next_power_of_two().trailing_zeros().max(1) as usize
+    }
+
+    fn round_complete(round: &[Fixture]) -> bool {
+        round.iter().all(|fixture| fixture.result.is_some())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        if !self.rounds.iter().all(|round| Self::round_complete(round)) {
+            return false;
+        }
+        match self.format {
+            TournamentFormat::RoundRobin => true,
+            TournamentFormat::Swiss => self.rounds.len() >= self.swiss_rounds(),
+            _ => self.next_elimination_round().is_empty(),
+        }
+    }
+
+    /// Records the result of a fixture, `round` and `fixture` counting from
+    /// zero. Results of earlier rounds can be changed as long as nothing
+    /// after them was played, the rounds drawn from them are drawn again.
+    pub fn record(&mut self, round: usize, fixture: usize, outcome: Outcome) -> Result<(), String> {
+        let Some(played) = self.rounds.get(round).and_then(|fixtures| fixtures.get(fixture)) else {
+            return Err(format!("No fixture {} in round {}", fixture + 1, round + 1));
+        };
+        if played.is_bye() {
+            return Err("Byes have no result to record".to_string());
+        }
+        if outcome == Outcome::Draw && !self.format.allows_draws() {
+            return Err(format!("Games of a {} tournament can not be drawn", self.format));
+        }
+        if self.format != TournamentFormat::RoundRobin {
+            let later_played = self.rounds[round + 1..].iter()
+                .flatten()
+                .any(|fixture| !fixture.is_bye() && fixture.result.is_some());
+            if later_played {
+                return Err(format!("Round {} can not be changed once later rounds are played", round + 1));
+            }
+            self.rounds.truncate(round + 1);
+        }
+        self.rounds[round][fixture].result = Some(outcome);
+        self.draw_next_round();
+        Ok(())
+    }
+
+    /// Draws the next round once the last one is complete.
+    fn draw_next_round(&mut self) {
+        if self.format == TournamentFormat::RoundRobin || self.is_finished() {
+            return;
+        }
+        if !self.rounds.last().is_some_and(|round| Self::round_complete(round)) {
+            return;
+        }
+        let next = match self.format {
+            TournamentFormat::Swiss => self.next_swiss_round(),
+            _ => self.next_elimination_round(),
+        };
+        if !next.is_empty() {
+            self.rounds.push(next);
+            // A round of nothing but byes needs no results.
+            self.draw_next_round();
+        }
+    }
+
+    fn records(&self) -> Vec<Record> {
+        let mut records = vec![Record::default(); self.players.len()];
+        for (number, round) in self.rounds.iter().enumerate() {
+            for fixture in round {
+                match (fixture.result, fixture.away) {
+                    (None, _) => {},
+                    (Some(_), None) => records[fixture.home].byes += 1,
+                    (Some(Outcome::Draw), Some(away)) => {
+                        records[fixture.home].drawn += 1;
+                        records[away].drawn += 1;
+                    },
+                    (Some(_), Some(_)) => {
+                        if let (Some(winner), Some(loser)) = (fixture.winner(), fixture.loser()) {
+                            records[winner].won += 1;
+                            records[loser].lost += 1;
+                            if self.format.lives().is_some_and(|lives| records[loser].lost as usize >= lives) {
+                                records[loser].out_in = Some(number);
+                            }
+                        }
+                    },
+                }
+            }
+        }
+        // Byes count as wins in Swiss, as they do in chess.
+        if self.format == TournamentFormat::Swiss {
+            for record in &mut records {
+                record.won += record.byes;
+            }
+        }
+        records
+    }
+
+    fn next_elimination_round(&self) -> Vec<Fixture> {
+        let lives = self.format.lives().unwrap_or(1);
+        let records = self.records();
+        let alive: Vec<usize> = (0..self.players.len()).filter(|&player| records[player].out_in.is_none()).collect();
+        if alive.len() < 2 {
+            return Vec::new();
+        }
+        // Players keep their place in the bracket: the order they came out of
+        // the last round in, winners before those who dropped down.
+        let mut order: Vec<usize> = Vec::new();
+        if let Some(last) = self.rounds.last() {
+            order.extend(last.iter().filter_map(Fixture::winner));
+            order.extend(last.iter().filter(|fixture| fixture.bracket == Bracket::Main).filter_map(Fixture::loser));
+        }
+        let place = |player: &usize| order.iter().position(|p| p == player).unwrap_or(order.len() + player);
+        let mut main: Vec<usize> = alive.iter().copied().filter(|&player| records[player].lost == 0).collect();
+        let mut losers: Vec<usize> = alive.iter().copied().filter(|&player| records[player].lost as usize == lives - 1 && lives > 1).collect();
+        main.sort_by_key(place);
+        losers.sort_by_key(place);
+
+        if main.len() == 1 && losers.len() == 1 {
+            return vec![Fixture::new(main[0], Some(losers[0]), Bracket::Final)];
+        }
+        let mut round = Vec::new();
+        if main.len() > 1 {
+            round.extend(pair_up(&main, Bracket::Main));
+        }
+        if losers.len() > 1 {
+            round.extend(pair_up(&losers, Bracket::Losers));
+        }
+        round
+    }
+
+    fn next_swiss_round(&self) -> Vec<Fixture> {
+        if self.rounds.len() >= self.swiss_rounds() {
+            return Vec::new();
+        }
+        let records = self.records();
+        let met: HashSet<(usize, usize)> = self.rounds.iter()
+            .flatten()
+            .filter_map(|fixture| fixture.away.map(|away| (fixture.home.min(away), fixture.home.max(away))))
+            .collect();
+        let mut standing = self.standings();
+        let mut round = Vec::new();
+        if standing.len() % 2 == 1 {
+            // The lowest placed player without a bye sits this round out.
+            let bye = standing.iter().rposition(|&player| records[player].byes == 0).unwrap_or(standing.len() - 1);
+            round.push(Fixture::new(standing.remove(bye), None, Bracket::Main));
+        }
+        let mut fixtures = Vec::new();
+        while !standing.is_empty() {
+            let home = standing.remove(0);
+            let opponent = standing.iter()
+                .position(|&away| !met.contains(&(home.min(away), home.max(away))))
+                .unwrap_or(0);
+            fixtures.push(Fixture::new(home, Some(standing.remove(opponent)), Bracket::Main));
+        }
+        fixtures.extend(round);
+        fixtures
+    }
+
+    /// The players from best to worst so far: by how far they got in
+    /// elimination formats, by points otherwise, and then by seed.
+    pub fn standings(&self) -> Vec<usize> {
+        let records = self.records();
+        let mut players: Vec<usize> = (0..self.players.len()).collect();
+        match self.format.lives() {
+            Some(_) => players.sort_by(|&a, &b| {
+                let out = |player: usize| records[player].out_in.map_or(usize::MAX, |round| round);
+                out(b).cmp(&out(a))
+                    .then(records[b].won.cmp(&records[a].won))
+                    .then(a.cmp(&b))
+            }),
+            None => players.sort_by(|&a, &b| records[b].points().cmp(&records[a].points()).then(a.cmp(&b))),
+        }
+        players
+    }
+
+    /// The standings as a table of wins, draws, losses and points.
+    pub fn standings_lines(&self) -> Vec<String> {
+        let records = self.records();
+        let width = self.players.iter().map(|player| player.name.chars().count()).max().unwrap_or(0).max(4);
+        let mut lines = vec![format!("{:>3}  {:<width$}  {:>3} {:>3} {:>3} {:>4}", "#", "Name", "W", "D", "L", "Pts")];
+        for (place, player) in self.standings().into_iter().enumerate() {
+            let record = &records[player];
+            lines.push(format!(
+                "{:>3}  {:<width$}  {:>3} {:>3} {:>3} {:>4}{}",
+                place + 1, self.players[player].name, record.won, record.drawn, record.lost, record.points(),
+                if record.out_in.is_some() { "  out" } else { "" },
+            ));
+        }
+        lines
+    }
+
+    /// Describes a fixture, e.g. "ann beat bob" or "ann vs bob" before it
+    /// is played.
+    pub fn describe(&self, fixture: &Fixture) -> String {
+        let home = &self.players[fixture.home].name;
+        let Some(away) = fixture.away.map(|away| &self.players[away].name) else {
+            return format!("{} has a bye", home);
+        };
+        let bracket = match fixture.bracket {
+            Bracket::Main => "",
+            Bracket::Losers => "losers: ",
+            Bracket::Final => "final: ",
+        };
+        match fixture.result {
+            None => format!("{}{} vs {}", bracket, home, away),
+            Some(Outcome::FirstWins) => format!("{}{} beat {}", bracket, home, away),
+            Some(Outcome::SecondWins) => format!("{}{} beat {}", bracket, away, home),
+            Some(Outcome::Draw) => format!("{}{} drew with {}", bracket, home, away),
+        }
+    }
+
+    /// The main bracket of an elimination tournament drawn as a tree, one
+    /// column per round. Round robins and Swiss tournaments have no bracket.
+    pub fn bracket_lines(&self) -> Vec<String> {
+        if self.format.lives().is_none() {
+            return Vec::new();
+        }
+        let main: Vec<Vec<&Fixture>> = self.rounds.iter()
+            .map(|round| round.iter().filter(|fixture| fixture.bracket == Bracket::Main).collect::<Vec<_>>())
+            .take_while(|round| !round.is_empty())
+            .collect();
+        let Some(first) = main.first() else { return Vec::new() };
+        let label = |player: Option<usize>| match player {
+            Some(player) => format!("{} {}", player + 1, self.players[player].name),
+            None => String::new(),
+        };
+        let width = self.players.iter().enumerate()
+            .map(|(seed, player)| format!("{} {}", seed + 1, player.name).chars().count())
+            .max()
+            .unwrap_or(0);
+        let column = width + 3;
+        let leaves = first.len() * 2;
+        let rounds = leaves.trailing_zeros() as usize;
+        let mut grid = vec![vec![' '; column * (rounds + 1)]; leaves * 2 - 1];
+        let put = |grid: &mut Vec<Vec<char>>, x: usize, y: usize, text: &str| {
+            for (i, c) in text.chars().enumerate() {
+                grid[y][x + i] = c;
+            }
+        };
+        // Lines the entrants of each round are written on, starting with the
+        // two entrants of every first round fixture.
+        let mut rows: Vec<usize> = (0..leaves).map(|leaf| leaf * 2).collect();
+        let mut entrants: Vec<Option<usize>> = first.iter()
+            .flat_map(|fixture| [Some(fixture.home), fixture.away])
+            .collect();
+        for round in 0..rounds {
+            let x = round * column;
+            for (&row, &entrant) in rows.iter().zip(&entrants) {
+                let text = match (round, entrant) {
+                    (0, None) => "bye".to_string(),
+                    (_, entrant) => label(entrant),
+                };
+                put(&mut grid, x, row, &text);
+                for cell in &mut grid[row][x + text.chars().count()..x + width] {
+                    *cell = '─';
+                }
+            }
+            let mut next_rows = Vec::new();
+            let mut next_entrants = Vec::new();
+            for (position, pair) in rows.chunks(2).enumerate() {
+                let (top, bottom) = (pair[0], pair[1]);
+                let middle = (top + bottom) / 2;
+                for (row, line) in grid.iter_mut().enumerate().take(bottom + 1).skip(top) {
+                    line[x + width + 1] = match row {
+                        _ if row == top => '┐',
+                        _ if row == bottom => '┘',
+                        _ if row == middle => '├',
+                        _ => '│',
+                    };
+                }
+                grid[top][x + width] = '─';
+                grid[bottom][x + width] = '─';
+                grid[middle][x + width + 2] = '─';
+                next_rows.push(middle);
+                next_entrants.push(main.get(round).and_then(|fixtures| fixtures.get(position)).and_then(|fixture| fixture.winner()));
+            }
+            rows = next_rows;
+            entrants = next_entrants;
+        }
+        for (&row, &entrant) in rows.iter().zip(&entrants) {
+            put(&mut grid, rounds * column, row, &label(entrant));
+        }
+        grid.into_iter().map(|line| line.into_iter().collect::<String>().trim_end().to_string()).collect()
+    }
+
+    /// The bracket, when there is one, the standings and every round of the
+    /// tournament as text.
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} ({}, {} players{})",
+            self.name, self.format, self.players.len(), if self.is_finished() { ", finished" } else { "" })];
+        if self.format.lives().is_some() {
+            lines.push(String::new());
+            lines.extend(self.bracket_lines());
+        }
+        lines.push(String::new());
+        lines.extend(self.standings_lines());
+        for (number, round) in self.rounds.iter().enumerate() {
+            lines.push(String::new());
+            lines.push(format!("Round {}", number + 1));
+            for (index, fixture) in round.iter().enumerate() {
+                lines.push(format!("  {}.{} {}", number + 1, index + 1, self.describe(fixture)));
+            }
+        }
+        lines
+    }
+}
+
+/// Pairs neighbours, the last player gets a bye when there is an odd number.
+fn pair_up(players: &[usize], bracket: Bracket) -> Vec<Fixture> {
+    players.chunks(2)
+        .map(|pair| Fixture::new(pair[0], pair.get(1).copied(), bracket))
+        .collect()
+}
+
+/// Seed positions in bracket order, so that the best seeds meet as late as
+/// possible: 0, 7, 3, 4, 1, 6, 2, 5 for eight players.
+fn seeding_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let count = order.len() * 2;
+        order = order.iter().flat_map(|&seed| [seed, count - 1 - seed]).collect();
+    }
+    order
+}
+
+/// Every round of a round robin between `count` players, using the circle
+/// method. With an odd number of players someone sits each round out.
+fn round_robin(count: usize) -> Vec<Vec<Fixture>> {
+    let mut circle: Vec<Option<usize>> = (0..count).map(Some).collect();
+    if count % 2 == 1 {
+        circle.push(None);
+    }
+    let size = circle.len();
+    let mut rounds = Vec::new();
+    for _ in 0..size - 1 {
+        let round = (0..size / 2)
+            .filter_map(|i| match (circle[i], circle[size - 1 - i]) {
+                (Some(home), Some(away)) => Some(Fixture::new(home, Some(away), Bracket::Main)),
+                _ => None,
+            })
+            .collect();
+        rounds.push(round);
+        // Everyone but the first player moves one place around the circle.
+        let last = circle.remove(size - 1);
+        circle.insert(1, last);
+    }
+    rounds
+}
+
+/// The tournaments of the leaderboard `board`, oldest first.
+pub fn read_tournaments(storage: &dyn Storage, board: &str) -> Result<Vec<Tournament>, Box<dyn Error>> {
+    match storage.read_attachment(board, "tournaments")? {
+        Some(data) => Ok(serde_json::from_str(&data)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn write_tournaments(storage: &dyn Storage, board: &str, tournaments: &[Tournament]) -> Result<(), Box<dyn Error>> {
+    if tournaments.is_empty() {
+        storage.remove_attachment(board, "tournaments")
+    } else {
+        storage.write_attachment(board, "tournaments", &serde_json::to_string(tournaments)?)
+    }
+}
+
+/// Creates a tournament between the entries of `leaderboard`, seeded by
+/// their ranks. Tournament names are unique per leaderboard.
+pub fn create_tournament(storage: &dyn Storage, leaderboard: &Leaderboard, name: &str, format: TournamentFormat) -> Result<Tournament, Box<dyn Error>> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Tournament name must not be empty".into());
+    }
+    let mut tournaments = read_tournaments(storage, leaderboard.name())?;
+    if tournaments.iter().any(|tournament| tournament.name == name) {
+        return Err(format!("{} already has a tournament named {}", leaderboard.name(), name).into());
+    }
+    let players = leaderboard.entries().iter().map(|node| Player { id: node.id, name: node.name.clone() }).collect();
+    let tournament = Tournament::new(name, format, players)?;
+    tournaments.push(tournament.clone());
+    write_tournaments(storage, leaderboard.name(), &tournaments)?;
+    Ok(tournament)
+}
+
+/// Records a result in the tournament `name` of `board` and returns the
+/// updated tournament.
+pub fn record_result(storage: &dyn Storage, board: &str, name: &str, round: usize, fixture: usize, outcome: Outcome) -> Result<Tournament, Box<dyn Error>> {
+    let mut tournaments = read_tournaments(storage, board)?;
+    let Some(tournament) = tournaments.iter_mut().find(|tournament| tournament.name == name) else {
+        return Err(format!("{} has no tournament named {}", board, name).into());
+    };
+    tournament.record(round, fixture, outcome)?;
+    let updated = tournament.clone();
+    write_tournaments(storage, board, &tournaments)?;
+    Ok(updated)
+}
+
+pub fn remove_tournament(storage: &dyn Storage, board: &str, name: &str) -> Result<(), Box<dyn Error>> {
+    let mut tournaments = read_tournaments(storage, board)?;
+    let before = tournaments.len();
+    tournaments.retain(|tournament| tournament.name != name);
+    if tournaments.len() == before {
+        return Err(format!("{} has no tournament named {}", board, name).into());
+    }
+    write_tournaments(storage, board, &tournaments)
+}
+
+/// Ranks the entries of `target` by the standings of `tournament`. Players
+/// are found by id when `target` is the leaderboard the tournament was
+/// seeded from, by name otherwise, and added when missing. Entries that did
+/// not take part keep their order below the players.
+pub fn write_placements(tournament: &Tournament, target: &mut Leaderboard, seeded_from: bool) -> Result<(), String> {
+    if target.ranking_mode().is_scored() {
+        return Err(format!("Ranks of {} are computed from scores ({})", target.name(), target.ranking_mode()));
+    }
+    let mut placed = Vec::new();
+    for player in tournament.standings().into_iter().map(|player| &tournament.players[player]) {
+        let existing = target.entries().iter()
+            .find(|node| if seeded_from { node.id == player.id } else { node.name == player.name })
+            .map(|node| node.id);
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                target.new_entry(&player.name, target.len() + 1)?;
+                target.entries().last().map(|node| node.id).unwrap_or_default()
+            },
+        };
+        placed.push(id);
+    }
+    let rest: Vec<ID> = target.entries().iter().map(|node| node.id).filter(|id| !placed.contains(id)).collect();
+    placed.extend(rest);
+    target.reorder(&placed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(format: TournamentFormat, count: usize) -> Tournament {
+        let players = (0..count).map(|seed| Player { id: seed + 1, name: format!("P{}", seed + 1) }).collect();
+        Tournament::new("test", format, players).unwrap()
+    }
+
+    fn pairs(round: &[Fixture]) -> Vec<(usize, Option<usize>)> {
+        round.iter().map(|fixture| (fixture.home, fixture.away)).collect()
+    }
+
+    /// Lets the home player win every game of the last round.
+    fn play_round(tournament: &mut Tournament) {
+        let round = tournament.rounds.len() - 1;
+        let unplayed: Vec<usize> = (0..tournament.rounds[round].len())
+            .filter(|&fixture| tournament.rounds[round][fixture].result.is_none())
+            .collect();
+        for fixture in unplayed {
+            tournament.record(round, fixture, Outcome::FirstWins).unwrap();
+        }
+    }
+
+    #[test]
+    fn seeding_order_keeps_the_best_seeds_apart() {
+        assert_eq!(seeding_order(1), [0]);
+        assert_eq!(seeding_order(2), [0, 1]);
+        assert_eq!(seeding_order(4), [0, 3, 1, 2]);
+        assert_eq!(seeding_order(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+        for size in [2, 4, 8, 16, 32] {
+            let order = seeding_order(size);
+            assert!(order.chunks(2).all(|pair| pair[0] + pair[1] == size - 1));
+        }
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        for count in 2..=9 {
+            let rounds = round_robin(count);
+            assert_eq!(rounds.len(), count + count % 2 - 1, "{} players", count);
+            let mut met = HashSet::new();
+            for round in &rounds {
+                let mut playing = HashSet::new();
+                for fixture in round {
+                    let away = fixture.away.unwrap();
+                    assert!(playing.insert(fixture.home) && playing.insert(away));
+                    assert!(met.insert((fixture.home.min(away), fixture.home.max(away))));
+                }
+                assert_eq!(playing.len(), count - count % 2);
+            }
+            assert_eq!(met.len(), count * (count - 1) / 2);
+        }
+    }
+
+    #[test]
+    fn single_elimination_gives_the_top_seeds_byes() {
+        let mut five = tournament(TournamentFormat::SingleElimination, 5);
+        assert_eq!(pairs(&five.rounds[0]), [(0, None), (3, Some(4)), (1, None), (2, None)]);
+        play_round(&mut five);
+        assert_eq!(pairs(&five.rounds[1]), [(0, Some(3)), (1, Some(2))]);
+        while !five.is_finished() {
+            play_round(&mut five);
+        }
+        assert_eq!(five.rounds.len(), 3);
+        assert_eq!(five.standings()[0], 0);
+
+        let eight = tournament(TournamentFormat::SingleElimination, 8);
+        assert_eq!(pairs(&eight.rounds[0]), [(0, Some(7)), (3, Some(4)), (1, Some(6)), (2, Some(5))]);
+    }
+
+    #[test]
+    fn double_elimination_drops_losers_into_the_losers_bracket() {
+        let mut four = tournament(TournamentFormat::DoubleElimination, 4);
+        assert_eq!(pairs(&four.rounds[0]), [(0, Some(3)), (1, Some(2))]);
+        play_round(&mut four);
+        let brackets: Vec<Bracket> = four.rounds[1].iter().map(|fixture| fixture.bracket).collect();
+        assert_eq!(pairs(&four.rounds[1]), [(0, Some(1)), (3, Some(2))]);
+        assert_eq!(brackets, [Bracket::Main, Bracket::Losers]);
+        play_round(&mut four);
+        // The loser of the winners bracket final meets the losers bracket's
+        // last player.
+        assert_eq!(pairs(&four.rounds[2]), [(3, Some(1))]);
+        assert_eq!(four.rounds[2][0].bracket, Bracket::Losers);
+        play_round(&mut four);
+        assert_eq!(pairs(&four.rounds[3]), [(0, Some(3))]);
+        assert_eq!(four.rounds[3][0].bracket, Bracket::Final);
+        play_round(&mut four);
+        assert!(four.is_finished());
+        assert_eq!(four.standings(), [0, 3, 1, 2]);
+        assert!(four.record(3, 0, Outcome::Draw).is_err());
+    }
+
+    #[test]
+    fn double_elimination_with_byes_finishes() {
+        for count in [3, 5, 6] {
+            let mut tournament = tournament(TournamentFormat::DoubleElimination, count);
+            while !tournament.is_finished() {
+                play_round(&mut tournament);
+            }
+            let games = tournament.rounds.iter().flatten().filter(|fixture| !fixture.is_bye()).count();
+            // Everyone but the winner loses twice.
+            assert_eq!(games, 2 * (count - 1), "{} players", count);
+            assert_eq!(tournament.standings()[0], 0);
+        }
+    }
+
+    #[test]
+    fn swiss_pairs_players_on_the_same_points() {
+        let mut six = tournament(TournamentFormat::Swiss, 6);
+        assert_eq!(pairs(&six.rounds[0]), [(0, Some(3)), (1, Some(4)), (2, Some(5))]);
+        play_round(&mut six);
+        // The winners 0, 1 and 2 meet, the odd one out meets the best loser.
+        assert_eq!(pairs(&six.rounds[1]), [(0, Some(1)), (2, Some(3)), (4, Some(5))]);
+        play_round(&mut six);
+        assert_eq!(pairs(&six.rounds[2])[..2], [(0, Some(2)), (1, Some(3))]);
+        play_round(&mut six);
+        assert!(six.is_finished());
+        assert_eq!(six.rounds.len(), 3);
+    }
+
+    #[test]
+    fn swiss_gives_each_player_at_most_one_bye() {
+        let mut five = tournament(TournamentFormat::Swiss, 5);
+        assert_eq!(pairs(&five.rounds[0]), [(0, Some(3)), (1, Some(4)), (2, None)]);
+        play_round(&mut five);
+        // Byes count as wins, so 2 ranks with 0 and 1 and the bye goes to
+        // the lowest placed player.
+        assert_eq!(five.records()[2].points(), WIN_POINTS);
+        assert_eq!(pairs(&five.rounds[1]), [(0, Some(1)), (2, Some(3)), (4, None)]);
+        while !five.is_finished() {
+            play_round(&mut five);
+        }
+        let byes: Vec<usize> = five.rounds.iter().flatten().filter(|fixture| fixture.is_bye()).map(|fixture| fixture.home).collect();
+        assert_eq!(byes.len(), 3);
+        assert_eq!(byes.iter().collect::<HashSet<_>>().len(), 3);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::Leaderboard;
+use crate::node::Node;
+use crate::storage::{Storage, ATTACHMENT_KINDS};
+
+/// A deleted leaderboard, kept with its attachments until it is restored or
+/// purged.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashedBoard {
+    pub name: String,
+    /// Unix time of the deletion.
+    pub deleted_at: i64,
+    board: serde_json::Value,
+    attachments: HashMap<String, String>,
+}
+
+/// A deleted entry, kept in the leaderboard it was deleted from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tombstone {
+    /// The entry as it was when deleted, holding its last rank.
+    pub node: Node,
+    /// Unix time of the deletion.
+    pub deleted_at: i64,
+}
+
+pub fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+/// Unix time `days` days ago, everything deleted before it has expired.
+pub fn expiry_cutoff(days: u32) -> i64 {
+    now() - i64::from(days) * 24 * 60 * 60
+}
+
+/// A deletion time in local time, for display.
+pub fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Moves the leaderboard `name` and its attachments to the trash. The index
+/// is left to the caller.
+pub fn trash_leaderboard(storage: &dyn Storage, name: &str) -> Result<(), Box<dyn Error>> {
+    let board = serde_json::to_value(storage.read_leaderboard(name)?)?;
+    let mut attachments = HashMap::new();
+    for kind in ATTACHMENT_KINDS {
+        if let Some(data) = storage.read_attachment(name, kind)? {
+            attachments.insert(kind.to_string(), data);
+        }
+    }
+    let mut trash = storage.read_trash()?;
+    trash.push(TrashedBoard { name: name.to_string(), deleted_at: now(), board, attachments });
+    storage.write_trash(&trash)?;
+    storage.remove_leaderboard(name)
+}
+
+/// Puts the trashed leaderboard at `position` back into the store and
+/// returns its name. The index is left to the caller.
+pub fn restore_leaderboard(storage: &dyn Storage, position: usize) -> Result<String, Box<dyn Error>> {
+    let mut trash = storage.read_trash()?;
+    if position >= trash.len() {
+        return Err(format!("Nothing in the trash at {}", position).into());
+    }
+    if storage.read_leaderboard(&trash[position].name).is_ok() {
+        return Err(format!("Leaderboard named {} already exists!", trash[position].name).into());
+    }
+    let trashed = trash.remove(position);
+    let leaderboard: Leaderboard = serde_json::from_value(trashed.board)?;
+    storage.write_leaderboard(&leaderboard)?;
+    for (kind, data) in &trashed.attachments {
+        storage.write_attachment(&trashed.name, kind, data)?;
+    }
+    storage.write_trash(&trash)?;
+    Ok(trashed.name)
+}
+
+/// Deletes the trashed leaderboard at `position` for good.
+pub fn purge_leaderboard(storage: &dyn Storage, position: usize) -> Result<(), Box<dyn Error>> {
+    let mut trash = storage.read_trash()?;
+    if position >= trash.len() {
+        return Err(format!("Nothing in the trash at {}", position).into());
+    }
+    trash.remove(position);
+    storage.write_trash(&trash)
+}
+
+/// Deletes every leaderboard trashed before `cutoff` for good, returning how
+/// many there were.
+pub fn purge_expired(storage: &dyn Storage, cutoff: i64) -> Result<usize, Box<dyn Error>> {
+    let mut trash = storage.read_trash()?;
+    let before = trash.len();
+    trash.retain(|trashed| trashed.deleted_at >= cutoff);
+    if trash.len() != before {
+        storage.write_trash(&trash)?;
+    }
+    Ok(before - trash.len())
+}